@@ -0,0 +1,26 @@
+//! What the rotary encoder reports, the pins are read by the firmware
+
+use std::fmt::{Display, Formatter};
+
+#[derive(Clone, Copy, PartialEq, PartialOrd, Eq, Ord, Debug, Hash)]
+pub enum SpinDirection {
+    CounterClockwise,
+    Clockwise,
+}
+pub type EncoderInfo = (i32, SpinDirection);
+
+/// Sent when the rotary encoder button is released
+#[derive(Clone, Copy, PartialEq, PartialOrd, Eq, Ord, Debug, Hash)]
+pub enum ButtonPress {
+    Short,
+    Long,
+}
+
+impl Display for SpinDirection {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SpinDirection::CounterClockwise => f.write_str("(SD:Counter Clockwise)"),
+            SpinDirection::Clockwise => f.write_str("(SD:Clockwise)"),
+        }
+    }
+}
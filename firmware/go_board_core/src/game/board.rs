@@ -0,0 +1,338 @@
+use super::zobrist::MAX_BOARD_SIDE;
+use anyhow::{anyhow, bail, Result};
+use std::fmt::{Display, Formatter};
+
+/// A stone on the board, the same values online-go uses in `BoardState::board`
+/// (`1` is black, `2` is white, `0` is an empty point)
+#[derive(Clone, Copy, PartialEq, PartialOrd, Eq, Ord, Debug, Hash)]
+pub enum Stone {
+    Black = 1,
+    White = 2,
+}
+
+impl Stone {
+    pub fn opponent(&self) -> Self {
+        match self {
+            Stone::Black => Stone::White,
+            Stone::White => Stone::Black,
+        }
+    }
+
+    /// Converts a raw online-go board value, `0` is an empty point
+    pub fn from_raw(value: i32) -> Result<Option<Self>> {
+        match value {
+            0 => Ok(None),
+            1 => Ok(Some(Stone::Black)),
+            2 => Ok(Some(Stone::White)),
+            _ => Err(anyhow!("Failed to convert board value to stone! {value}")),
+        }
+    }
+
+    pub fn to_raw(point: Option<Self>) -> i32 {
+        point.map(|stone| stone as i32).unwrap_or(0)
+    }
+}
+
+impl Display for Stone {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Stone::Black => f.write_str("B"),
+            Stone::White => f.write_str("W"),
+        }
+    }
+}
+
+/// A point on the board, `x` is the outer index of `BoardState::board` and `y` the inner one,
+/// the same way `BoardState::board_iter` and the led grid address points
+#[derive(Clone, Copy, PartialEq, PartialOrd, Eq, Ord, Debug, Hash)]
+pub struct Point {
+    pub x: u8,
+    pub y: u8,
+}
+
+impl Point {
+    pub const fn new(x: u8, y: u8) -> Self {
+        Self { x, y }
+    }
+
+    /// The point the way players write it, like `D4`: the column as a letter, skipping I,
+    /// and the row counted up from the bottom of a board `height` rows high
+    pub fn go_coordinate(&self, height: usize) -> String {
+        let column = GO_COLUMNS
+            .get(self.y as usize)
+            .map_or('?', |&letter| letter as char);
+        format!("{column}{}", height.saturating_sub(self.x as usize))
+    }
+}
+
+/// column letters, I is left out so it can't be mistaken for J or 1
+const GO_COLUMNS: &[u8] = b"ABCDEFGHJKLMNOPQRSTUVWXYZ";
+
+impl Display for Point {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let Point { x, y } = self;
+        write!(f, "({x},{y})")
+    }
+}
+
+/// A plain go board, has no knowledge of whose turn it is or of the rules,
+/// see [`crate::game::rules::Position`] for that
+#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+pub struct Board {
+    /// number of points along x (the number of rows in `BoardState::board`)
+    height: usize,
+    /// number of points along y (the length of each row in `BoardState::board`)
+    width: usize,
+    points: Vec<Option<Stone>>,
+}
+
+impl Board {
+    /// Fails for sides longer than [`MAX_BOARD_SIDE`], the zobrist keys only cover boards that big
+    pub fn new(height: usize, width: usize) -> Result<Self> {
+        if height > MAX_BOARD_SIDE || width > MAX_BOARD_SIDE {
+            bail!("A {height}x{width} board is larger than {MAX_BOARD_SIDE}x{MAX_BOARD_SIDE}");
+        }
+        Ok(Self {
+            height,
+            width,
+            points: vec![None; height * width],
+        })
+    }
+
+    /// Creates a board from the raw online-go representation (`board[x][y]`)
+    pub fn from_rows(rows: &[Vec<i32>]) -> Result<Self> {
+        let height = rows.len();
+        let width = rows.first().map(|row| row.len()).unwrap_or(0);
+        let mut board = Self::new(height, width)?;
+        for (x, row) in rows.iter().enumerate() {
+            if row.len() != width {
+                return Err(anyhow!(
+                    "Board row {x} has {} points, expected {width}",
+                    row.len()
+                ));
+            }
+            for (y, value) in row.iter().enumerate() {
+                board.set(Point::new(x as u8, y as u8), Stone::from_raw(*value)?);
+            }
+        }
+        Ok(board)
+    }
+
+    /// Converts back into the raw online-go representation (`board[x][y]`)
+    pub fn to_rows(&self) -> Vec<Vec<i32>> {
+        (0..self.height)
+            .map(|x| {
+                (0..self.width)
+                    .map(|y| Stone::to_raw(self.get(Point::new(x as u8, y as u8))))
+                    .collect()
+            })
+            .collect()
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn contains(&self, point: Point) -> bool {
+        (point.x as usize) < self.height && (point.y as usize) < self.width
+    }
+
+    fn index(&self, point: Point) -> usize {
+        point.x as usize * self.width + point.y as usize
+    }
+
+    /// Panics if the point is not on the board, check with [`Board::contains`] first
+    pub fn get(&self, point: Point) -> Option<Stone> {
+        self.points[self.index(point)]
+    }
+
+    /// Panics if the point is not on the board, check with [`Board::contains`] first
+    pub fn set(&mut self, point: Point, stone: Option<Stone>) {
+        let index = self.index(point);
+        self.points[index] = stone;
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Point, Option<Stone>)> + '_ {
+        self.points.iter().enumerate().map(|(index, stone)| {
+            (
+                Point::new((index / self.width) as u8, (index % self.width) as u8),
+                *stone,
+            )
+        })
+    }
+
+    /// The up to 4 points orthogonally next to `point` that are on the board
+    pub fn neighbors(&self, point: Point) -> impl Iterator<Item = Point> {
+        let Point { x, y } = point;
+        let (height, width) = (self.height as u8, self.width as u8);
+        [
+            (x > 0).then(|| Point::new(x - 1, y)),
+            (x + 1 < height).then(|| Point::new(x + 1, y)),
+            (y > 0).then(|| Point::new(x, y - 1)),
+            (y + 1 < width).then(|| Point::new(x, y + 1)),
+        ]
+        .into_iter()
+        .flatten()
+    }
+
+    /// Flood fills from `point` across every connected point with the same contents,
+    /// works for both groups of stones and empty regions
+    pub fn chain(&self, point: Point) -> Vec<Point> {
+        let contents = self.get(point);
+        let mut visited = vec![false; self.points.len()];
+        let mut chain = Vec::new();
+        let mut stack = vec![point];
+        visited[self.index(point)] = true;
+        while let Some(current) = stack.pop() {
+            chain.push(current);
+            for neighbor in self.neighbors(current) {
+                let index = self.index(neighbor);
+                if !visited[index] && self.get(neighbor) == contents {
+                    visited[index] = true;
+                    stack.push(neighbor);
+                }
+            }
+        }
+        chain
+    }
+
+    /// The group of stones at `point` and its number of liberties,
+    /// `None` if the point is empty
+    pub fn group(&self, point: Point) -> Option<Group> {
+        self.get(point)?;
+        let stones = self.chain(point);
+        let mut liberties: Vec<Point> = stones
+            .iter()
+            .flat_map(|stone| self.neighbors(*stone))
+            .filter(|neighbor| self.get(*neighbor).is_none())
+            .collect();
+        liberties.sort_unstable();
+        liberties.dedup();
+        Some(Group { stones, liberties })
+    }
+
+    /// The hoshi (star points) usually marked on a board of this size: the 3-3 points
+    /// (4-4 from 13x13 up), the center of odd sized boards and the side stars from 15x15 up
+    pub fn star_points(&self) -> Vec<Point> {
+        let (rows, columns) = (star_lines(self.height), star_lines(self.width));
+        let mut points = Vec::new();
+        for x in &rows.corners {
+            for y in &columns.corners {
+                points.push(Point::new(*x, *y));
+            }
+        }
+        if let (Some(x), Some(y)) = (rows.center, columns.center) {
+            points.push(Point::new(x, y));
+            if self.height >= 15 && self.width >= 15 {
+                for corner in &rows.corners {
+                    points.push(Point::new(*corner, y));
+                }
+                for corner in &columns.corners {
+                    points.push(Point::new(x, *corner));
+                }
+            }
+        }
+        points
+    }
+}
+
+/// The lines star points sit on along one side of the board
+struct StarLines {
+    corners: Vec<u8>,
+    center: Option<u8>,
+}
+
+fn star_lines(side: usize) -> StarLines {
+    if side < 7 {
+        return StarLines {
+            corners: Vec::new(),
+            center: None,
+        };
+    }
+    let from_edge = if side < 13 { 2 } else { 3 };
+    StarLines {
+        corners: vec![from_edge as u8, (side - 1 - from_edge) as u8],
+        center: (side % 2 == 1 && side >= 9).then_some((side / 2) as u8),
+    }
+}
+
+/// A connected group of stones of the same color
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Group {
+    pub stones: Vec<Point>,
+    pub liberties: Vec<Point>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rows_round_trip() {
+        let rows = vec![vec![0, 1, 2], vec![2, 0, 0]];
+        let board = Board::from_rows(&rows).unwrap();
+        assert_eq!((board.height(), board.width()), (2, 3));
+        assert_eq!(board.get(Point::new(0, 1)), Some(Stone::Black));
+        assert_eq!(board.get(Point::new(1, 0)), Some(Stone::White));
+        assert_eq!(board.to_rows(), rows);
+    }
+
+    #[test]
+    fn bad_rows_are_refused() {
+        assert!(Board::from_rows(&[vec![0, 0], vec![0]]).is_err());
+        assert!(Board::from_rows(&[vec![0, 3]]).is_err());
+        assert!(Board::from_rows(&vec![vec![0; 2]; 26]).is_err());
+    }
+
+    #[test]
+    fn boards_past_the_zobrist_keys_are_refused() {
+        assert!(Board::new(25, 25).is_ok());
+        assert!(Board::new(26, 19).is_err());
+        assert!(Board::new(19, 26).is_err());
+    }
+
+    #[test]
+    fn corners_have_two_neighbors() {
+        let board = Board::new(9, 9).unwrap();
+        assert_eq!(board.neighbors(Point::new(0, 0)).count(), 2);
+        assert_eq!(board.neighbors(Point::new(8, 4)).count(), 3);
+        assert_eq!(board.neighbors(Point::new(4, 4)).count(), 4);
+    }
+
+    #[test]
+    fn groups_count_shared_liberties_once() {
+        let board = Board::from_rows(&[vec![1, 1, 0], vec![0, 1, 2], vec![0, 0, 0]]).unwrap();
+        let group = board.group(Point::new(0, 0)).unwrap();
+        assert_eq!(group.stones.len(), 3);
+        assert_eq!(
+            group.liberties,
+            vec![Point::new(0, 2), Point::new(1, 0), Point::new(2, 1)]
+        );
+        assert_eq!(board.group(Point::new(2, 2)), None);
+        // empty regions flood fill too
+        assert_eq!(board.chain(Point::new(2, 2)).len(), 4);
+    }
+
+    #[test]
+    fn star_points_depend_on_the_size() {
+        assert!(Board::new(5, 5).unwrap().star_points().is_empty());
+        assert_eq!(Board::new(9, 9).unwrap().star_points().len(), 5);
+        assert_eq!(Board::new(13, 13).unwrap().star_points().len(), 5);
+        assert_eq!(Board::new(19, 19).unwrap().star_points().len(), 9);
+        assert!(Board::new(19, 19)
+            .unwrap()
+            .star_points()
+            .contains(&Point::new(3, 15)));
+    }
+
+    #[test]
+    fn go_coordinates_skip_i() {
+        assert_eq!(Point::new(18, 0).go_coordinate(19), "A1");
+        assert_eq!(Point::new(0, 8).go_coordinate(19), "J19");
+        assert_eq!(Point::new(4, 4).go_coordinate(9), "E5");
+    }
+}
@@ -0,0 +1,9 @@
+pub mod board;
+pub mod move_entry;
+pub mod picker;
+pub mod removal;
+pub mod replay;
+pub mod rules;
+pub mod scoring;
+pub mod sgf;
+pub mod zobrist;
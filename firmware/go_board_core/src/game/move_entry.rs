@@ -0,0 +1,633 @@
+use super::board::{Point, Stone};
+use super::rules::{IllegalMove, Move};
+use super::scoring::{score, Score, ScoringRules};
+use super::zobrist::PositionHistory;
+use crate::encoder::{ButtonPress, EncoderInfo, SpinDirection};
+use crate::neopixel::frame::LedDisplay;
+use crate::neopixel::go_board::{
+    fade_board_layers, last_move_color, render_board, render_points, render_screen,
+    show_board_layers, stone_color,
+};
+use crate::neopixel::led_ctrl::{DisplayOnLeds, LedChange};
+use crate::neopixel::led_font::score_board;
+use crate::neopixel::rgb::{Rgb, BLUE, ORANGE, RED};
+use crate::neopixel::viewport::Viewport;
+use crate::BOARD_SIZE;
+use anyhow::Result;
+use std::fmt::{Display, Formatter};
+use std::time::Duration;
+
+const CURSOR: Rgb = ORANGE;
+/// the edge of the matrix while a pass is selected
+const PASS_BORDER: Rgb = BLUE;
+/// and while resigning is
+const RESIGN_BORDER: Rgb = RED;
+const INVALID: Rgb = RED;
+
+/// how long the score is shown before switching to the last move and whose turn it is
+const SCORE_TIMEOUT: Duration = Duration::from_secs(5);
+/// how long an invalid move is shown before going back to the board
+const INVALID_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Why a selected move was not submitted
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Rejection {
+    NotYourTurn,
+    Illegal(IllegalMove),
+    /// the move passed the local checks but online-go refused it
+    Refused,
+}
+
+impl Display for Rejection {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Rejection::NotYourTurn => f.write_str("it is not your turn"),
+            Rejection::Illegal(illegal) => write!(f, "{illegal}"),
+            Rejection::Refused => f.write_str("online-go refused the move"),
+        }
+    }
+}
+
+/// What the player confirmed, a move or resigning to send to online-go or leaving the game
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Submission {
+    Play(Move),
+    Resign,
+    /// back to the game list, nothing is sent
+    Leave,
+}
+
+/// Inputs of the move entry state machine
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Input {
+    Spin(EncoderInfo),
+    Press(ButtonPress),
+    /// the duration given by [`MoveEntry::timeout`] passed without any other input
+    Timeout,
+}
+
+/// The states of the active game flowchart in the README
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum State {
+    /// rotating moves the cursor, a press selects the point under it
+    Board,
+    /// the point under the cursor is selected, a press plays it, rotating switches to a pass
+    Selected,
+    /// a pass is selected, a press passes, rotating switches to resigning
+    PassSelected,
+    /// resigning is selected, a long press resigns, rotating switches back to the point
+    ResignSelected,
+    /// the selected move was refused, shown until the timeout
+    Invalid(Rejection),
+    /// after a long press, shown until the timeout or another long press
+    Score,
+    /// after the score, a press goes back to the board and a long press leaves the game
+    LastMove,
+}
+
+/// Entering moves with the rotary encoder during an active game
+///
+/// Moves are checked against the local rules engine before being handed out for submission,
+/// the position is only advanced with [`MoveEntry::play`] once the server accepted it
+pub struct MoveEntry {
+    history: PositionHistory,
+    /// the color of the player using the board
+    player: Stone,
+    last_move: Option<Point>,
+    komi: f32,
+    scoring: ScoringRules,
+    cursor: Point,
+    state: State,
+    /// follows the cursor on boards larger than the matrix
+    viewport: Viewport,
+}
+
+impl MoveEntry {
+    pub fn new(
+        history: PositionHistory,
+        player: Stone,
+        last_move: Option<Point>,
+        komi: f32,
+        scoring: ScoringRules,
+    ) -> Self {
+        let board = &history.position().board;
+        let mut viewport = Viewport::new(board);
+        // start the cursor next to the last move, or in the middle of an empty board
+        let cursor = last_move.unwrap_or(Point::new(
+            (board.height() / 2) as u8,
+            (board.width() / 2) as u8,
+        ));
+        viewport.follow(cursor);
+        Self {
+            history,
+            player,
+            last_move,
+            komi,
+            scoring,
+            cursor,
+            state: State::Board,
+            viewport,
+        }
+    }
+
+    pub fn history(&self) -> &PositionHistory {
+        &self.history
+    }
+
+    pub fn is_players_turn(&self) -> bool {
+        self.history.position().to_move == self.player
+    }
+
+    pub fn cursor(&self) -> Point {
+        self.cursor
+    }
+
+    /// Advances the game with a move accepted by the server (ours or the opponent's)
+    pub fn play(&mut self, mv: Move) -> Result<(), IllegalMove> {
+        self.history.play(mv)?;
+        self.last_move = match mv {
+            Move::Place(point) => Some(point),
+            Move::Pass => None,
+        };
+        // a move played by the opponent while a move was selected cancels the selection
+        if matches!(
+            self.state,
+            State::Selected | State::PassSelected | State::ResignSelected
+        ) {
+            self.state = State::Board;
+        }
+        Ok(())
+    }
+
+    /// The current score estimate, no stones are considered dead
+    pub fn score(&self) -> Score {
+        let position = self.history.position();
+        score(
+            &position.board,
+            &[],
+            position.black_captures,
+            position.white_captures,
+            self.komi,
+            self.scoring,
+        )
+    }
+
+    /// How long to wait for input before sending [`Input::Timeout`], `None` waits forever
+    pub fn timeout(&self) -> Option<Duration> {
+        match self.state {
+            State::Score => Some(SCORE_TIMEOUT),
+            State::Invalid(_) => Some(INVALID_TIMEOUT),
+            _ => None,
+        }
+    }
+
+    /// Returns what to submit, if the input confirmed a move, resigning or leaving
+    pub fn handle(&mut self, input: Input) -> Option<Submission> {
+        match (self.state, input) {
+            (State::Board, Input::Spin((_, direction))) => {
+                self.move_cursor(direction);
+                None
+            }
+            (State::Board, Input::Press(ButtonPress::Short)) => {
+                self.state = if self.is_players_turn() {
+                    State::Selected
+                } else {
+                    State::Invalid(Rejection::NotYourTurn)
+                };
+                None
+            }
+            (State::Board, Input::Press(ButtonPress::Long)) => {
+                self.state = State::Score;
+                None
+            }
+            (State::Selected, Input::Spin(_)) => {
+                self.state = State::PassSelected;
+                None
+            }
+            (State::PassSelected, Input::Spin(_)) => {
+                self.state = State::ResignSelected;
+                None
+            }
+            (State::ResignSelected, Input::Spin(_)) => {
+                self.state = State::Selected;
+                None
+            }
+            (State::Selected, Input::Press(ButtonPress::Short)) => {
+                self.confirm(Move::Place(self.cursor))
+            }
+            (State::PassSelected, Input::Press(ButtonPress::Short)) => self.confirm(Move::Pass),
+            // resigning can't be taken back, so it needs the long press
+            (State::ResignSelected, Input::Press(ButtonPress::Long)) => {
+                self.state = State::Board;
+                Some(Submission::Resign)
+            }
+            // the other press backs out of the selection
+            (State::Selected | State::PassSelected, Input::Press(ButtonPress::Long))
+            | (State::ResignSelected, Input::Press(ButtonPress::Short)) => {
+                self.state = State::Board;
+                None
+            }
+            (State::Invalid(_), Input::Timeout | Input::Press(_)) => {
+                self.state = State::Board;
+                None
+            }
+            (State::Score, Input::Timeout) => {
+                self.state = State::LastMove;
+                None
+            }
+            (State::Score, Input::Press(ButtonPress::Long))
+            | (State::LastMove, Input::Press(ButtonPress::Short)) => {
+                self.state = State::Board;
+                None
+            }
+            (State::LastMove, Input::Press(ButtonPress::Long)) => {
+                self.state = State::Board;
+                Some(Submission::Leave)
+            }
+            _ => None,
+        }
+    }
+
+    fn confirm(&mut self, mv: Move) -> Option<Submission> {
+        if !self.is_players_turn() {
+            self.state = State::Invalid(Rejection::NotYourTurn);
+            return None;
+        }
+        match self.history.check(mv) {
+            Ok(_) => {
+                self.state = State::Board;
+                Some(Submission::Play(mv))
+            }
+            Err(illegal) => {
+                self.state = State::Invalid(Rejection::Illegal(illegal));
+                None
+            }
+        }
+    }
+
+    /// Steps through the empty points in reading order, wrapping around the board
+    fn move_cursor(&mut self, direction: SpinDirection) {
+        let board = &self.history.position().board;
+        let points = board.height() * board.width();
+        let index = |point: Point| point.x as usize * board.width() + point.y as usize;
+        let point_at =
+            |index: usize| Point::new((index / board.width()) as u8, (index % board.width()) as u8);
+        let mut index_now = index(self.cursor);
+        for _ in 0..points {
+            index_now = match direction {
+                SpinDirection::Clockwise => (index_now + 1) % points,
+                SpinDirection::CounterClockwise => (index_now + points - 1) % points,
+            };
+            if board.get(point_at(index_now)).is_none() {
+                self.cursor = point_at(index_now);
+                self.viewport.follow(self.cursor);
+                return;
+            }
+        }
+    }
+
+    /// Shows a submitted move as invalid, ie when the server refused it
+    pub fn refuse(&mut self, rejection: Rejection) {
+        self.state = State::Invalid(rejection);
+    }
+
+    /// Why the last selected move was refused, while it is being shown
+    pub fn rejection(&self) -> Option<Rejection> {
+        match self.state {
+            State::Invalid(rejection) => Some(rejection),
+            _ => None,
+        }
+    }
+
+    /// The board layer: the stones, or the score
+    pub fn render(&self) -> Vec<LedChange> {
+        let position = self.history.position();
+        match self.state {
+            State::Score => render_screen(score_board(&self.score())),
+            State::LastMove => {
+                // the corners of the matrix show whose turn it is
+                let turn = stone_color(Some(position.to_move));
+                let last = BOARD_SIZE as u8 - 1;
+                let mut changes = render_board(&position.board, &self.viewport);
+                for change in changes.iter_mut() {
+                    if (change.x == 0 || change.x == last) && (change.y == 0 || change.y == last) {
+                        change.color = turn;
+                    }
+                }
+                changes
+            }
+            State::PassSelected => self.render_border(PASS_BORDER),
+            State::ResignSelected => self.render_border(RESIGN_BORDER),
+            State::Board | State::Selected | State::Invalid(_) => {
+                render_board(&position.board, &self.viewport)
+            }
+        }
+    }
+
+    /// The last move layer: the stone played last drawn brighter, while the board is shown
+    pub fn render_last_move(&self) -> Vec<LedChange> {
+        let board = &self.history.position().board;
+        let last_move = self
+            .last_move
+            .and_then(|point| Some((point, board.get(point)?)));
+        match (self.state, last_move) {
+            (
+                State::Board | State::Selected | State::Invalid(_) | State::LastMove,
+                Some((point, stone)),
+            ) => render_points(&self.viewport, [point], last_move_color(stone)),
+            _ => Vec::new(),
+        }
+    }
+
+    /// The cursor layer: the point under the cursor, in the color of what is happening to it
+    pub fn render_cursor(&self) -> Vec<LedChange> {
+        let color = match self.state {
+            State::Board => CURSOR,
+            State::Selected => last_move_color(self.player),
+            State::Invalid(_) => INVALID,
+            _ => return Vec::new(),
+        };
+        render_points(&self.viewport, [self.cursor], color)
+    }
+
+    /// The board with the edge of the matrix drawn in `color`
+    fn render_border(&self, color: Rgb) -> Vec<LedChange> {
+        let last = BOARD_SIZE as u8 - 1;
+        let mut changes = render_board(&self.history.position().board, &self.viewport);
+        for change in changes.iter_mut() {
+            if change.x == 0 || change.y == 0 || change.x == last || change.y == last {
+                change.color = color;
+            }
+        }
+        changes
+    }
+}
+
+impl MoveEntry {
+    /// Like [`DisplayOnLeds::display`] but fading the board, after [`MoveEntry::play`]
+    pub async fn display_move(&self, display: &LedDisplay) -> Result<()> {
+        fade_board_layers(
+            display,
+            self.render(),
+            self.render_last_move(),
+            self.render_cursor(),
+        )
+        .await
+    }
+}
+
+impl DisplayOnLeds for MoveEntry {
+    async fn display(&self, display: &LedDisplay) -> Result<()> {
+        show_board_layers(
+            display,
+            self.render(),
+            self.render_last_move(),
+            self.render_cursor(),
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::rules::tests::from_picture;
+    use crate::game::rules::Position;
+    use crate::game::zobrist::KoRule;
+
+    fn entry(rows: &[&str], to_move: Stone, player: Stone) -> MoveEntry {
+        let history = PositionHistory::new(from_picture(rows, to_move), KoRule::Simple);
+        MoveEntry::new(history, player, None, 6.5, ScoringRules::Area)
+    }
+
+    fn spin(entry: &mut MoveEntry, direction: SpinDirection) -> Option<Submission> {
+        entry.handle(Input::Spin((0, direction)))
+    }
+
+    fn press(entry: &mut MoveEntry, press: ButtonPress) -> Option<Submission> {
+        entry.handle(Input::Press(press))
+    }
+
+    /// Spins clockwise until the cursor is on `point`
+    fn move_to(entry: &mut MoveEntry, point: Point) {
+        for _ in 0..BOARD_SIZE * BOARD_SIZE {
+            if entry.cursor() == point {
+                return;
+            }
+            spin(entry, SpinDirection::Clockwise);
+        }
+        panic!("the cursor never reached {point}");
+    }
+
+    /// Selects the point under the cursor and confirms it
+    fn play_cursor(entry: &mut MoveEntry) -> Option<Submission> {
+        assert_eq!(press(entry, ButtonPress::Short), None);
+        press(entry, ButtonPress::Short)
+    }
+
+    #[test]
+    fn cursor_skips_stones_and_wraps() {
+        let mut entry = entry(&["X..", "...", "..O"], Stone::Black, Stone::Black);
+        // an empty board starts in the middle
+        assert_eq!(entry.cursor(), Point::new(1, 1));
+        let mut visited = Vec::new();
+        for _ in 0..4 {
+            spin(&mut entry, SpinDirection::Clockwise);
+            visited.push(entry.cursor());
+        }
+        assert_eq!(
+            visited,
+            [(1, 2), (2, 0), (2, 1), (0, 1)].map(|(x, y)| Point::new(x, y))
+        );
+        spin(&mut entry, SpinDirection::CounterClockwise);
+        assert_eq!(entry.cursor(), Point::new(2, 1));
+    }
+
+    #[test]
+    fn cursor_starts_on_the_last_move() {
+        let history = PositionHistory::new(Position::empty(9, 9).unwrap(), KoRule::Simple);
+        let last_move = Point::new(2, 6);
+        let entry = MoveEntry::new(
+            history,
+            Stone::Black,
+            Some(last_move),
+            6.5,
+            ScoringRules::Area,
+        );
+        assert_eq!(entry.cursor(), last_move);
+    }
+
+    #[test]
+    fn confirmed_moves_are_submitted() {
+        let mut entry = entry(&["...", "...", "..."], Stone::Black, Stone::Black);
+        let point = entry.cursor();
+        assert_eq!(
+            play_cursor(&mut entry),
+            Some(Submission::Play(Move::Place(point)))
+        );
+        // nothing is played until the server accepts it
+        assert_eq!(entry.history().position().board.get(point), None);
+        entry.play(Move::Place(point)).unwrap();
+        assert_eq!(
+            entry.history().position().board.get(point),
+            Some(Stone::Black)
+        );
+        assert!(!entry.is_players_turn());
+    }
+
+    #[test]
+    fn long_press_backs_out_of_the_selection() {
+        let mut entry = entry(&["...", "...", "..."], Stone::Black, Stone::Black);
+        press(&mut entry, ButtonPress::Short);
+        assert_eq!(press(&mut entry, ButtonPress::Long), None);
+        // back on the board a spin moves the cursor again
+        spin(&mut entry, SpinDirection::Clockwise);
+        assert_eq!(entry.cursor(), Point::new(1, 2));
+    }
+
+    #[test]
+    fn moves_out_of_turn_are_refused() {
+        let mut entry = entry(&["...", "...", "..."], Stone::White, Stone::Black);
+        assert_eq!(press(&mut entry, ButtonPress::Short), None);
+        assert_eq!(entry.rejection(), Some(Rejection::NotYourTurn));
+        assert!(entry.timeout().is_some());
+        entry.handle(Input::Timeout);
+        assert_eq!(entry.rejection(), None);
+    }
+
+    #[test]
+    fn suicide_is_refused() {
+        let mut entry = entry(&[".X.", "X.X", ".X."], Stone::White, Stone::White);
+        move_to(&mut entry, Point::new(1, 1));
+        assert_eq!(play_cursor(&mut entry), None);
+        assert_eq!(
+            entry.rejection(),
+            Some(Rejection::Illegal(IllegalMove::Suicide(Point::new(1, 1))))
+        );
+        // any press clears it too
+        press(&mut entry, ButtonPress::Short);
+        assert_eq!(entry.rejection(), None);
+    }
+
+    #[test]
+    fn ko_is_refused() {
+        let mut entry = entry(
+            &[".XO.", "XO.O", ".XO.", "...."],
+            Stone::Black,
+            Stone::White,
+        );
+        // black takes the ko
+        entry.play(Move::Place(Point::new(1, 2))).unwrap();
+        let ko = Point::new(1, 1);
+        move_to(&mut entry, ko);
+        assert_eq!(play_cursor(&mut entry), None);
+        assert_eq!(
+            entry.rejection(),
+            Some(Rejection::Illegal(IllegalMove::Ko(ko)))
+        );
+    }
+
+    #[test]
+    fn spinning_a_selection_goes_through_pass_and_resign() {
+        let mut entry = entry(&["...", "...", "..."], Stone::Black, Stone::Black);
+        press(&mut entry, ButtonPress::Short);
+        spin(&mut entry, SpinDirection::Clockwise);
+        assert_eq!(
+            press(&mut entry, ButtonPress::Short),
+            Some(Submission::Play(Move::Pass))
+        );
+
+        press(&mut entry, ButtonPress::Short);
+        spin(&mut entry, SpinDirection::Clockwise);
+        spin(&mut entry, SpinDirection::Clockwise);
+        // a short press does not resign, it backs out
+        assert_eq!(press(&mut entry, ButtonPress::Short), None);
+        press(&mut entry, ButtonPress::Short);
+        spin(&mut entry, SpinDirection::Clockwise);
+        spin(&mut entry, SpinDirection::Clockwise);
+        assert_eq!(
+            press(&mut entry, ButtonPress::Long),
+            Some(Submission::Resign)
+        );
+
+        // and spinning once more comes back to the point
+        let point = entry.cursor();
+        press(&mut entry, ButtonPress::Short);
+        for _ in 0..3 {
+            spin(&mut entry, SpinDirection::Clockwise);
+        }
+        assert_eq!(
+            press(&mut entry, ButtonPress::Short),
+            Some(Submission::Play(Move::Place(point)))
+        );
+    }
+
+    #[test]
+    fn refused_moves_are_shown_until_the_timeout() {
+        let mut entry = entry(&["...", "...", "..."], Stone::Black, Stone::Black);
+        play_cursor(&mut entry);
+        entry.refuse(Rejection::Refused);
+        assert_eq!(entry.rejection(), Some(Rejection::Refused));
+        assert_eq!(entry.timeout(), Some(INVALID_TIMEOUT));
+        entry.handle(Input::Timeout);
+        assert_eq!(entry.rejection(), None);
+        assert_eq!(entry.timeout(), None);
+    }
+
+    #[test]
+    fn played_moves_cancel_the_selection() {
+        let mut entry = entry(&["...", "...", "..."], Stone::Black, Stone::Black);
+        press(&mut entry, ButtonPress::Short);
+        entry.play(Move::Place(Point::new(0, 0))).unwrap();
+        // back on the board, so spinning moves the cursor instead of selecting a pass
+        spin(&mut entry, SpinDirection::Clockwise);
+        assert_eq!(entry.cursor(), Point::new(1, 2));
+    }
+
+    #[test]
+    fn the_game_is_left_from_the_last_move() {
+        let mut entry = entry(&["...", "...", "..."], Stone::Black, Stone::Black);
+        // the score goes back to the board
+        assert_eq!(press(&mut entry, ButtonPress::Long), None);
+        assert_eq!(entry.timeout(), Some(SCORE_TIMEOUT));
+        assert_eq!(press(&mut entry, ButtonPress::Long), None);
+        assert_eq!(entry.timeout(), None);
+
+        // and so does a press on the last move
+        press(&mut entry, ButtonPress::Long);
+        entry.handle(Input::Timeout);
+        assert_eq!(press(&mut entry, ButtonPress::Short), None);
+        spin(&mut entry, SpinDirection::Clockwise);
+        assert_eq!(entry.cursor(), Point::new(1, 2));
+
+        press(&mut entry, ButtonPress::Long);
+        entry.handle(Input::Timeout);
+        assert_eq!(
+            press(&mut entry, ButtonPress::Long),
+            Some(Submission::Leave)
+        );
+    }
+
+    #[test]
+    fn the_cursor_and_last_move_have_their_own_layers() {
+        let mut entry = entry(&["...", "...", "..."], Stone::Black, Stone::White);
+        entry.play(Move::Place(Point::new(0, 0))).unwrap();
+        let led = |point| entry.viewport.to_led(point).unwrap();
+        let (x, y) = led(Point::new(0, 0));
+        // the board layer only has the stones
+        let stone = entry.render().into_iter().find(|c| (c.x, c.y) == (x, y));
+        assert_eq!(stone.unwrap().color, stone_color(Some(Stone::Black)));
+        assert_eq!(
+            entry.render_last_move(),
+            [LedChange::new(x, y, last_move_color(Stone::Black))]
+        );
+        let (x, y) = led(entry.cursor());
+        assert_eq!(entry.render_cursor(), [LedChange::new(x, y, CURSOR)]);
+
+        // neither marker is shown over the pass border
+        press(&mut entry, ButtonPress::Short);
+        spin(&mut entry, SpinDirection::Clockwise);
+        assert!(entry.render_cursor().is_empty());
+        assert!(entry.render_last_move().is_empty());
+    }
+}
@@ -0,0 +1,246 @@
+use crate::encoder::{ButtonPress, SpinDirection};
+use crate::neopixel::frame::LedDisplay;
+use crate::neopixel::go_board::render_screen;
+use crate::neopixel::led_ctrl::{DisplayOnLeds, LedChange};
+use crate::neopixel::led_font::{write_centered, Font, Marquee};
+use crate::neopixel::rgb::{Rgb, BLUE, GREEN, ORANGE, WHITE};
+use crate::onlinego::api::{GameListData, Player};
+use crate::BOARD_SIZE;
+use anyhow::Result;
+use postcard::experimental::max_size::MaxSize;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+const OPPONENT_NAME: Rgb = WHITE;
+/// the board size is drawn in this color when it is the player's move
+const YOUR_TURN: Rgb = GREEN;
+/// and in this one while waiting on the opponent
+const THEIR_TURN: Rgb = BLUE;
+/// one dot per game along the bottom edge, the shown game's is brighter
+const GAME_DOT: Rgb = Rgb::new(35, 35, 35);
+const SELECTED_GAME_DOT: Rgb = ORANGE;
+
+const NAME_ROW: u8 = 1;
+const SIZE_ROW: u8 = NAME_ROW + Font::Small.height() as u8 + 2;
+const DOT_ROW: u8 = BOARD_SIZE as u8 - 1;
+
+/// The game opened last, the board reopens it after a reboot while it is still running
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, MaxSize)]
+pub struct SelectedGame {
+    pub game_id: i64,
+}
+
+struct Entry {
+    game: GameListData,
+    /// the opponent's name
+    name: Marquee,
+    your_turn: bool,
+}
+
+/// Picking the game to play: rotating goes through the games, each shown as the opponent's
+/// name (scrolling if it is too long) above the board size, a short press opens the game
+pub struct GamePicker {
+    entries: Vec<Entry>,
+    selected: usize,
+}
+
+impl GamePicker {
+    /// Starts on the game with `selected_id` if it is one of `games`, otherwise on the first
+    pub fn new(games: Vec<GameListData>, player: &Player, selected_id: Option<i64>) -> Self {
+        let selected = games
+            .iter()
+            .position(|game| Some(game.id) == selected_id)
+            .unwrap_or(0);
+        let entries = games
+            .into_iter()
+            .map(|game| {
+                let opponent = if game.players.black.id() == player.id() {
+                    &game.players.white
+                } else {
+                    &game.players.black
+                };
+                Entry {
+                    name: Marquee::new(opponent.username(), Font::Small, NAME_ROW, OPPONENT_NAME),
+                    your_turn: game.is_turn_of(player).unwrap_or(false),
+                    game,
+                }
+            })
+            .collect();
+        Self { entries, selected }
+    }
+
+    pub fn selected(&self) -> Option<&GameListData> {
+        self.entries.get(self.selected).map(|entry| &entry.game)
+    }
+
+    /// [`GamePicker::tick`] should be called after this long, `None` if nothing moves
+    pub fn timeout(&self) -> Option<Duration> {
+        self.entries.get(self.selected)?.name.timeout()
+    }
+
+    /// Scrolls the name one column
+    pub fn tick(&mut self) {
+        if let Some(entry) = self.entries.get_mut(self.selected) {
+            entry.name.tick();
+        }
+    }
+
+    pub fn on_spin(&mut self, direction: SpinDirection) {
+        let count = self.entries.len();
+        if count == 0 {
+            return;
+        }
+        self.selected = match direction {
+            SpinDirection::Clockwise => (self.selected + 1) % count,
+            SpinDirection::CounterClockwise => (self.selected + count - 1) % count,
+        };
+        self.entries[self.selected].name.reset();
+    }
+
+    /// The game to open, on a short press
+    pub fn on_press(&self, press: ButtonPress) -> Option<&GameListData> {
+        match press {
+            ButtonPress::Short => self.selected(),
+            ButtonPress::Long => None,
+        }
+    }
+
+    pub fn render(&self) -> Vec<LedChange> {
+        let Some(entry) = self.entries.get(self.selected) else {
+            return render_screen([]);
+        };
+        let mut changes = entry.name.render();
+
+        let game = &entry.game;
+        let size = if game.width == game.height {
+            game.width.to_string()
+        } else {
+            format!("{}x{}", game.width, game.height)
+        };
+        let color = if entry.your_turn {
+            YOUR_TURN
+        } else {
+            THEIR_TURN
+        };
+        changes.extend(write_centered(&size, Font::Small, SIZE_ROW, color));
+
+        let count = self.entries.len();
+        if count <= BOARD_SIZE {
+            let left = (BOARD_SIZE - count) / 2;
+            changes.extend((0..count).map(|i| {
+                let color = if i == self.selected {
+                    SELECTED_GAME_DOT
+                } else {
+                    GAME_DOT
+                };
+                LedChange::new(DOT_ROW, (left + i) as u8, color)
+            }));
+        } else {
+            // too many games for a dot each, the dot shows how far along the list this is
+            let column = self.selected * BOARD_SIZE / count;
+            changes.push(LedChange::new(DOT_ROW, column as u8, SELECTED_GAME_DOT));
+        }
+        render_screen(changes)
+    }
+}
+
+impl DisplayOnLeds for GamePicker {
+    async fn display(&self, display: &LedDisplay) -> Result<()> {
+        display.show_changes(self.render()).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn player(id: i64, username: &str) -> Player {
+        serde_json::from_value(json!({ "id": id, "username": username, "ranking": 20.0 })).unwrap()
+    }
+
+    /// A 9x9 game of the board's account (black) against `opponent`
+    fn game(id: i64, opponent: &str) -> GameListData {
+        serde_json::from_value(json!({
+            "id": id,
+            "name": format!("game {id}"),
+            "width": 9,
+            "height": 9,
+            "players": {
+                "black": { "id": 1, "username": "board", "ranking": 20.0 },
+                "white": { "id": id + 100, "username": opponent, "ranking": 20.0 },
+            },
+            "started": "",
+            "black_lost": true,
+            "white_lost": true,
+        }))
+        .unwrap()
+    }
+
+    fn picker(selected_id: Option<i64>) -> GamePicker {
+        let games = vec![game(10, "ann"), game(20, "bob"), game(30, "cyd")];
+        GamePicker::new(games, &player(1, "board"), selected_id)
+    }
+
+    fn selected_id(picker: &GamePicker) -> Option<i64> {
+        picker.selected().map(|game| game.id)
+    }
+
+    #[test]
+    fn spinning_cycles_through_the_games_and_wraps_around() {
+        let mut picker = picker(None);
+        assert_eq!(selected_id(&picker), Some(10));
+        picker.on_spin(SpinDirection::Clockwise);
+        assert_eq!(selected_id(&picker), Some(20));
+        picker.on_spin(SpinDirection::CounterClockwise);
+        picker.on_spin(SpinDirection::CounterClockwise);
+        assert_eq!(selected_id(&picker), Some(30));
+        picker.on_spin(SpinDirection::Clockwise);
+        assert_eq!(selected_id(&picker), Some(10));
+    }
+
+    #[test]
+    fn the_saved_game_is_selected_while_it_is_listed() {
+        assert_eq!(selected_id(&picker(Some(30))), Some(30));
+        // a game that ended since it was saved falls back to the first one
+        assert_eq!(selected_id(&picker(Some(99))), Some(10));
+    }
+
+    #[test]
+    fn a_short_press_opens_the_selected_game() {
+        let mut picker = picker(None);
+        picker.on_spin(SpinDirection::Clockwise);
+        assert_eq!(
+            picker.on_press(ButtonPress::Short).map(|game| game.id),
+            Some(20)
+        );
+        assert!(picker.on_press(ButtonPress::Long).is_none());
+    }
+
+    #[test]
+    fn the_selected_games_dot_is_lit() {
+        let mut picker = picker(None);
+        picker.on_spin(SpinDirection::Clockwise);
+        let dot = |y: u8| {
+            picker
+                .render()
+                .into_iter()
+                .find(|change| change.x == DOT_ROW && change.y == y)
+                .unwrap()
+                .color
+        };
+        // three dots centered on the bottom row
+        assert_eq!(dot(6), GAME_DOT);
+        assert_eq!(dot(7), SELECTED_GAME_DOT);
+        assert_eq!(dot(8), GAME_DOT);
+    }
+
+    #[test]
+    fn an_empty_list_has_nothing_to_open() {
+        let mut picker = GamePicker::new(Vec::new(), &player(1, "board"), Some(10));
+        picker.on_spin(SpinDirection::Clockwise);
+        assert!(picker.selected().is_none());
+        assert!(picker.on_press(ButtonPress::Short).is_none());
+        assert_eq!(picker.render().len(), BOARD_SIZE * BOARD_SIZE);
+    }
+}
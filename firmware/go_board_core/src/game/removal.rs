@@ -0,0 +1,311 @@
+use super::board::{Board, Point, Stone};
+use super::scoring::dead_stones_from_removal;
+use crate::encoder::{ButtonPress, SpinDirection};
+use crate::neopixel::frame::LedDisplay;
+use crate::neopixel::go_board::{render_board_with, render_points, show_board_layers, stone_color};
+use crate::neopixel::led_ctrl::{DisplayOnLeds, LedChange};
+use crate::neopixel::rgb::{Rgb, GREEN, ORANGE, RED};
+use crate::neopixel::viewport::Viewport;
+use crate::BOARD_SIZE;
+use anyhow::Result;
+
+const DEAD_BLACK_STONE: Rgb = Rgb::new(35, 0, 0);
+const DEAD_WHITE_STONE: Rgb = Rgb::new(0, 35, 0);
+const CURSOR: Rgb = ORANGE;
+
+/// What the player decided during the stone removal phase, to be sent to online-go
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum RemovalAction {
+    /// mark (or unmark) a group as dead
+    SetRemoved { stones: Vec<Point>, removed: bool },
+    /// agree to the current proposal
+    Accept { removed: Vec<Point> },
+    /// reject the proposal and go back to playing
+    Reject,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Mode {
+    /// rotating moves the cursor between groups, a press toggles the group
+    Marking,
+    /// after a long press, rotating picks accept or reject and a press confirms
+    Confirming { accept: bool },
+}
+
+/// Stone removal phase: the cursor moves group by group, a short press toggles the group under
+/// the cursor as dead or alive, a long press opens the accept/reject prompt
+pub struct StoneRemoval {
+    board: Board,
+    removed: Vec<bool>,
+    /// one stone of every group, in board order, the cursor moves through these
+    groups: Vec<Point>,
+    cursor: usize,
+    mode: Mode,
+    /// follows the cursor on boards larger than the matrix
+    viewport: Viewport,
+}
+
+impl StoneRemoval {
+    /// `removal` is online-go's removal matrix, any non zero point is proposed as dead
+    pub fn new(board: Board, removal: &[Vec<i32>]) -> Self {
+        let mut seen = vec![false; board.height() * board.width()];
+        let mut groups = Vec::new();
+        for (point, stone) in board.iter() {
+            if stone.is_none() || seen[Self::index(&board, point)] {
+                continue;
+            }
+            for stone in board.chain(point) {
+                seen[Self::index(&board, stone)] = true;
+            }
+            groups.push(point);
+        }
+        let mut state = Self {
+            removed: vec![false; board.height() * board.width()],
+            viewport: Viewport::new(&board),
+            board,
+            groups,
+            cursor: 0,
+            mode: Mode::Marking,
+        };
+        state.update_removal(removal);
+        state
+    }
+
+    fn index(board: &Board, point: Point) -> usize {
+        point.x as usize * board.width() + point.y as usize
+    }
+
+    /// Replaces the proposal, ie when the opponent changed it
+    pub fn update_removal(&mut self, removal: &[Vec<i32>]) {
+        self.removed.iter_mut().for_each(|removed| *removed = false);
+        for point in dead_stones_from_removal(removal) {
+            if self.board.contains(point) {
+                let index = Self::index(&self.board, point);
+                self.removed[index] = true;
+            }
+        }
+    }
+
+    pub fn is_removed(&self, point: Point) -> bool {
+        self.removed[Self::index(&self.board, point)]
+    }
+
+    /// Every stone currently proposed as dead
+    pub fn removed_stones(&self) -> Vec<Point> {
+        self.board
+            .iter()
+            .filter(|(point, stone)| stone.is_some() && self.is_removed(*point))
+            .map(|(point, _)| point)
+            .collect()
+    }
+
+    fn cursor_group(&self) -> Vec<Point> {
+        self.groups
+            .get(self.cursor)
+            .map(|point| self.board.chain(*point))
+            .unwrap_or_default()
+    }
+
+    pub fn on_spin(&mut self, direction: SpinDirection) {
+        match &mut self.mode {
+            Mode::Marking if !self.groups.is_empty() => {
+                let len = self.groups.len();
+                self.cursor = match direction {
+                    SpinDirection::Clockwise => (self.cursor + 1) % len,
+                    SpinDirection::CounterClockwise => (self.cursor + len - 1) % len,
+                };
+                self.viewport.follow(self.groups[self.cursor]);
+            }
+            Mode::Marking => {}
+            Mode::Confirming { accept } => *accept = !*accept,
+        }
+    }
+
+    pub fn on_press(&mut self, press: ButtonPress) -> Option<RemovalAction> {
+        match (self.mode, press) {
+            (Mode::Marking, ButtonPress::Short) => {
+                let stones = self.cursor_group();
+                let removed = !stones.first().is_some_and(|point| self.is_removed(*point));
+                for stone in &stones {
+                    let index = Self::index(&self.board, *stone);
+                    self.removed[index] = removed;
+                }
+                (!stones.is_empty()).then_some(RemovalAction::SetRemoved { stones, removed })
+            }
+            (Mode::Marking, ButtonPress::Long) => {
+                self.mode = Mode::Confirming { accept: true };
+                None
+            }
+            (Mode::Confirming { accept }, ButtonPress::Short) => {
+                self.mode = Mode::Marking;
+                Some(if accept {
+                    RemovalAction::Accept {
+                        removed: self.removed_stones(),
+                    }
+                } else {
+                    RemovalAction::Reject
+                })
+            }
+            // a long press backs out of the prompt
+            (Mode::Confirming { .. }, ButtonPress::Long) => {
+                self.mode = Mode::Marking;
+                None
+            }
+        }
+    }
+
+    /// The board with dead stones dimmed,
+    /// while confirming the edge of the matrix shows green for accept or red for reject
+    pub fn render(&self) -> Vec<LedChange> {
+        let mut changes =
+            render_board_with(&self.board, &self.viewport, |point, stone| match stone {
+                Some(Stone::Black) if self.is_removed(point) => DEAD_BLACK_STONE,
+                Some(Stone::White) if self.is_removed(point) => DEAD_WHITE_STONE,
+                stone => stone_color(stone),
+            });
+        if let Mode::Confirming { accept } = self.mode {
+            let color = if accept { GREEN } else { RED };
+            let last = BOARD_SIZE as u8 - 1;
+            for change in changes.iter_mut() {
+                if change.x == 0 || change.y == 0 || change.x == last || change.y == last {
+                    change.color = color;
+                }
+            }
+        }
+        changes
+    }
+
+    /// The cursor layer: the group under the cursor, while marking
+    pub fn render_cursor(&self) -> Vec<LedChange> {
+        match self.mode {
+            Mode::Marking => render_points(&self.viewport, self.cursor_group(), CURSOR),
+            Mode::Confirming { .. } => Vec::new(),
+        }
+    }
+}
+
+impl DisplayOnLeds for StoneRemoval {
+    async fn display(&self, display: &LedDisplay) -> Result<()> {
+        show_board_layers(display, self.render(), Vec::new(), self.render_cursor()).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::rules::tests::from_picture;
+
+    /// groups in board order: the black pair on top, the white pair, the lone black stone
+    fn removal(removal: &[Vec<i32>]) -> StoneRemoval {
+        let board =
+            from_picture(&["XX...", ".....", "..O..", "..O.X", "....."], Stone::Black).board;
+        StoneRemoval::new(board, removal)
+    }
+
+    fn nothing_removed() -> Vec<Vec<i32>> {
+        vec![vec![0; 5]; 5]
+    }
+
+    fn toggled(action: Option<RemovalAction>) -> (Vec<Point>, bool) {
+        match action {
+            Some(RemovalAction::SetRemoved {
+                mut stones,
+                removed,
+            }) => {
+                stones.sort();
+                (stones, removed)
+            }
+            action => panic!("expected a group to be toggled, got {action:?}"),
+        }
+    }
+
+    #[test]
+    fn groups_are_toggled_dead_and_back() {
+        let mut state = removal(&nothing_removed());
+        let white = vec![Point::new(2, 2), Point::new(3, 2)];
+        state.on_spin(SpinDirection::Clockwise);
+        assert_eq!(
+            toggled(state.on_press(ButtonPress::Short)),
+            (white.clone(), true)
+        );
+        assert!(state.is_removed(Point::new(2, 2)) && state.is_removed(Point::new(3, 2)));
+        assert_eq!(state.removed_stones(), white);
+
+        assert_eq!(toggled(state.on_press(ButtonPress::Short)), (white, false));
+        assert!(state.removed_stones().is_empty());
+    }
+
+    #[test]
+    fn the_cursor_wraps_around_the_groups() {
+        let mut state = removal(&nothing_removed());
+        state.on_spin(SpinDirection::CounterClockwise);
+        assert_eq!(
+            toggled(state.on_press(ButtonPress::Short)),
+            (vec![Point::new(3, 4)], true)
+        );
+        state.on_spin(SpinDirection::Clockwise);
+        assert_eq!(
+            toggled(state.on_press(ButtonPress::Short)),
+            (vec![Point::new(0, 0), Point::new(0, 1)], true)
+        );
+    }
+
+    #[test]
+    fn the_prompt_accepts_or_rejects() {
+        let mut state = removal(&nothing_removed());
+        state.on_press(ButtonPress::Short);
+        assert_eq!(state.on_press(ButtonPress::Long), None);
+        assert!(state.render_cursor().is_empty());
+        // the edge of the matrix is green for accept
+        assert!(state
+            .render()
+            .iter()
+            .any(|change| change.x == 0 && change.color == GREEN));
+        assert_eq!(
+            state.on_press(ButtonPress::Short),
+            Some(RemovalAction::Accept {
+                removed: vec![Point::new(0, 0), Point::new(0, 1)]
+            })
+        );
+
+        state.on_press(ButtonPress::Long);
+        state.on_spin(SpinDirection::Clockwise);
+        assert_eq!(
+            state.on_press(ButtonPress::Short),
+            Some(RemovalAction::Reject)
+        );
+
+        // a long press backs out of the prompt, a short one toggles a group again
+        state.on_press(ButtonPress::Long);
+        assert_eq!(state.on_press(ButtonPress::Long), None);
+        assert_eq!(
+            toggled(state.on_press(ButtonPress::Short)),
+            (vec![Point::new(0, 0), Point::new(0, 1)], false)
+        );
+    }
+
+    #[test]
+    fn the_removal_matrix_round_trips() {
+        let mut proposal = nothing_removed();
+        proposal[2][2] = 1;
+        proposal[3][2] = 1;
+        // empty points can't be dead
+        proposal[4][4] = 1;
+        let mut state = removal(&proposal);
+        let removed = state.removed_stones();
+        assert_eq!(removed, vec![Point::new(2, 2), Point::new(3, 2)]);
+
+        let mut matrix = nothing_removed();
+        for point in &removed {
+            matrix[point.x as usize][point.y as usize] = 1;
+        }
+        let mut expected = proposal.clone();
+        expected[4][4] = 0;
+        assert_eq!(matrix, expected);
+        assert_eq!(dead_stones_from_removal(&matrix), removed);
+
+        // the opponent's new proposal replaces the old one
+        state.update_removal(&nothing_removed());
+        assert!(state.removed_stones().is_empty());
+    }
+}
@@ -0,0 +1,263 @@
+use super::board::Point;
+use super::rules::{Move, Position};
+use crate::encoder::SpinDirection;
+use crate::neopixel::frame::LedDisplay;
+use crate::neopixel::go_board::{
+    fade_board_layers, last_move_color, render_board, render_points, show_board_layers,
+};
+use crate::neopixel::led_ctrl::{DisplayOnLeds, LedChange};
+use crate::neopixel::viewport::Viewport;
+use anyhow::{anyhow, Result};
+
+/// a position is kept every this many moves, so stepping back only replays a few moves
+/// instead of keeping every board in RAM
+const CHECKPOINT_INTERVAL: usize = 32;
+
+/// Steps through the moves of a game
+pub struct GameReplay {
+    moves: Vec<Move>,
+    /// `checkpoints[i]` is the position after `i * CHECKPOINT_INTERVAL` moves
+    checkpoints: Vec<Position>,
+    current: Position,
+    /// number of moves played to reach `current`
+    move_number: usize,
+    /// follows the last move on boards larger than the matrix
+    viewport: Viewport,
+}
+
+impl GameReplay {
+    /// Fails if any of the moves is illegal
+    pub fn new(initial: Position, moves: Vec<Move>) -> Result<Self> {
+        let mut checkpoints = vec![initial.clone()];
+        let mut position = initial.clone();
+        for (move_number, mv) in moves.iter().enumerate() {
+            position = position
+                .play_move(*mv)
+                .map_err(|e| anyhow!(e).context(format!("Failed to replay move {move_number}")))?
+                .position;
+            if (move_number + 1) % CHECKPOINT_INTERVAL == 0 {
+                checkpoints.push(position.clone());
+            }
+        }
+        Ok(Self {
+            moves,
+            checkpoints,
+            viewport: Viewport::new(&initial.board),
+            current: initial,
+            move_number: 0,
+        })
+    }
+
+    pub fn position(&self) -> &Position {
+        &self.current
+    }
+
+    pub fn move_number(&self) -> usize {
+        self.move_number
+    }
+
+    pub fn total_moves(&self) -> usize {
+        self.moves.len()
+    }
+
+    pub fn is_at_start(&self) -> bool {
+        self.move_number == 0
+    }
+
+    pub fn is_at_end(&self) -> bool {
+        self.move_number == self.moves.len()
+    }
+
+    /// The stone placed by the move that led to the current position, `None` for a pass
+    pub fn last_move(&self) -> Option<Point> {
+        match self.moves.get(self.move_number.checked_sub(1)?)? {
+            Move::Place(point) => Some(*point),
+            Move::Pass => None,
+        }
+    }
+
+    /// Jumps to the position after `move_number` moves (clamped to the end of the game)
+    pub fn go_to(&mut self, move_number: usize) {
+        let move_number = move_number.min(self.moves.len());
+        // replay from the current position when going forward, otherwise from a checkpoint
+        if move_number < self.move_number {
+            let checkpoint = move_number / CHECKPOINT_INTERVAL;
+            self.current = self.checkpoints[checkpoint].clone();
+            self.move_number = checkpoint * CHECKPOINT_INTERVAL;
+        }
+        while self.move_number < move_number {
+            self.current = self
+                .current
+                .play_move(self.moves[self.move_number])
+                .expect("moves were checked when the replay was created")
+                .position;
+            self.move_number += 1;
+        }
+        if let Some(last_move) = self.last_move() {
+            self.viewport.follow(last_move);
+        }
+    }
+
+    /// Scrolls the window on boards larger than the matrix, it goes back to following
+    /// the last move on the next step
+    pub fn pan(&mut self, direction: SpinDirection) {
+        self.viewport.pan(direction);
+    }
+
+    /// Returns false if already at the end
+    pub fn forward(&mut self) -> bool {
+        if self.is_at_end() {
+            return false;
+        }
+        self.go_to(self.move_number + 1);
+        true
+    }
+
+    /// Returns false if already at the start
+    pub fn back(&mut self) -> bool {
+        if self.is_at_start() {
+            return false;
+        }
+        self.go_to(self.move_number - 1);
+        true
+    }
+
+    /// Jumps to the end, or to the start when already at the end
+    pub fn jump(&mut self) {
+        if self.is_at_end() {
+            self.go_to(0);
+        } else {
+            self.go_to(self.moves.len());
+        }
+    }
+
+    /// The board layer, just the stones
+    pub fn render(&self) -> Vec<LedChange> {
+        render_board(&self.current.board, &self.viewport)
+    }
+
+    /// The last move layer: the stone played last drawn brighter
+    pub fn render_last_move(&self) -> Vec<LedChange> {
+        let last_move = self
+            .last_move()
+            .and_then(|point| Some((point, self.current.board.get(point)?)));
+        match last_move {
+            Some((point, stone)) => render_points(&self.viewport, [point], last_move_color(stone)),
+            None => Vec::new(),
+        }
+    }
+}
+
+impl GameReplay {
+    /// Like [`DisplayOnLeds::display`] but fading the board, after stepping through the moves
+    pub async fn display_move(&self, display: &LedDisplay) -> Result<()> {
+        fade_board_layers(display, self.render(), self.render_last_move(), Vec::new()).await
+    }
+}
+
+impl DisplayOnLeds for GameReplay {
+    async fn display(&self, display: &LedDisplay) -> Result<()> {
+        show_board_layers(display, self.render(), self.render_last_move(), Vec::new()).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::board::{Board, Stone};
+
+    fn empty() -> Position {
+        Position::new(Board::new(9, 9).unwrap(), Stone::Black)
+    }
+
+    /// Opens with black capturing in the corner, then fills the board with legal moves until
+    /// it is well past the second checkpoint
+    fn game() -> Vec<Move> {
+        let mut moves = vec![
+            Move::Place(Point::new(0, 1)),
+            Move::Place(Point::new(0, 0)),
+            Move::Place(Point::new(1, 0)),
+            Move::Pass,
+        ];
+        let mut position = moves.iter().fold(empty(), |position, mv| {
+            position.play_move(*mv).unwrap().position
+        });
+        let mut candidate = 0;
+        while moves.len() < 2 * CHECKPOINT_INTERVAL + 5 {
+            candidate = (candidate + 7) % 81;
+            let mv = Move::Place(Point::new(candidate / 9, candidate % 9));
+            if let Ok(outcome) = position.play_move(mv) {
+                position = outcome.position;
+                moves.push(mv);
+            }
+        }
+        moves
+    }
+
+    /// What the replay should show, played from the first move every time
+    fn from_scratch(moves: &[Move], move_number: usize) -> Position {
+        moves[..move_number].iter().fold(empty(), |position, mv| {
+            position.play_move(*mv).unwrap().position
+        })
+    }
+
+    #[test]
+    fn stepping_matches_replaying_from_scratch() {
+        let moves = game();
+        let mut replay = GameReplay::new(empty(), moves.clone()).unwrap();
+        while replay.forward() {
+            let move_number = replay.move_number();
+            assert_eq!(replay.position(), &from_scratch(&moves, move_number));
+        }
+        assert!(replay.is_at_end());
+        // back across both checkpoints, each step starts over from the one before it
+        while replay.back() {
+            let move_number = replay.move_number();
+            assert_eq!(replay.position(), &from_scratch(&moves, move_number));
+        }
+        assert!(replay.is_at_start());
+        assert!(!replay.back());
+    }
+
+    #[test]
+    fn captures_are_replayed() {
+        let mut replay = GameReplay::new(empty(), game()).unwrap();
+        replay.go_to(3);
+        assert_eq!(replay.position().board.get(Point::new(0, 0)), None);
+        assert_eq!(replay.position().black_captures, 1);
+        assert_eq!(replay.last_move(), Some(Point::new(1, 0)));
+        replay.back();
+        assert_eq!(
+            replay.position().board.get(Point::new(0, 0)),
+            Some(Stone::White)
+        );
+        assert_eq!(replay.position().black_captures, 0);
+        // a pass has no stone to point at
+        replay.go_to(4);
+        assert_eq!(replay.last_move(), None);
+    }
+
+    #[test]
+    fn jumps_go_to_the_end_and_back_to_the_start() {
+        let moves = game();
+        let mut replay = GameReplay::new(empty(), moves.clone()).unwrap();
+        replay.go_to(CHECKPOINT_INTERVAL + 1);
+        replay.jump();
+        assert!(replay.is_at_end());
+        assert_eq!(replay.move_number(), replay.total_moves());
+        assert_eq!(replay.position(), &from_scratch(&moves, moves.len()));
+        assert!(!replay.forward());
+        replay.jump();
+        assert!(replay.is_at_start());
+        assert_eq!(replay.position(), &empty());
+        // past the end is clamped
+        replay.go_to(moves.len() + 10);
+        assert!(replay.is_at_end());
+    }
+
+    #[test]
+    fn illegal_moves_are_refused_up_front() {
+        let moves = vec![Move::Place(Point::new(0, 0)), Move::Place(Point::new(0, 0))];
+        assert!(GameReplay::new(empty(), moves).is_err());
+    }
+}
@@ -0,0 +1,315 @@
+use super::board::{Board, Point, Stone};
+use super::zobrist;
+use std::error::Error;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+
+/// Why a move was refused
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+pub enum IllegalMove {
+    OutOfBounds(Point),
+    Occupied(Point),
+    /// the placed stone would have no liberties and captures nothing
+    Suicide(Point),
+    /// the move retakes a ko immediately
+    Ko(Point),
+    /// the move recreates an earlier position, see [`zobrist::KoRule`]
+    Superko(Point),
+}
+
+impl Display for IllegalMove {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            IllegalMove::OutOfBounds(point) => write!(f, "{point} is not on the board"),
+            IllegalMove::Occupied(point) => write!(f, "{point} already has a stone on it"),
+            IllegalMove::Suicide(point) => write!(f, "playing at {point} is suicide"),
+            IllegalMove::Ko(point) => write!(f, "playing at {point} retakes the ko"),
+            IllegalMove::Superko(point) => {
+                write!(f, "playing at {point} repeats an earlier position")
+            }
+        }
+    }
+}
+
+impl Error for IllegalMove {}
+
+/// A move, either a stone placed on a point or a pass
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+pub enum Move {
+    Place(Point),
+    Pass,
+}
+
+/// The board plus everything needed to decide if the next move is legal
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Position {
+    pub board: Board,
+    pub to_move: Stone,
+    /// the point that can't be played this turn because it would retake a simple ko
+    pub ko: Option<Point>,
+    /// stones captured *by* black
+    pub black_captures: u32,
+    /// stones captured *by* white
+    pub white_captures: u32,
+    /// zobrist hash of the board, updated on every move
+    pub hash: u64,
+}
+
+/// The position after a legal move
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct MoveOutcome {
+    pub position: Position,
+    /// stones of the opponent taken off the board by the move
+    pub captured: Vec<Point>,
+}
+
+impl Position {
+    pub fn new(board: Board, to_move: Stone) -> Self {
+        Self {
+            hash: zobrist::hash_board(&board),
+            board,
+            to_move,
+            ko: None,
+            black_captures: 0,
+            white_captures: 0,
+        }
+    }
+
+    pub fn empty(height: usize, width: usize) -> anyhow::Result<Self> {
+        Ok(Self::new(Board::new(height, width)?, Stone::Black))
+    }
+
+    pub fn captures_by(&self, stone: Stone) -> u32 {
+        match stone {
+            Stone::Black => self.black_captures,
+            Stone::White => self.white_captures,
+        }
+    }
+
+    /// Checks if the player to move can play at `point`
+    pub fn is_legal(&self, point: Point) -> Result<(), IllegalMove> {
+        self.play(point).map(|_| ())
+    }
+
+    pub fn play_move(&self, mv: Move) -> Result<MoveOutcome, IllegalMove> {
+        match mv {
+            Move::Place(point) => self.play(point),
+            Move::Pass => Ok(MoveOutcome {
+                position: self.pass(),
+                captured: Vec::new(),
+            }),
+        }
+    }
+
+    pub fn pass(&self) -> Self {
+        Self {
+            to_move: self.to_move.opponent(),
+            ko: None,
+            ..self.clone()
+        }
+    }
+
+    /// Places a stone for the player to move, removing any opponent groups left without liberties
+    pub fn play(&self, point: Point) -> Result<MoveOutcome, IllegalMove> {
+        if !self.board.contains(point) {
+            return Err(IllegalMove::OutOfBounds(point));
+        }
+        if self.board.get(point).is_some() {
+            return Err(IllegalMove::Occupied(point));
+        }
+        if self.ko == Some(point) {
+            return Err(IllegalMove::Ko(point));
+        }
+
+        let player = self.to_move;
+        let mut board = self.board.clone();
+        board.set(point, Some(player));
+        let mut hash = self.hash ^ zobrist::point_key(point, player);
+
+        // remove every neighboring opponent group that just lost its last liberty
+        let mut captured = Vec::new();
+        for neighbor in self.board.neighbors(point) {
+            if board.get(neighbor) != Some(player.opponent()) {
+                continue;
+            }
+            if let Some(group) = board.group(neighbor) {
+                if group.liberties.is_empty() {
+                    for stone in &group.stones {
+                        board.set(*stone, None);
+                        hash ^= zobrist::point_key(*stone, player.opponent());
+                    }
+                    captured.extend(group.stones);
+                }
+            }
+        }
+
+        let own_group = board
+            .group(point)
+            .expect("a stone was just placed on this point");
+        if own_group.liberties.is_empty() {
+            return Err(IllegalMove::Suicide(point));
+        }
+
+        // a single stone that captured a single stone and is left in atari can be retaken
+        // straight away, which would repeat the previous position
+        let ko =
+            if captured.len() == 1 && own_group.stones.len() == 1 && own_group.liberties.len() == 1
+            {
+                Some(captured[0])
+            } else {
+                None
+            };
+
+        let (mut black_captures, mut white_captures) = (self.black_captures, self.white_captures);
+        match player {
+            Stone::Black => black_captures += captured.len() as u32,
+            Stone::White => white_captures += captured.len() as u32,
+        }
+
+        Ok(MoveOutcome {
+            position: Position {
+                board,
+                to_move: player.opponent(),
+                ko,
+                black_captures,
+                white_captures,
+                hash,
+            },
+            captured,
+        })
+    }
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use super::*;
+    use crate::game::zobrist::{KoRule, PositionHistory};
+
+    /// `X` is black, `O` white and `.` empty, one string per row
+    pub(in crate::game) fn from_picture(rows: &[&str], to_move: Stone) -> Position {
+        let rows: Vec<Vec<i32>> = rows
+            .iter()
+            .map(|row| {
+                row.chars()
+                    .map(|point| match point {
+                        'X' => 1,
+                        'O' => 2,
+                        _ => 0,
+                    })
+                    .collect()
+            })
+            .collect();
+        Position::new(Board::from_rows(&rows).unwrap(), to_move)
+    }
+
+    fn ko_shape() -> Position {
+        from_picture(&[".XO.", "XO.O", ".XO.", "...."], Stone::Black)
+    }
+
+    #[test]
+    fn single_stone_is_captured() {
+        let position = from_picture(&[".X..", "XO..", ".X..", "...."], Stone::Black);
+        let outcome = position.play(Point::new(1, 2)).unwrap();
+        assert_eq!(outcome.captured, vec![Point::new(1, 1)]);
+        assert_eq!(outcome.position.board.get(Point::new(1, 1)), None);
+        assert_eq!(outcome.position.black_captures, 1);
+        assert_eq!(outcome.position.to_move, Stone::White);
+        assert_eq!(outcome.position.ko, None);
+    }
+
+    #[test]
+    fn whole_group_is_captured() {
+        let position = from_picture(&[".XX.", "XOO.", ".XX.", "...."], Stone::Black);
+        let mut outcome = position.play(Point::new(1, 3)).unwrap();
+        outcome.captured.sort();
+        assert_eq!(outcome.captured, vec![Point::new(1, 1), Point::new(1, 2)]);
+        assert_eq!(outcome.position.captures_by(Stone::Black), 2);
+        assert_eq!(outcome.position.captures_by(Stone::White), 0);
+        assert_eq!(outcome.position.ko, None);
+        // the hash is updated move by move, it must match hashing the board again
+        assert_eq!(
+            outcome.position.hash,
+            zobrist::hash_board(&outcome.position.board)
+        );
+    }
+
+    #[test]
+    fn suicide_is_refused() {
+        let position = from_picture(&[".X.", "X.X", ".X."], Stone::White);
+        let point = Point::new(1, 1);
+        assert_eq!(position.play(point), Err(IllegalMove::Suicide(point)));
+        // filling the last liberty of its own group is suicide too
+        let position = from_picture(&[".OX", "XX.", "..."], Stone::White);
+        let point = Point::new(0, 0);
+        assert_eq!(position.play(point), Err(IllegalMove::Suicide(point)));
+    }
+
+    #[test]
+    fn capturing_is_not_suicide() {
+        // the black stone has no liberty of its own until it takes the white one
+        let outcome = ko_shape().play(Point::new(1, 2)).unwrap();
+        assert_eq!(outcome.captured, vec![Point::new(1, 1)]);
+    }
+
+    #[test]
+    fn ko_cannot_be_retaken_straight_away() {
+        let taken = ko_shape().play(Point::new(1, 2)).unwrap().position;
+        let ko = Point::new(1, 1);
+        assert_eq!(taken.ko, Some(ko));
+        assert_eq!(taken.play(ko), Err(IllegalMove::Ko(ko)));
+        assert_eq!(taken.is_legal(ko), Err(IllegalMove::Ko(ko)));
+
+        // a move elsewhere lifts the ban
+        let threat = taken.play(Point::new(3, 3)).unwrap().position;
+        let answer = threat.play(Point::new(3, 0)).unwrap().position;
+        let retaken = answer.play(ko).unwrap();
+        assert_eq!(retaken.captured, vec![Point::new(1, 2)]);
+        // and so does a pass
+        assert_eq!(taken.play_move(Move::Pass).unwrap().position.ko, None);
+    }
+
+    #[test]
+    fn occupied_and_off_board_points_are_refused() {
+        let position = ko_shape();
+        let occupied = Point::new(0, 1);
+        assert_eq!(
+            position.play(occupied),
+            Err(IllegalMove::Occupied(occupied))
+        );
+        let off = Point::new(4, 0);
+        assert_eq!(position.play(off), Err(IllegalMove::OutOfBounds(off)));
+        let off = Point::new(0, 4);
+        assert_eq!(position.play(off), Err(IllegalMove::OutOfBounds(off)));
+    }
+
+    #[test]
+    fn superko_refuses_repeated_boards() {
+        let ko = Point::new(1, 1);
+        for (rule, allowed) in [(KoRule::Simple, true), (KoRule::PositionalSuperko, false)] {
+            let mut history = PositionHistory::new(ko_shape(), rule);
+            history.play(Move::Place(Point::new(1, 2))).unwrap();
+            // the passes clear the simple ko, only superko still sees the repetition
+            history.play(Move::Pass).unwrap();
+            history.play(Move::Pass).unwrap();
+            let retake = history.play(Move::Place(ko));
+            if allowed {
+                assert!(retake.is_ok(), "{rule:?}");
+            } else {
+                assert_eq!(retake, Err(IllegalMove::Superko(ko)), "{rule:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn illegal_moves_explain_themselves() {
+        let point = Point::new(2, 3);
+        assert_eq!(
+            IllegalMove::Occupied(point).to_string(),
+            "(2,3) already has a stone on it"
+        );
+        assert_eq!(
+            IllegalMove::Superko(point).to_string(),
+            "playing at (2,3) repeats an earlier position"
+        );
+    }
+}
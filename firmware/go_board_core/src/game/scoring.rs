@@ -0,0 +1,264 @@
+use super::board::{Board, Point, Stone};
+
+/// What counts as points at the end of the game
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+pub enum ScoringRules {
+    /// japanese/korean: surrounded empty points plus prisoners
+    Territory,
+    /// chinese/aga: surrounded empty points plus stones on the board
+    Area,
+}
+
+impl ScoringRules {
+    /// Maps the `rules` string online-go reports for a game
+    pub fn from_ogs_rules(rules: &str) -> Self {
+        match rules {
+            "chinese" | "aga" | "nz" | "ing" => ScoringRules::Area,
+            // japanese, korean and anything unknown
+            _ => ScoringRules::Territory,
+        }
+    }
+}
+
+/// Final score of a game, komi is already added to white
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct Score {
+    pub black: f32,
+    pub white: f32,
+}
+
+impl Score {
+    /// `None` when the game is a draw (jigo)
+    pub fn winner(&self) -> Option<Stone> {
+        if self.black > self.white {
+            Some(Stone::Black)
+        } else if self.white > self.black {
+            Some(Stone::White)
+        } else {
+            None
+        }
+    }
+
+    pub fn margin(&self) -> f32 {
+        (self.black - self.white).abs()
+    }
+}
+
+/// Everything counted for one player, before komi
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+struct Tally {
+    territory: u32,
+    stones: u32,
+    prisoners: u32,
+}
+
+/// The points marked in online-go's `removal` matrix (any value other than `0`)
+pub fn dead_stones_from_removal(removal: &[Vec<i32>]) -> Vec<Point> {
+    removal
+        .iter()
+        .enumerate()
+        .flat_map(|(x, row)| {
+            row.iter()
+                .enumerate()
+                .filter(|(_, removed)| **removed != 0)
+                .map(move |(y, _)| Point::new(x as u8, y as u8))
+        })
+        .collect()
+}
+
+/// Scores a finished board
+///
+/// `dead` are the stones agreed dead in the stone removal phase, `black_captures`/`white_captures`
+/// are the stones each player captured during the game. Seki is not detected, so under
+/// territory rules eyes inside a seki are counted as territory.
+pub fn score(
+    board: &Board,
+    dead: &[Point],
+    black_captures: u32,
+    white_captures: u32,
+    komi: f32,
+    rules: ScoringRules,
+) -> Score {
+    let mut black = Tally {
+        prisoners: black_captures,
+        ..Default::default()
+    };
+    let mut white = Tally {
+        prisoners: white_captures,
+        ..Default::default()
+    };
+
+    // dead stones are taken off and given to the other player as prisoners
+    let mut board = board.clone();
+    for point in dead {
+        if !board.contains(*point) {
+            continue;
+        }
+        match board.get(*point) {
+            Some(Stone::Black) => white.prisoners += 1,
+            Some(Stone::White) => black.prisoners += 1,
+            None => continue,
+        }
+        board.set(*point, None);
+    }
+
+    let mut visited = vec![false; board.height() * board.width()];
+    for (point, stone) in board.iter() {
+        let index = point.x as usize * board.width() + point.y as usize;
+        match stone {
+            Some(Stone::Black) => black.stones += 1,
+            Some(Stone::White) => white.stones += 1,
+            None if !visited[index] => {
+                // an empty region belongs to a player only if it touches just their stones
+                let region = board.chain(point);
+                let mut borders_black = false;
+                let mut borders_white = false;
+                for empty in &region {
+                    visited[empty.x as usize * board.width() + empty.y as usize] = true;
+                    for neighbor in board.neighbors(*empty) {
+                        match board.get(neighbor) {
+                            Some(Stone::Black) => borders_black = true,
+                            Some(Stone::White) => borders_white = true,
+                            None => {}
+                        }
+                    }
+                }
+                match (borders_black, borders_white) {
+                    (true, false) => black.territory += region.len() as u32,
+                    (false, true) => white.territory += region.len() as u32,
+                    _ => {}
+                }
+            }
+            None => {}
+        }
+    }
+
+    let points = |tally: Tally| -> f32 {
+        match rules {
+            ScoringRules::Territory => (tally.territory + tally.prisoners) as f32,
+            ScoringRules::Area => (tally.territory + tally.stones) as f32,
+        }
+    };
+    Score {
+        black: points(black),
+        white: points(white) + komi,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::rules::tests::from_picture;
+
+    fn board(rows: &[&str]) -> Board {
+        from_picture(rows, Stone::Black).board
+    }
+
+    /// black owns the left column, white the two right ones
+    fn walls() -> Board {
+        board(&[".XO..", ".XO..", ".XO..", ".XO..", ".XO.."])
+    }
+
+    #[test]
+    fn territory_counts_prisoners_and_area_counts_stones() {
+        let territory = score(&walls(), &[], 2, 1, 0.5, ScoringRules::Territory);
+        assert_eq!(
+            territory,
+            Score {
+                black: 5.0 + 2.0,
+                white: 10.0 + 1.0 + 0.5
+            }
+        );
+        let area = score(&walls(), &[], 2, 1, 0.5, ScoringRules::Area);
+        assert_eq!(
+            area,
+            Score {
+                black: 5.0 + 5.0,
+                white: 10.0 + 5.0 + 0.5
+            }
+        );
+        assert_eq!(area.winner(), Some(Stone::White));
+        assert_eq!(area.margin(), 5.5);
+    }
+
+    #[test]
+    fn komi_goes_to_white() {
+        let board = board(&["XO", "XO"]);
+        let even = score(&board, &[], 0, 0, 0.0, ScoringRules::Area);
+        assert_eq!(even.winner(), None);
+        let komi = score(&board, &[], 0, 0, 6.5, ScoringRules::Area);
+        assert_eq!(
+            komi,
+            Score {
+                black: 2.0,
+                white: 8.5
+            }
+        );
+        assert_eq!(komi.winner(), Some(Stone::White));
+    }
+
+    #[test]
+    fn dead_stones_are_prisoners_and_free_the_territory() {
+        let board = board(&[".XO..", ".XO..", "OXO..", ".XO..", ".XO.."]);
+        // the white stone makes the left column nobody's
+        let alive = score(&board, &[], 0, 0, 0.0, ScoringRules::Territory);
+        assert_eq!(
+            alive,
+            Score {
+                black: 0.0,
+                white: 10.0
+            }
+        );
+
+        let mut removal = vec![vec![0; 5]; 5];
+        removal[2][0] = 1;
+        let dead = dead_stones_from_removal(&removal);
+        assert_eq!(dead, vec![Point::new(2, 0)]);
+        let territory = score(&board, &dead, 0, 0, 0.0, ScoringRules::Territory);
+        assert_eq!(
+            territory,
+            Score {
+                black: 5.0 + 1.0,
+                white: 10.0
+            }
+        );
+        let area = score(&board, &dead, 0, 0, 0.0, ScoringRules::Area);
+        assert_eq!(
+            area,
+            Score {
+                black: 5.0 + 5.0,
+                white: 10.0 + 5.0
+            }
+        );
+    }
+
+    #[test]
+    fn shared_liberties_of_a_seki_are_nobodys() {
+        // neither side can fill the two empty points without being captured
+        let seki = board(&["X.O", "X.O", "XXO"]);
+        let territory = score(&seki, &[], 0, 0, 0.0, ScoringRules::Territory);
+        assert_eq!(territory, Score::default());
+        let area = score(&seki, &[], 0, 0, 0.0, ScoringRules::Area);
+        assert_eq!(
+            area,
+            Score {
+                black: 4.0,
+                white: 3.0
+            }
+        );
+    }
+
+    #[test]
+    fn online_go_rules_are_mapped() {
+        assert_eq!(ScoringRules::from_ogs_rules("chinese"), ScoringRules::Area);
+        assert_eq!(ScoringRules::from_ogs_rules("aga"), ScoringRules::Area);
+        assert_eq!(
+            ScoringRules::from_ogs_rules("japanese"),
+            ScoringRules::Territory
+        );
+        assert_eq!(
+            ScoringRules::from_ogs_rules("something new"),
+            ScoringRules::Territory
+        );
+    }
+}
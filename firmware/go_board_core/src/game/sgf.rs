@@ -0,0 +1,391 @@
+//! Reading and writing SGF (FF[4]) game records
+//!
+//! Only the main line of a record is kept, variations are skipped when parsing.
+//! See https://www.red-bean.com/sgf/ for the format.
+
+use super::board::{Board, Point, Stone};
+use super::rules::{Move, Position};
+use super::zobrist::{KoRule, PositionHistory};
+use anyhow::{anyhow, bail, Result};
+use std::fmt::Write;
+
+/// A game record as stored in an SGF file
+#[derive(Clone, PartialEq, Debug)]
+pub struct SgfGame {
+    /// `SZ`, number of columns
+    pub width: usize,
+    /// `SZ`, number of rows
+    pub height: usize,
+    /// `KM`
+    pub komi: f32,
+    /// `HA`
+    pub handicap: u32,
+    /// `RU`, kept as written (`Japanese`, `Chinese`, `AGA`...)
+    pub rules: Option<String>,
+    /// `PB`
+    pub black_name: Option<String>,
+    /// `PW`
+    pub white_name: Option<String>,
+    /// `RE`, ie `B+R`, `W+12.5`, `0` for a draw
+    pub result: Option<String>,
+    /// `AB` in the root node, usually handicap stones
+    pub setup_black: Vec<Point>,
+    /// `AW` in the root node
+    pub setup_white: Vec<Point>,
+    /// `PL` in the root node, who plays first when it is not black
+    pub first_player: Option<Stone>,
+    /// `B` and `W` properties of the main line, in order
+    pub moves: Vec<(Stone, Move)>,
+}
+
+impl SgfGame {
+    pub fn new(height: usize, width: usize) -> Self {
+        Self {
+            width,
+            height,
+            komi: 0.0,
+            handicap: 0,
+            rules: None,
+            black_name: None,
+            white_name: None,
+            result: None,
+            setup_black: Vec::new(),
+            setup_white: Vec::new(),
+            first_player: None,
+            moves: Vec::new(),
+        }
+    }
+
+    /// The board before the first move, with the setup stones
+    pub fn initial_position(&self) -> Result<Position> {
+        let mut board = Board::new(self.height, self.width)?;
+        for (points, stone) in [
+            (&self.setup_black, Stone::Black),
+            (&self.setup_white, Stone::White),
+        ] {
+            for point in points {
+                if !board.contains(*point) {
+                    bail!("Setup stone {point} is not on the board");
+                }
+                board.set(*point, Some(stone));
+            }
+        }
+        let to_move = self.first_player.unwrap_or(if self.handicap > 1 {
+            Stone::White
+        } else {
+            Stone::Black
+        });
+        Ok(Position::new(board, to_move))
+    }
+
+    /// Plays every move through the rules engine, the ko rule comes from `RU`
+    pub fn replay(&self) -> Result<PositionHistory> {
+        let rules = self.rules.as_deref().unwrap_or_default().to_lowercase();
+        let mut history =
+            PositionHistory::new(self.initial_position()?, KoRule::from_ogs_rules(&rules));
+        for (move_number, (stone, mv)) in self.moves.iter().enumerate() {
+            if history.position().to_move != *stone {
+                // SGF allows the same color to play twice, treat it as a pass in between
+                history.play(Move::Pass)?;
+            }
+            history
+                .play(*mv)
+                .map_err(|e| anyhow!(e).context(format!("Failed to replay move {move_number}")))?;
+        }
+        Ok(history)
+    }
+
+    /// The board after the last move of the main line
+    pub fn final_board(&self) -> Result<Board> {
+        Ok(self.replay()?.position().board.clone())
+    }
+
+    pub fn parse(sgf: &str) -> Result<Self> {
+        let nodes = Parser::new(sgf).main_line()?;
+        let root = nodes.first().ok_or_else(|| anyhow!("SGF has no nodes"))?;
+
+        let (width, height) = match root.get("SZ") {
+            None => (19, 19),
+            Some(size) => match size.split_once(':') {
+                Some((width, height)) => (width.trim().parse()?, height.trim().parse()?),
+                None => {
+                    let side = size.trim().parse()?;
+                    (side, side)
+                }
+            },
+        };
+        if width > 25 || height > 25 {
+            bail!("SGF board size {width}x{height} is larger than 25x25");
+        }
+
+        let mut game = SgfGame::new(height, width);
+        game.komi = root
+            .get("KM")
+            .map(|komi| komi.trim().parse())
+            .transpose()?
+            .unwrap_or(0.0);
+        game.handicap = root
+            .get("HA")
+            .map(|handicap| handicap.trim().parse())
+            .transpose()?
+            .unwrap_or(0);
+        game.rules = root.get("RU").map(str::to_string);
+        game.black_name = root.get("PB").map(str::to_string);
+        game.white_name = root.get("PW").map(str::to_string);
+        game.result = root.get("RE").map(str::to_string);
+        game.first_player = root.get("PL").map(parse_color).transpose()?;
+        game.setup_black = root
+            .get_all("AB")
+            .map(|value| parse_point_list(value, &game))
+            .collect::<Result<Vec<_>>>()?
+            .concat();
+        game.setup_white = root
+            .get_all("AW")
+            .map(|value| parse_point_list(value, &game))
+            .collect::<Result<Vec<_>>>()?
+            .concat();
+
+        for node in &nodes {
+            for (id, stone) in [("B", Stone::Black), ("W", Stone::White)] {
+                if let Some(value) = node.get(id) {
+                    game.moves.push((stone, parse_move(value, &game)?));
+                }
+            }
+        }
+        Ok(game)
+    }
+
+    pub fn to_sgf(&self) -> String {
+        let mut sgf = String::from("(;FF[4]GM[1]CA[UTF-8]AP[go_board_firmware_std]");
+        if self.width == self.height {
+            let _ = write!(sgf, "SZ[{}]", self.width);
+        } else {
+            let _ = write!(sgf, "SZ[{}:{}]", self.width, self.height);
+        }
+        let _ = write!(sgf, "KM[{}]", self.komi);
+        if self.handicap > 0 {
+            let _ = write!(sgf, "HA[{}]", self.handicap);
+        }
+        for (id, value) in [
+            ("RU", &self.rules),
+            ("PB", &self.black_name),
+            ("PW", &self.white_name),
+            ("RE", &self.result),
+        ] {
+            if let Some(value) = value {
+                let _ = write!(sgf, "{id}[{}]", escape(value));
+            }
+        }
+        if let Some(stone) = self.first_player {
+            let _ = write!(sgf, "PL[{stone}]");
+        }
+        for (id, points) in [("AB", &self.setup_black), ("AW", &self.setup_white)] {
+            if !points.is_empty() {
+                sgf.push_str(id);
+                for point in points {
+                    let _ = write!(sgf, "[{}]", point_to_sgf(*point));
+                }
+            }
+        }
+        for (stone, mv) in &self.moves {
+            let value = match mv {
+                Move::Place(point) => point_to_sgf(*point),
+                Move::Pass => String::new(),
+            };
+            let _ = write!(sgf, ";{stone}[{value}]");
+        }
+        sgf.push_str(")\n");
+        sgf
+    }
+}
+
+/// SGF coordinates are column then row, `aa` is the top left
+fn point_to_sgf(point: Point) -> String {
+    [(b'a' + point.y) as char, (b'a' + point.x) as char]
+        .iter()
+        .collect()
+}
+
+fn point_from_sgf(value: &str, game: &SgfGame) -> Result<Point> {
+    match value.as_bytes() {
+        [column @ b'a'..=b'z', row @ b'a'..=b'z'] => {
+            let point = Point::new(row - b'a', column - b'a');
+            if (point.x as usize) < game.height && (point.y as usize) < game.width {
+                Ok(point)
+            } else {
+                Err(anyhow!("SGF point {value} is not on the board"))
+            }
+        }
+        _ => Err(anyhow!("Invalid SGF point {value}")),
+    }
+}
+
+fn parse_move(value: &str, game: &SgfGame) -> Result<Move> {
+    // `tt` is the old FF[3] way to write a pass on boards up to 19x19
+    if value.is_empty() || (value == "tt" && game.width <= 19 && game.height <= 19) {
+        Ok(Move::Pass)
+    } else {
+        point_from_sgf(value, game).map(Move::Place)
+    }
+}
+
+/// A single point or a compressed rectangle (`aa:cc`)
+fn parse_point_list(value: &str, game: &SgfGame) -> Result<Vec<Point>> {
+    match value.split_once(':') {
+        None => Ok(vec![point_from_sgf(value, game)?]),
+        Some((from, to)) => {
+            let (from, to) = (point_from_sgf(from, game)?, point_from_sgf(to, game)?);
+            let mut points = Vec::new();
+            for x in from.x.min(to.x)..=from.x.max(to.x) {
+                for y in from.y.min(to.y)..=from.y.max(to.y) {
+                    points.push(Point::new(x, y));
+                }
+            }
+            Ok(points)
+        }
+    }
+}
+
+fn parse_color(value: &str) -> Result<Stone> {
+    match value.trim() {
+        "B" | "b" => Ok(Stone::Black),
+        "W" | "w" => Ok(Stone::White),
+        _ => Err(anyhow!("Invalid SGF color {value}")),
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace(']', "\\]")
+}
+
+/// The properties of one node, values are unescaped
+#[derive(Debug, Default)]
+struct Node(Vec<(String, String)>);
+
+impl Node {
+    fn get(&self, id: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|(key, _)| key == id)
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn get_all<'n>(&'n self, id: &'n str) -> impl Iterator<Item = &'n str> + 'n {
+        self.0
+            .iter()
+            .filter(move |(key, _)| key == id)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+struct Parser<'s> {
+    chars: std::iter::Peekable<std::str::Chars<'s>>,
+}
+
+impl<'s> Parser<'s> {
+    fn new(sgf: &'s str) -> Self {
+        Self {
+            chars: sgf.chars().peekable(),
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.chars.next_if(|c| c.is_whitespace()).is_some() {}
+    }
+
+    fn expect(&mut self, expected: char) -> Result<()> {
+        self.skip_whitespace();
+        match self.chars.next() {
+            Some(c) if c == expected => Ok(()),
+            Some(c) => Err(anyhow!("Expected '{expected}' in SGF, found '{c}'")),
+            None => Err(anyhow!("Expected '{expected}' in SGF, found the end")),
+        }
+    }
+
+    /// Nodes of the first game tree, following the first variation at every branch
+    fn main_line(&mut self) -> Result<Vec<Node>> {
+        let mut nodes = Vec::new();
+        self.game_tree(&mut nodes, true)?;
+        Ok(nodes)
+    }
+
+    /// `( sequence { game_tree } )`, nodes are only kept when `keep` is set
+    fn game_tree(&mut self, nodes: &mut Vec<Node>, keep: bool) -> Result<()> {
+        self.expect('(')?;
+        loop {
+            self.skip_whitespace();
+            match self.chars.peek() {
+                Some(';') => {
+                    self.chars.next();
+                    let node = self.node()?;
+                    if keep {
+                        nodes.push(node);
+                    }
+                }
+                _ => break,
+            }
+        }
+        let mut first_variation = true;
+        loop {
+            self.skip_whitespace();
+            match self.chars.peek() {
+                Some('(') => {
+                    self.game_tree(nodes, keep && first_variation)?;
+                    first_variation = false;
+                }
+                _ => break,
+            }
+        }
+        self.expect(')')
+    }
+
+    fn node(&mut self) -> Result<Node> {
+        let mut node = Node::default();
+        loop {
+            self.skip_whitespace();
+            let mut id = String::new();
+            // FF[3] allowed lower case letters in ids (ie `AddBlack`), only the upper case ones count
+            while let Some(c) = self.chars.next_if(|c| c.is_ascii_alphabetic()) {
+                if c.is_ascii_uppercase() {
+                    id.push(c);
+                }
+            }
+            if id.is_empty() {
+                return Ok(node);
+            }
+            self.skip_whitespace();
+            if self.chars.peek() != Some(&'[') {
+                bail!("Property {id} in SGF has no value");
+            }
+            while self.chars.peek() == Some(&'[') {
+                self.chars.next();
+                node.0.push((id.clone(), self.value()?));
+                self.skip_whitespace();
+            }
+        }
+    }
+
+    /// The text up to the closing `]`, with escapes removed
+    fn value(&mut self) -> Result<String> {
+        let mut value = String::new();
+        loop {
+            match self.chars.next() {
+                Some(']') => return Ok(value),
+                Some('\\') => match self.chars.next() {
+                    // escaped line breaks are removed
+                    Some('\n') => {
+                        self.chars.next_if_eq(&'\r');
+                    }
+                    Some('\r') => {
+                        self.chars.next_if_eq(&'\n');
+                    }
+                    Some(c) => value.push(c),
+                    None => break,
+                },
+                Some(c) => value.push(c),
+                None => break,
+            }
+        }
+        Err(anyhow!("SGF property value is missing its closing ']'"))
+    }
+}
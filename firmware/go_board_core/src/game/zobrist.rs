@@ -0,0 +1,137 @@
+use super::board::{Board, Point, Stone};
+use super::rules::{IllegalMove, Move, MoveOutcome, Position};
+
+/// Largest board side online-go allows, the key table covers every point up to this size
+pub const MAX_BOARD_SIDE: usize = 25;
+
+/// One key per (point, stone) pair, generated at compile time so the table lives in flash
+/// instead of RAM
+const POINT_KEYS: [[u64; 2]; MAX_BOARD_SIDE * MAX_BOARD_SIDE] = generate_point_keys();
+
+/// Mixed into the hash when white is the player to move, used for situational superko
+const WHITE_TO_MOVE_KEY: u64 = split_mix(0x5EED_0F60_B0A2_D000);
+
+/// splitmix64 step, the output of a counter through it is random enough for hashing positions
+const fn split_mix(state: u64) -> u64 {
+    let mut z = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+const fn generate_point_keys() -> [[u64; 2]; MAX_BOARD_SIDE * MAX_BOARD_SIDE] {
+    let mut keys = [[0; 2]; MAX_BOARD_SIDE * MAX_BOARD_SIDE];
+    let mut i = 0;
+    while i < keys.len() {
+        keys[i][0] = split_mix((2 * i) as u64);
+        keys[i][1] = split_mix((2 * i + 1) as u64);
+        i += 1;
+    }
+    keys
+}
+
+/// The key to xor in (or out) when `stone` is placed on (or removed from) `point`
+pub fn point_key(point: Point, stone: Stone) -> u64 {
+    let index = point.x as usize * MAX_BOARD_SIDE + point.y as usize;
+    let color = match stone {
+        Stone::Black => 0,
+        Stone::White => 1,
+    };
+    POINT_KEYS[index][color]
+}
+
+/// Hashes a whole board from scratch, only needed once, after that
+/// [`Position::play`] keeps [`Position::hash`] up to date incrementally
+pub fn hash_board(board: &Board) -> u64 {
+    board
+        .iter()
+        .filter_map(|(point, stone)| stone.map(|stone| point_key(point, stone)))
+        .fold(0, |hash, key| hash ^ key)
+}
+
+/// How repeated positions are handled, depends on the rule set of the game
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+pub enum KoRule {
+    /// only the immediate recapture of a single stone is forbidden
+    Simple,
+    /// a move may not recreate any earlier board
+    PositionalSuperko,
+    /// a move may not recreate any earlier board with the same player to move
+    SituationalSuperko,
+}
+
+impl KoRule {
+    /// Maps the `rules` string online-go reports for a game
+    pub fn from_ogs_rules(rules: &str) -> Self {
+        match rules {
+            "chinese" | "ing" => KoRule::PositionalSuperko,
+            "aga" | "nz" => KoRule::SituationalSuperko,
+            // japanese, korean and anything unknown
+            _ => KoRule::Simple,
+        }
+    }
+}
+
+/// The current position plus the hashes of every position before it
+///
+/// Each entry is a single `u64`, so even a few hundred moves only use a few KB
+#[derive(Clone, Debug)]
+pub struct PositionHistory {
+    rule: KoRule,
+    position: Position,
+    seen: Vec<u64>,
+}
+
+impl PositionHistory {
+    pub fn new(position: Position, rule: KoRule) -> Self {
+        let mut history = Self {
+            rule,
+            position,
+            seen: Vec::with_capacity(256),
+        };
+        history.seen.push(history.key(&history.position));
+        history
+    }
+
+    pub fn position(&self) -> &Position {
+        &self.position
+    }
+
+    pub fn rule(&self) -> KoRule {
+        self.rule
+    }
+
+    /// Number of positions recorded, including the starting one
+    pub fn positions_seen(&self) -> usize {
+        self.seen.len()
+    }
+
+    fn key(&self, position: &Position) -> u64 {
+        match self.rule {
+            KoRule::Simple | KoRule::PositionalSuperko => position.hash,
+            KoRule::SituationalSuperko => match position.to_move {
+                Stone::Black => position.hash,
+                Stone::White => position.hash ^ WHITE_TO_MOVE_KEY,
+            },
+        }
+    }
+
+    /// Checks a move against both the basic rules and the superko rule, without playing it
+    pub fn check(&self, mv: Move) -> Result<MoveOutcome, IllegalMove> {
+        let outcome = self.position.play_move(mv)?;
+        if let Move::Place(point) = mv {
+            if self.rule != KoRule::Simple && self.seen.contains(&self.key(&outcome.position)) {
+                return Err(IllegalMove::Superko(point));
+            }
+        }
+        Ok(outcome)
+    }
+
+    /// Plays a move, returning the stones it captured
+    pub fn play(&mut self, mv: Move) -> Result<Vec<Point>, IllegalMove> {
+        let MoveOutcome { position, captured } = self.check(mv)?;
+        self.seen.push(self.key(&position));
+        self.position = position;
+        Ok(captured)
+    }
+}
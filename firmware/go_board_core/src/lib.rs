@@ -0,0 +1,11 @@
+//! Everything of the board firmware that does not touch esp-idf: the rules, what the leds show
+//! and the online-go api. The firmware wires it to the hardware, this crate also builds on a
+//! computer so it can be tested there, the api against `mock-online-go`
+
+pub mod encoder;
+pub mod game;
+pub mod neopixel;
+pub mod onlinego;
+
+/// leds on each side of the square grid
+pub const BOARD_SIZE: usize = 16;
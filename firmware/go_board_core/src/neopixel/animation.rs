@@ -0,0 +1,148 @@
+use std::time::Duration;
+
+use tokio::time::{self, Instant, Interval, MissedTickBehavior};
+
+use super::frame::Frame;
+use super::led_ctrl::LedChange;
+
+/// How a layer changes over time, the led task redraws it on every tick of its [`FrameClock`]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Animation {
+    /// shown for the first half of the period, hidden for the second
+    Blink { period: Duration },
+    /// the opacity goes from `min` up to full and back down once per period
+    Pulse { period: Duration, min: u8 },
+    /// from hidden to fully shown, then the layer stays shown
+    FadeIn { duration: Duration },
+    /// from shown to hidden, then the layer is cleared
+    FadeOut { duration: Duration },
+}
+
+/// An animation playing on a layer
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Running {
+    Opacity(Animation, Instant),
+    /// the layer's new frame fading in over what it showed before
+    CrossFade {
+        from: Box<Frame>,
+        started: Instant,
+        duration: Duration,
+    },
+}
+
+impl Running {
+    pub fn start(animation: Animation) -> Self {
+        Self::Opacity(animation, Instant::now())
+    }
+
+    pub fn cross_fade(from: Frame, duration: Duration) -> Self {
+        Self::CrossFade {
+            from: Box::new(from),
+            started: Instant::now(),
+            duration,
+        }
+    }
+
+    /// The opacity of the layer at `now`, 255 is fully shown
+    pub fn opacity(&self, now: Instant) -> u8 {
+        let Self::Opacity(animation, started) = self else {
+            return u8::MAX;
+        };
+        let elapsed = now.saturating_duration_since(*started);
+        match *animation {
+            Animation::Blink { period } => {
+                if phase(elapsed, period) < 0.5 {
+                    u8::MAX
+                } else {
+                    0
+                }
+            }
+            Animation::Pulse { period, min } => {
+                // a triangle wave, starting from `min`
+                let level = 1.0 - (2.0 * phase(elapsed, period) - 1.0).abs();
+                min + ((u8::MAX - min) as f32 * level).round() as u8
+            }
+            Animation::FadeIn { duration } => to_opacity(progress(elapsed, duration)),
+            Animation::FadeOut { duration } => to_opacity(1.0 - progress(elapsed, duration)),
+        }
+    }
+
+    /// What the layer shows at `now` instead of `frame`, only cross-fades change the frame
+    pub fn frame(&self, frame: &Frame, now: Instant) -> Option<Frame> {
+        let Self::CrossFade {
+            from,
+            started,
+            duration,
+        } = self
+        else {
+            return None;
+        };
+        let opacity = to_opacity(progress(now.saturating_duration_since(*started), *duration));
+        let mut mixed = **from;
+        for change in frame.changes() {
+            let below = from.get(change.x, change.y).unwrap_or(change.color);
+            mixed.set(LedChange {
+                color: change.color.over(below, opacity),
+                ..change
+            });
+        }
+        Some(mixed)
+    }
+
+    /// Finished animations are dropped, looping ones never finish
+    pub fn is_done(&self, now: Instant) -> bool {
+        let (started, duration) = match self {
+            Self::Opacity(Animation::FadeIn { duration }, started)
+            | Self::Opacity(Animation::FadeOut { duration }, started) => (started, duration),
+            Self::CrossFade {
+                started, duration, ..
+            } => (started, duration),
+            Self::Opacity(..) => return false,
+        };
+        now.saturating_duration_since(*started) >= *duration
+    }
+
+    /// The layer is cleared once the animation is done
+    pub fn clears_layer(&self) -> bool {
+        matches!(self, Self::Opacity(Animation::FadeOut { .. }, _))
+    }
+}
+
+/// How far into the current period, from 0 to 1
+fn phase(elapsed: Duration, period: Duration) -> f32 {
+    if period.is_zero() {
+        return 0.0;
+    }
+    (elapsed.as_secs_f32() % period.as_secs_f32()) / period.as_secs_f32()
+}
+
+/// How far into the animation, from 0 to 1
+fn progress(elapsed: Duration, duration: Duration) -> f32 {
+    if duration.is_zero() {
+        return 1.0;
+    }
+    (elapsed.as_secs_f32() / duration.as_secs_f32()).min(1.0)
+}
+
+fn to_opacity(progress: f32) -> u8 {
+    (u8::MAX as f32 * progress.clamp(0.0, 1.0)).round() as u8
+}
+
+/// Ticks at a fixed frame rate while animations play.
+/// Late ticks are skipped rather than bunched up, a slow refresh only lowers the frame rate
+pub struct FrameClock {
+    interval: Interval,
+}
+
+impl FrameClock {
+    pub fn new(fps: u32) -> Self {
+        let mut interval = time::interval(Duration::from_secs(1) / fps.max(1));
+        interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+        Self { interval }
+    }
+
+    /// Waits for the next frame, returns its time
+    pub async fn tick(&mut self) -> Instant {
+        self.interval.tick().await
+    }
+}
@@ -0,0 +1,180 @@
+use super::animation::Animation;
+use super::led_ctrl::{Layer, LedChange};
+use super::rgb::Rgb;
+use crate::BOARD_SIZE;
+use anyhow::{anyhow, Result};
+use std::time::Duration;
+use tokio::sync::mpsc::{self, Receiver, Sender};
+
+/// updates waiting for the led task, a sender that gets ahead of the strip waits for it
+const FRAME_QUEUE_SIZE: usize = 4;
+
+const OFF: Rgb = Rgb::new(0, 0, 0);
+
+/// Everything the matrix shows at once, indexed like [`LedChange`]: x is the row, y the column
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Frame {
+    pixels: [[Rgb; BOARD_SIZE]; BOARD_SIZE],
+}
+
+impl Default for Frame {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Frame {
+    /// Every led off
+    pub const fn new() -> Self {
+        Self {
+            pixels: [[OFF; BOARD_SIZE]; BOARD_SIZE],
+        }
+    }
+
+    /// The leds not in `changes` are off, changes outside of the matrix are dropped
+    pub fn from_changes(changes: impl IntoIterator<Item = LedChange>) -> Self {
+        let mut frame = Self::new();
+        for change in changes {
+            frame.set(change);
+        }
+        frame
+    }
+
+    /// `None` outside of the matrix
+    pub fn get(&self, x: u8, y: u8) -> Option<Rgb> {
+        self.pixels.get(x as usize)?.get(y as usize).copied()
+    }
+
+    /// Changes outside of the matrix are dropped
+    pub fn set(&mut self, change: LedChange) {
+        if let Some(pixel) = self
+            .pixels
+            .get_mut(change.x as usize)
+            .and_then(|row| row.get_mut(change.y as usize))
+        {
+            *pixel = change.color;
+        }
+    }
+
+    /// Every led of the frame, row by row
+    pub fn changes(&self) -> impl Iterator<Item = LedChange> + '_ {
+        self.pixels.iter().enumerate().flat_map(|(x, row)| {
+            row.iter()
+                .enumerate()
+                .map(move |(y, color)| LedChange::new(x as u8, y as u8, *color))
+        })
+    }
+}
+
+/// A rectangle of leds, `x, y` is its top left corner
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Region {
+    pub x: u8,
+    pub y: u8,
+    pub height: u8,
+    pub width: u8,
+}
+
+impl Region {
+    pub fn contains(&self, x: u8, y: u8) -> bool {
+        (self.x..self.x.saturating_add(self.height)).contains(&x)
+            && (self.y..self.y.saturating_add(self.width)).contains(&y)
+    }
+}
+
+/// What the led task draws, each update is shown whole in a single refresh
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum FrameUpdate {
+    /// replaces everything on the layer
+    Full(Layer, Box<Frame>),
+    /// only the leds inside the region are taken from the frame, the others stay as they are
+    Region(Layer, Box<Frame>, Region),
+    /// empties the layer, uncovering the layers below
+    Clear(Layer),
+    /// plays the animation on the layer, `None` stops it
+    Animate(Layer, Option<Animation>),
+    /// replaces everything on the layer, fading from what it showed before
+    CrossFade(Layer, Box<Frame>, Duration),
+}
+
+/// Sends frames to the led task, cheap to clone
+#[derive(Clone, Debug)]
+pub struct LedDisplay {
+    tx: Sender<FrameUpdate>,
+}
+
+/// A display and the receiving end to hand to [`super::led_ctrl::led_ctrl`]
+pub fn channel() -> (LedDisplay, Receiver<FrameUpdate>) {
+    let (tx, rx) = mpsc::channel(FRAME_QUEUE_SIZE);
+    (LedDisplay { tx }, rx)
+}
+
+impl LedDisplay {
+    async fn send(&self, update: FrameUpdate) -> Result<()> {
+        self.tx
+            .send(update)
+            .await
+            .map_err(|_| anyhow!("Led task stopped, can't show the frame"))
+    }
+
+    /// Replaces everything on the board layer with `frame`
+    pub async fn show(&self, frame: Frame) -> Result<()> {
+        self.show_on(Layer::Board, frame).await
+    }
+
+    /// Redraws only `region` of the board layer, from `frame`
+    pub async fn show_region(&self, frame: Frame, region: Region) -> Result<()> {
+        self.show_region_on(Layer::Board, frame, region).await
+    }
+
+    /// Replaces everything on the board layer, the leds not in `changes` are turned off
+    pub async fn show_changes(&self, changes: impl IntoIterator<Item = LedChange>) -> Result<()> {
+        self.show(Frame::from_changes(changes)).await
+    }
+
+    /// Replaces everything on `layer` with `frame`
+    pub async fn show_on(&self, layer: Layer, frame: Frame) -> Result<()> {
+        self.send(FrameUpdate::Full(layer, Box::new(frame))).await
+    }
+
+    /// Redraws only `region` of `layer`, from `frame`
+    pub async fn show_region_on(&self, layer: Layer, frame: Frame, region: Region) -> Result<()> {
+        self.send(FrameUpdate::Region(layer, Box::new(frame), region))
+            .await
+    }
+
+    /// Empties `layer`, the layers below show again without being redrawn
+    pub async fn clear(&self, layer: Layer) -> Result<()> {
+        self.send(FrameUpdate::Clear(layer)).await
+    }
+
+    /// Plays `animation` on `layer` until it is done, stopped or the layer is cleared.
+    /// The layer keeps animating when it is redrawn
+    pub async fn animate(&self, layer: Layer, animation: Animation) -> Result<()> {
+        self.send(FrameUpdate::Animate(layer, Some(animation)))
+            .await
+    }
+
+    /// Shows `layer` as it is, without its animation
+    pub async fn stop_animation(&self, layer: Layer) -> Result<()> {
+        self.send(FrameUpdate::Animate(layer, None)).await
+    }
+
+    /// Replaces everything on `layer` with `frame`, fading it in from hidden
+    pub async fn fade_in(&self, layer: Layer, frame: Frame, duration: Duration) -> Result<()> {
+        // started before the frame arrives so it is never shown at full brightness first
+        self.animate(layer, Animation::FadeIn { duration }).await?;
+        self.show_on(layer, frame).await
+    }
+
+    /// Fades `layer` out, then clears it
+    pub async fn fade_out(&self, layer: Layer, duration: Duration) -> Result<()> {
+        self.animate(layer, Animation::FadeOut { duration }).await
+    }
+
+    /// Replaces everything on `layer` with `frame`, fading from what it showed before
+    pub async fn cross_fade(&self, layer: Layer, frame: Frame, duration: Duration) -> Result<()> {
+        self.send(FrameUpdate::CrossFade(layer, Box::new(frame), duration))
+            .await
+    }
+}
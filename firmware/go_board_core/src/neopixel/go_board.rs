@@ -0,0 +1,172 @@
+use anyhow::Result;
+use super::animation::Animation;
+use super::frame::{Frame, LedDisplay};
+use super::led_ctrl::{Layer, LedChange};
+use super::rgb::Rgb;
+use super::viewport::Viewport;
+use crate::game::board::{Board, Point, Stone};
+use crate::BOARD_SIZE;
+use std::time::Duration;
+
+pub const BLACK_STONE: Rgb = Rgb::new(50, 0, 0);
+pub const WHITE_STONE: Rgb = Rgb::new(0, 50, 0);
+pub const EMPTY_POINT: Rgb = Rgb::new(0, 0, 0);
+/// the most recently played stone is drawn brighter than the rest
+pub const LAST_BLACK_STONE: Rgb = Rgb::new(120, 0, 0);
+pub const LAST_WHITE_STONE: Rgb = Rgb::new(0, 120, 0);
+
+pub const fn stone_color(stone: Option<Stone>) -> Rgb {
+    match stone {
+        Some(Stone::Black) => BLACK_STONE,
+        Some(Stone::White) => WHITE_STONE,
+        None => EMPTY_POINT,
+    }
+}
+
+pub const fn last_move_color(stone: Stone) -> Rgb {
+    match stone {
+        Stone::Black => LAST_BLACK_STONE,
+        Stone::White => LAST_WHITE_STONE,
+    }
+}
+
+/// drawn on empty points along an edge of the window when there is more board past it
+pub const MORE_BOARD_MARKER: Rgb = Rgb::new(0, 0, 35);
+/// the line just outside the edge of boards smaller than the matrix
+pub const BOARD_FRAME: Rgb = Rgb::new(35, 35, 35);
+/// empty star points
+pub const HOSHI: Rgb = Rgb::new(35, 0, 35);
+
+/// Every led of the matrix for `board`, leds that are not on the board are turned off
+/// (or show the frame) so whatever was shown before is cleared
+pub fn render_board(board: &Board, viewport: &Viewport) -> Vec<LedChange> {
+    render_board_with(board, viewport, |_, stone| stone_color(stone))
+}
+
+/// Like [`render_board`] but lets the caller pick the color of each point, ie to dim dead stones.
+/// The cursor and the last move go on their own layers, see [`render_points`].
+/// Star points are drawn where `color_of` leaves an empty point off
+pub fn render_board_with(
+    board: &Board,
+    viewport: &Viewport,
+    color_of: impl Fn(Point, Option<Stone>) -> Rgb,
+) -> Vec<LedChange> {
+    let star_points = board.star_points();
+    let mut changes = Vec::with_capacity(BOARD_SIZE * BOARD_SIZE);
+    for x in 0..BOARD_SIZE as u8 {
+        for y in 0..BOARD_SIZE as u8 {
+            let color = match viewport.to_board(x, y) {
+                Some(point) if board.contains(point) => {
+                    let stone = board.get(point);
+                    let hidden_past_edge =
+                        Viewport::edges_at(x, y).any(|edge| viewport.has_more_past(edge));
+                    if stone.is_none() && hidden_past_edge {
+                        MORE_BOARD_MARKER
+                    } else {
+                        match color_of(point, stone) {
+                            color if color.is_off() && star_points.contains(&point) => HOSHI,
+                            color => color,
+                        }
+                    }
+                }
+                _ if viewport.is_frame(x, y) => BOARD_FRAME,
+                _ => EMPTY_POINT,
+            };
+            changes.push(LedChange::new(x, y, color));
+        }
+    }
+    changes
+}
+
+/// The leds showing `points` in `color`, the points outside the window are left out.
+/// Used for the layers drawn over the board, where every other led is off
+pub fn render_points(
+    viewport: &Viewport,
+    points: impl IntoIterator<Item = Point>,
+    color: Rgb,
+) -> Vec<LedChange> {
+    points
+        .into_iter()
+        .filter_map(|point| viewport.to_led(point))
+        .map(|(x, y)| LedChange::new(x, y, color))
+        .collect()
+}
+
+pub const CURSOR_BLINK: Animation = Animation::Blink {
+    period: Duration::from_millis(800),
+};
+/// between the plain stone and the brighter one
+pub const LAST_MOVE_PULSE: Animation = Animation::Pulse {
+    period: Duration::from_millis(1500),
+    min: 0,
+};
+/// how long a played stone takes to fade in, and its captures to fade out
+pub const MOVE_FADE: Duration = Duration::from_millis(300);
+
+/// Shows a board on [`Layer::Board`] with its last move and cursor on their own layers,
+/// an empty marker clears its layer
+pub async fn show_board_layers(
+    display: &LedDisplay,
+    board: Vec<LedChange>,
+    last_move: Vec<LedChange>,
+    cursor: Vec<LedChange>,
+) -> Result<()> {
+    display.show_changes(board).await?;
+    show_markers(display, last_move, cursor).await
+}
+
+/// Like [`show_board_layers`] but the board fades over [`MOVE_FADE`] from what it showed,
+/// to show a move being played or undone
+pub async fn fade_board_layers(
+    display: &LedDisplay,
+    board: Vec<LedChange>,
+    last_move: Vec<LedChange>,
+    cursor: Vec<LedChange>,
+) -> Result<()> {
+    display
+        .cross_fade(Layer::Board, Frame::from_changes(board), MOVE_FADE)
+        .await?;
+    show_markers(display, last_move, cursor).await
+}
+
+async fn show_markers(
+    display: &LedDisplay,
+    last_move: Vec<LedChange>,
+    cursor: Vec<LedChange>,
+) -> Result<()> {
+    display
+        .show_on(Layer::LastMove, Frame::from_changes(last_move))
+        .await?;
+    display
+        .show_on(Layer::Cursor, Frame::from_changes(cursor))
+        .await
+}
+
+/// Starts the cursor blinking and the last move pulsing, they keep going while the board is
+/// redrawn until the layers are cleared
+pub async fn animate_markers(display: &LedDisplay) -> Result<()> {
+    display.animate(Layer::Cursor, CURSOR_BLINK).await?;
+    display.animate(Layer::LastMove, LAST_MOVE_PULSE).await
+}
+
+/// Every led of the matrix, the ones not in `changes` are turned off
+pub fn render_screen(changes: impl IntoIterator<Item = LedChange>) -> Vec<LedChange> {
+    let mut screen: Vec<LedChange> = (0..BOARD_SIZE * BOARD_SIZE)
+        .map(|i| LedChange::new((i / BOARD_SIZE) as u8, (i % BOARD_SIZE) as u8, EMPTY_POINT))
+        .collect();
+    for change in changes {
+        if (change.x as usize) < BOARD_SIZE && (change.y as usize) < BOARD_SIZE {
+            screen[change.x as usize * BOARD_SIZE + change.y as usize] = change;
+        }
+    }
+    screen
+}
+
+/// Shows the top left of boards that are too large for the matrix, smaller ones are centered,
+/// use [`render_board`] with a [`Viewport`] that follows the action for those
+pub async fn show_board(display: &LedDisplay, board: &[Vec<i32>]) -> Result<()> {
+    let board = Board::from_rows(board)?;
+    display
+        .show_changes(render_board(&board, &Viewport::new(&board)))
+        .await
+}
@@ -0,0 +1,260 @@
+use std::fmt::{Display, Formatter};
+
+use anyhow::Result;
+use tokio::time::Instant;
+
+use super::animation::Running;
+use super::frame::{Frame, FrameUpdate, LedDisplay};
+use super::rgb::Rgb;
+
+// only awaited on the board's single threaded runtime, the futures never need to be `Send`
+#[allow(async_fn_in_trait)]
+pub trait DisplayOnLeds {
+    async fn display(&self, display: &LedDisplay) -> Result<()>;
+}
+
+/// The named layers of the picture, from the bottom up.
+/// A higher layer is drawn over the ones below following its [`LayerStyle`]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+pub enum Layer {
+    /// the stones, or whatever screen is showing
+    Board,
+    /// the stone played last, drawn brighter over the board
+    LastMove,
+    /// the point or group the encoder is on
+    Cursor,
+    /// messages and screens shown over the board for a while, like the score
+    Notification,
+    Error,
+}
+
+impl Layer {
+    pub const ALL: [Layer; 5] = [
+        Layer::Board,
+        Layer::LastMove,
+        Layer::Cursor,
+        Layer::Notification,
+        Layer::Error,
+    ];
+
+    const fn index(self) -> usize {
+        self as usize
+    }
+
+    /// How the layer is drawn over the ones below
+    pub const fn style(self) -> LayerStyle {
+        match self {
+            Layer::Notification => LayerStyle::new(BlendMode::Opaque, 255),
+            _ => LayerStyle::new(BlendMode::Over, 255),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BlendMode {
+    /// lit leds are drawn over the layers below, off leds let them show through
+    Over,
+    /// lit leds are added to the layers below, to highlight without hiding them
+    Add,
+    /// while anything is lit on the layer it hides everything below, off leds included
+    Opaque,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct LayerStyle {
+    pub blend: BlendMode,
+    /// 255 is fully opaque, 0 hides the layer
+    pub opacity: u8,
+}
+
+impl LayerStyle {
+    pub const fn new(blend: BlendMode, opacity: u8) -> Self {
+        Self { blend, opacity }
+    }
+}
+
+/// A frame for each [`Layer`], composited into the picture the strip shows.
+/// Every layer keeps its own leds so clearing one uncovers the layers below it
+pub struct LayerStack {
+    frames: [Frame; Layer::ALL.len()],
+    animations: [Option<Running>; Layer::ALL.len()],
+}
+
+impl Default for LayerStack {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LayerStack {
+    /// All layers empty
+    pub fn new() -> Self {
+        Self {
+            frames: [Frame::new(); Layer::ALL.len()],
+            animations: Default::default(),
+        }
+    }
+
+    pub fn frame(&self, layer: Layer) -> &Frame {
+        &self.frames[layer.index()]
+    }
+
+    pub fn apply(&mut self, update: &FrameUpdate) {
+        match update {
+            FrameUpdate::Full(layer, frame) => self.frames[layer.index()] = **frame,
+            FrameUpdate::Region(layer, frame, region) => {
+                let target = &mut self.frames[layer.index()];
+                for change in frame.changes() {
+                    if region.contains(change.x, change.y) {
+                        target.set(change);
+                    }
+                }
+            }
+            FrameUpdate::Clear(layer) => {
+                self.frames[layer.index()] = Frame::new();
+                self.animations[layer.index()] = None;
+            }
+            FrameUpdate::Animate(layer, animation) => {
+                self.animations[layer.index()] = animation.map(Running::start);
+            }
+            FrameUpdate::CrossFade(layer, frame, duration) => {
+                let from = std::mem::replace(&mut self.frames[layer.index()], **frame);
+                self.animations[layer.index()] = Some(Running::cross_fade(from, *duration));
+            }
+        }
+    }
+
+    /// True while a layer has an animation playing, the picture changes without updates
+    pub fn is_animating(&self) -> bool {
+        self.animations.iter().any(Option::is_some)
+    }
+
+    /// Drops the animations that are done at `now`, clearing the layers that faded out
+    pub fn finish_animations(&mut self, now: Instant) {
+        for layer in Layer::ALL {
+            let animation = &mut self.animations[layer.index()];
+            let Some(done) = animation.take_if(|running| running.is_done(now)) else {
+                continue;
+            };
+            if done.clears_layer() {
+                self.frames[layer.index()] = Frame::new();
+            }
+        }
+    }
+
+    /// Every layer drawn over the ones below it, as the animations show them at `now`
+    pub fn composite(&self, now: Instant) -> Frame {
+        let mut picture = Frame::new();
+        for layer in Layer::ALL {
+            let LayerStyle { blend, mut opacity } = layer.style();
+            let animation = self.animations[layer.index()].as_ref();
+            let animated = animation.and_then(|running| running.frame(self.frame(layer), now));
+            let frame = animated.as_ref().unwrap_or(self.frame(layer));
+            if let Some(running) = animation {
+                opacity = (opacity as u16 * running.opacity(now) as u16 / 255) as u8;
+            }
+            if opacity == 0 {
+                continue;
+            }
+            let covers = blend == BlendMode::Opaque && frame.changes().any(|c| !c.color.is_off());
+            for change in frame.changes() {
+                if change.color.is_off() && !covers {
+                    continue;
+                }
+                let below = picture.get(change.x, change.y).unwrap_or(Rgb::new(0, 0, 0));
+                let color = match blend {
+                    BlendMode::Over | BlendMode::Opaque => change.color.over(below, opacity),
+                    BlendMode::Add => change.color.add(below, opacity),
+                };
+                picture.set(LedChange { color, ..change });
+            }
+        }
+        picture
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, PartialOrd, Eq, Ord, Debug, Hash)]
+pub struct LedChange {
+    pub x: u8,
+    pub y: u8,
+    pub color: Rgb,
+}
+
+impl Display for LedChange {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let LedChange { x, y, color } = self;
+        write!(f, "({x},{y},{color})")
+    }
+}
+
+impl LedChange {
+    pub fn new(x: u8, y: u8, color: Rgb) -> Self {
+        Self { x, y, color }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::neopixel::animation::Animation;
+    use crate::neopixel::go_board::{CURSOR_BLINK, LAST_MOVE_PULSE, MOVE_FADE};
+
+    const STONE: Rgb = Rgb::raw(100, 0, 0);
+    const MARKER: Rgb = Rgb::raw(0, 0, 200);
+
+    fn one_led(color: Rgb) -> Box<Frame> {
+        Box::new(Frame::from_changes([LedChange::new(1, 1, color)]))
+    }
+
+    fn led(stack: &LayerStack, now: Instant) -> Rgb {
+        stack.composite(now).get(1, 1).unwrap()
+    }
+
+    // the clock is paused so every `Instant::now` is the same
+    #[tokio::test(start_paused = true)]
+    async fn played_stones_fade_in() {
+        let mut stack = LayerStack::new();
+        let start = Instant::now();
+        stack.apply(&FrameUpdate::CrossFade(
+            Layer::Board,
+            one_led(STONE),
+            MOVE_FADE,
+        ));
+        assert_eq!(led(&stack, start), Rgb::raw(0, 0, 0));
+        assert_eq!(led(&stack, start + MOVE_FADE / 2), Rgb::raw(50, 0, 0));
+        assert_eq!(led(&stack, start + MOVE_FADE), STONE);
+        stack.finish_animations(start + MOVE_FADE);
+        assert!(!stack.is_animating());
+        assert_eq!(stack.frame(Layer::Board).get(1, 1), Some(STONE));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn markers_keep_animating_when_redrawn() {
+        let mut stack = LayerStack::new();
+        let start = Instant::now();
+        stack.apply(&FrameUpdate::Full(Layer::Board, one_led(STONE)));
+        stack.apply(&FrameUpdate::Animate(Layer::Cursor, Some(CURSOR_BLINK)));
+        stack.apply(&FrameUpdate::Full(Layer::Cursor, one_led(MARKER)));
+        let Animation::Blink { period } = CURSOR_BLINK else {
+            unreachable!()
+        };
+        assert_eq!(led(&stack, start), MARKER);
+        // the stone shows through while the cursor is off
+        assert_eq!(led(&stack, start + period * 3 / 4), STONE);
+
+        stack.apply(&FrameUpdate::Animate(
+            Layer::LastMove,
+            Some(LAST_MOVE_PULSE),
+        ));
+        stack.apply(&FrameUpdate::Clear(Layer::Cursor));
+        stack.apply(&FrameUpdate::Full(Layer::LastMove, one_led(MARKER)));
+        let Animation::Pulse { period, .. } = LAST_MOVE_PULSE else {
+            unreachable!()
+        };
+        assert_eq!(led(&stack, start + period / 2), MARKER);
+        // the plain stone at the bottom of the pulse
+        assert_eq!(led(&stack, start + period), STONE);
+        stack.finish_animations(start + period * 10);
+        assert!(stack.is_animating());
+    }
+}
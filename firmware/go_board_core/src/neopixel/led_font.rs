@@ -0,0 +1,449 @@
+use crate::game::board::Stone;
+use crate::game::scoring::Score;
+use crate::neopixel::frame::{Frame, LedDisplay};
+use crate::neopixel::go_board::{last_move_color, stone_color};
+use crate::neopixel::led_ctrl::{DisplayOnLeds, Layer, LedChange};
+use crate::neopixel::rgb::Rgb;
+use crate::BOARD_SIZE;
+use anyhow::Result;
+use std::time::Duration;
+use tokio::time::{self, Instant};
+
+/// the blank column after every glyph
+pub const GLYPH_SPACING: usize = 1;
+
+/// how long each step of text too wide for the matrix is shown
+const MARQUEE_STEP: Duration = Duration::from_millis(300);
+/// put between the end of scrolling text and its start coming around again
+const MARQUEE_GAP: &str = " ";
+
+/// The sizes of the led font, both cover A-Z, 0-9 and common punctuation.
+/// Lowercase is drawn as uppercase, anything unknown as a `?`
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Font {
+    /// 3x5, four glyphs fit across the matrix
+    Small,
+    /// 5x7, easier to read from across the room
+    Large,
+}
+
+impl Font {
+    pub const fn width(self) -> usize {
+        match self {
+            Font::Small => 3,
+            Font::Large => 5,
+        }
+    }
+
+    pub const fn height(self) -> usize {
+        match self {
+            Font::Small => 5,
+            Font::Large => 7,
+        }
+    }
+
+    /// The rows of the glyph of `c` from the top, only the first [`Font::height`] are used.
+    /// The low [`Font::width`] bits of a row are its columns, with the left one highest
+    const fn glyph(self, c: char) -> [u8; 7] {
+        match self {
+            Font::Small => {
+                let [a, b, c, d, e] = glyph_3x5(c);
+                [a, b, c, d, e, 0, 0]
+            }
+            Font::Large => glyph_5x7(c),
+        }
+    }
+}
+
+const fn glyph_3x5(c: char) -> [u8; 5] {
+    match c.to_ascii_uppercase() {
+        'A' => [0b010, 0b101, 0b111, 0b101, 0b101],
+        'B' => [0b110, 0b101, 0b110, 0b101, 0b110],
+        'C' => [0b011, 0b100, 0b100, 0b100, 0b011],
+        'D' => [0b110, 0b101, 0b101, 0b101, 0b110],
+        'E' => [0b111, 0b100, 0b110, 0b100, 0b111],
+        'F' => [0b111, 0b100, 0b110, 0b100, 0b100],
+        'G' => [0b011, 0b100, 0b101, 0b101, 0b011],
+        'H' => [0b101, 0b101, 0b111, 0b101, 0b101],
+        'I' => [0b111, 0b010, 0b010, 0b010, 0b111],
+        'J' => [0b001, 0b001, 0b001, 0b101, 0b010],
+        'K' => [0b101, 0b101, 0b110, 0b101, 0b101],
+        'L' => [0b100, 0b100, 0b100, 0b100, 0b111],
+        'M' => [0b101, 0b111, 0b111, 0b101, 0b101],
+        'N' => [0b110, 0b101, 0b101, 0b101, 0b101],
+        'O' => [0b010, 0b101, 0b101, 0b101, 0b010],
+        'P' => [0b110, 0b101, 0b110, 0b100, 0b100],
+        'Q' => [0b010, 0b101, 0b101, 0b110, 0b011],
+        'R' => [0b110, 0b101, 0b110, 0b101, 0b101],
+        'S' => [0b011, 0b100, 0b010, 0b001, 0b110],
+        'T' => [0b111, 0b010, 0b010, 0b010, 0b010],
+        'U' => [0b101, 0b101, 0b101, 0b101, 0b111],
+        'V' => [0b101, 0b101, 0b101, 0b101, 0b010],
+        'W' => [0b101, 0b101, 0b111, 0b111, 0b101],
+        'X' => [0b101, 0b101, 0b010, 0b101, 0b101],
+        'Y' => [0b101, 0b101, 0b010, 0b010, 0b010],
+        'Z' => [0b111, 0b001, 0b010, 0b100, 0b111],
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b110, 0b001, 0b010, 0b100, 0b111],
+        '3' => [0b110, 0b001, 0b010, 0b001, 0b110],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b110, 0b001, 0b110],
+        '6' => [0b011, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b010, 0b010, 0b010],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b110],
+        ' ' => [0b000, 0b000, 0b000, 0b000, 0b000],
+        '-' => [0b000, 0b000, 0b111, 0b000, 0b000],
+        '_' => [0b000, 0b000, 0b000, 0b000, 0b111],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        ',' => [0b000, 0b000, 0b000, 0b010, 0b100],
+        ':' => [0b000, 0b010, 0b000, 0b010, 0b000],
+        ';' => [0b000, 0b010, 0b000, 0b010, 0b100],
+        '!' => [0b010, 0b010, 0b010, 0b000, 0b010],
+        '\'' => [0b010, 0b010, 0b000, 0b000, 0b000],
+        '"' => [0b101, 0b101, 0b000, 0b000, 0b000],
+        '+' => [0b000, 0b010, 0b111, 0b010, 0b000],
+        '=' => [0b000, 0b111, 0b000, 0b111, 0b000],
+        '*' => [0b000, 0b101, 0b010, 0b101, 0b000],
+        '/' => [0b001, 0b001, 0b010, 0b100, 0b100],
+        '%' => [0b101, 0b001, 0b010, 0b100, 0b101],
+        '#' => [0b101, 0b111, 0b101, 0b111, 0b101],
+        '@' => [0b010, 0b101, 0b111, 0b100, 0b011],
+        '(' => [0b001, 0b010, 0b010, 0b010, 0b001],
+        ')' => [0b100, 0b010, 0b010, 0b010, 0b100],
+        '[' => [0b110, 0b100, 0b100, 0b100, 0b110],
+        ']' => [0b011, 0b001, 0b001, 0b001, 0b011],
+        '<' => [0b001, 0b010, 0b100, 0b010, 0b001],
+        '>' => [0b100, 0b010, 0b001, 0b010, 0b100],
+        _ => [0b110, 0b001, 0b010, 0b000, 0b010],
+    }
+}
+
+// a glyph per line, rustfmt would wrap every one of them
+#[rustfmt::skip]
+const fn glyph_5x7(c: char) -> [u8; 7] {
+    match c.to_ascii_uppercase() {
+        'A' => [0b01110, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001],
+        'B' => [0b11110, 0b10001, 0b10001, 0b11110, 0b10001, 0b10001, 0b11110],
+        'C' => [0b01110, 0b10001, 0b10000, 0b10000, 0b10000, 0b10001, 0b01110],
+        'D' => [0b11100, 0b10010, 0b10001, 0b10001, 0b10001, 0b10010, 0b11100],
+        'E' => [0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b11111],
+        'F' => [0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b10000],
+        'G' => [0b01110, 0b10001, 0b10000, 0b10111, 0b10001, 0b10001, 0b01111],
+        'H' => [0b10001, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001],
+        'I' => [0b01110, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110],
+        'J' => [0b00111, 0b00010, 0b00010, 0b00010, 0b00010, 0b10010, 0b01100],
+        'K' => [0b10001, 0b10010, 0b10100, 0b11000, 0b10100, 0b10010, 0b10001],
+        'L' => [0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b11111],
+        'M' => [0b10001, 0b11011, 0b10101, 0b10101, 0b10001, 0b10001, 0b10001],
+        'N' => [0b10001, 0b10001, 0b11001, 0b10101, 0b10011, 0b10001, 0b10001],
+        'O' => [0b01110, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110],
+        'P' => [0b11110, 0b10001, 0b10001, 0b11110, 0b10000, 0b10000, 0b10000],
+        'Q' => [0b01110, 0b10001, 0b10001, 0b10001, 0b10101, 0b10010, 0b01101],
+        'R' => [0b11110, 0b10001, 0b10001, 0b11110, 0b10100, 0b10010, 0b10001],
+        'S' => [0b01111, 0b10000, 0b10000, 0b01110, 0b00001, 0b00001, 0b11110],
+        'T' => [0b11111, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100],
+        'U' => [0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110],
+        'V' => [0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01010, 0b00100],
+        'W' => [0b10001, 0b10001, 0b10001, 0b10101, 0b10101, 0b10101, 0b01010],
+        'X' => [0b10001, 0b10001, 0b01010, 0b00100, 0b01010, 0b10001, 0b10001],
+        'Y' => [0b10001, 0b10001, 0b10001, 0b01010, 0b00100, 0b00100, 0b00100],
+        'Z' => [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0b11111],
+        '0' => [0b01110, 0b10001, 0b10011, 0b10101, 0b11001, 0b10001, 0b01110],
+        '1' => [0b00100, 0b01100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110],
+        '2' => [0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b01000, 0b11111],
+        '3' => [0b11111, 0b00010, 0b00100, 0b00010, 0b00001, 0b10001, 0b01110],
+        '4' => [0b00010, 0b00110, 0b01010, 0b10010, 0b11111, 0b00010, 0b00010],
+        '5' => [0b11111, 0b10000, 0b11110, 0b00001, 0b00001, 0b10001, 0b01110],
+        '6' => [0b00110, 0b01000, 0b10000, 0b11110, 0b10001, 0b10001, 0b01110],
+        '7' => [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b01000, 0b01000],
+        '8' => [0b01110, 0b10001, 0b10001, 0b01110, 0b10001, 0b10001, 0b01110],
+        '9' => [0b01110, 0b10001, 0b10001, 0b01111, 0b00001, 0b00010, 0b01100],
+        ' ' => [0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000],
+        '-' => [0b00000, 0b00000, 0b00000, 0b11111, 0b00000, 0b00000, 0b00000],
+        '_' => [0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b11111],
+        '.' => [0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b01100, 0b01100],
+        ',' => [0b00000, 0b00000, 0b00000, 0b00000, 0b01100, 0b00100, 0b01000],
+        ':' => [0b00000, 0b01100, 0b01100, 0b00000, 0b01100, 0b01100, 0b00000],
+        ';' => [0b00000, 0b01100, 0b01100, 0b00000, 0b01100, 0b00100, 0b01000],
+        '!' => [0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00000, 0b00100],
+        '\'' => [0b01100, 0b00100, 0b01000, 0b00000, 0b00000, 0b00000, 0b00000],
+        '"' => [0b01010, 0b01010, 0b01010, 0b00000, 0b00000, 0b00000, 0b00000],
+        '+' => [0b00000, 0b00100, 0b00100, 0b11111, 0b00100, 0b00100, 0b00000],
+        '=' => [0b00000, 0b00000, 0b11111, 0b00000, 0b11111, 0b00000, 0b00000],
+        '*' => [0b00000, 0b00100, 0b10101, 0b01110, 0b10101, 0b00100, 0b00000],
+        '/' => [0b00000, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0b00000],
+        '%' => [0b11000, 0b11001, 0b00010, 0b00100, 0b01000, 0b10011, 0b00011],
+        '#' => [0b01010, 0b01010, 0b11111, 0b01010, 0b11111, 0b01010, 0b01010],
+        '@' => [0b01110, 0b10001, 0b00001, 0b01101, 0b10101, 0b10101, 0b01110],
+        '(' => [0b00010, 0b00100, 0b01000, 0b01000, 0b01000, 0b00100, 0b00010],
+        ')' => [0b01000, 0b00100, 0b00010, 0b00010, 0b00010, 0b00100, 0b01000],
+        '[' => [0b01110, 0b01000, 0b01000, 0b01000, 0b01000, 0b01000, 0b01110],
+        ']' => [0b01110, 0b00010, 0b00010, 0b00010, 0b00010, 0b00010, 0b01110],
+        '<' => [0b00010, 0b00100, 0b01000, 0b10000, 0b01000, 0b00100, 0b00010],
+        '>' => [0b01000, 0b00100, 0b00010, 0b00001, 0b00010, 0b00100, 0b01000],
+        _ => [0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b00000, 0b00100],
+    }
+}
+
+/// How many leds wide `text` is in `font`, without the spacing after the last glyph
+pub fn text_width(text: &str, font: Font) -> usize {
+    (text.chars().count() * (font.width() + GLYPH_SPACING)).saturating_sub(GLYPH_SPACING)
+}
+
+/// Draws `text` in `font` with the top of the glyphs on led row `top`, moved `scroll` columns
+/// to the left. Unlike the score board, x is the row and y the column like on the board and
+/// only the leds on the matrix are returned, so the text can be scrolled through
+pub fn write_text(text: &str, font: Font, top: u8, scroll: usize, color: Rgb) -> Vec<LedChange> {
+    let width = font.width();
+    let mut changes = Vec::new();
+    for (i, c) in text.chars().enumerate() {
+        let left = i * (width + GLYPH_SPACING);
+        if left + width <= scroll {
+            continue;
+        }
+        if left >= scroll + BOARD_SIZE {
+            break;
+        }
+        for (row, bits) in font.glyph(c).iter().take(font.height()).enumerate() {
+            for column in 0..width {
+                let lit = bits & (1 << (width - 1 - column)) != 0;
+                let Some(y) = (left + column).checked_sub(scroll) else {
+                    continue;
+                };
+                let x = top as usize + row;
+                if lit && x < BOARD_SIZE && y < BOARD_SIZE {
+                    changes.push(LedChange::new(x as u8, y as u8, color));
+                }
+            }
+        }
+    }
+    changes
+}
+
+/// Like [`write_text`], centered on the matrix. Text wider than the matrix starts at its left edge
+pub fn write_centered(text: &str, font: Font, top: u8, color: Rgb) -> Vec<LedChange> {
+    let left = BOARD_SIZE.saturating_sub(text_width(text, font)) / 2;
+    shifted(write_text(text, font, top, 0, color), left)
+}
+
+/// A line of text for the matrix: text that fits is shown centered, text too wide scrolls
+/// right to left and comes around again
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Marquee {
+    text: String,
+    font: Font,
+    top: u8,
+    color: Rgb,
+    /// columns the text is scrolled by
+    scroll: usize,
+}
+
+impl Marquee {
+    /// `top` is the led row of the top of the glyphs
+    pub fn new(text: impl Into<String>, font: Font, top: u8, color: Rgb) -> Self {
+        Self {
+            text: text.into(),
+            font,
+            top,
+            color,
+            scroll: 0,
+        }
+    }
+
+    /// Vertically centered on the matrix
+    pub fn centered(text: impl Into<String>, font: Font, color: Rgb) -> Self {
+        let top = BOARD_SIZE.saturating_sub(font.height()) / 2;
+        Self::new(text, font, top as u8, color)
+    }
+
+    pub fn scrolls(&self) -> bool {
+        text_width(&self.text, self.font) > BOARD_SIZE
+    }
+
+    /// [`Marquee::tick`] should be called after this long, `None` if the text doesn't move
+    pub fn timeout(&self) -> Option<Duration> {
+        self.scrolls().then_some(MARQUEE_STEP)
+    }
+
+    /// Scrolls the text one column, returns true when it has come all the way around
+    pub fn tick(&mut self) -> bool {
+        if !self.scrolls() {
+            return false;
+        }
+        // the columns from the start of the text to the start of its copy
+        let period = (self.text.chars().count() + MARQUEE_GAP.chars().count())
+            * (self.font.width() + GLYPH_SPACING);
+        self.scroll = (self.scroll + 1) % period;
+        self.scroll == 0
+    }
+
+    /// Back to the start of the text
+    pub fn reset(&mut self) {
+        self.scroll = 0;
+    }
+
+    /// Only the lit leds of the text
+    pub fn render(&self) -> Vec<LedChange> {
+        if self.scrolls() {
+            // the text twice so its start follows its end around
+            let text = format!("{0}{MARQUEE_GAP}{0}", self.text);
+            write_text(&text, self.font, self.top, self.scroll, self.color)
+        } else {
+            write_centered(&self.text, self.font, self.top, self.color)
+        }
+    }
+
+    /// Shows the text on `layer` for `duration`, scrolling it if it doesn't fit.
+    /// The layer is left showing the last step
+    pub async fn play(
+        &mut self,
+        display: &LedDisplay,
+        layer: Layer,
+        duration: Duration,
+    ) -> Result<()> {
+        let end = Instant::now() + duration;
+        loop {
+            display
+                .show_on(layer, Frame::from_changes(self.render()))
+                .await?;
+            match self.timeout() {
+                Some(step) if Instant::now() + step < end => time::sleep(step).await,
+                _ => {
+                    time::sleep_until(end).await;
+                    return Ok(());
+                }
+            }
+            self.tick();
+        }
+    }
+}
+
+impl DisplayOnLeds for Marquee {
+    async fn display(&self, display: &LedDisplay) -> Result<()> {
+        display.show_changes(self.render()).await
+    }
+}
+
+/// the rows of the top of the two score lines, black above white
+const BLACK_SCORE_ROW: u8 = 2;
+const WHITE_SCORE_ROW: u8 = 9;
+/// the column right of the last digit of a score, the half point dot is one column further
+const SCORE_RIGHT: usize = 14;
+
+/// Both scores as digits, black on top in the color of its stones and white below.
+/// A dot after the digits is half a point, and the winner is drawn brighter with a bar on its left
+pub fn score_board(score: &Score) -> Vec<LedChange> {
+    let winner = score.winner();
+    let mut changes = write_score(score.black, Stone::Black, BLACK_SCORE_ROW, winner);
+    changes.extend(write_score(
+        score.white,
+        Stone::White,
+        WHITE_SCORE_ROW,
+        winner,
+    ));
+    changes
+}
+
+fn write_score(points: f32, stone: Stone, top: u8, winner: Option<Stone>) -> Vec<LedChange> {
+    let won = winner == Some(stone);
+    let color = if won {
+        last_move_color(stone)
+    } else {
+        stone_color(Some(stone))
+    };
+    // komi makes every score whole or half a point, 999 is more than any board has
+    let points = points.clamp(0.0, 999.5);
+    let digits = (points.trunc() as u32).to_string();
+    let left = SCORE_RIGHT - text_width(&digits, Font::Small);
+    let mut changes = shifted(write_text(&digits, Font::Small, top, 0, color), left);
+    let bottom = top + Font::Small.height() as u8 - 1;
+    if points.fract() >= 0.5 {
+        changes.push(LedChange::new(bottom, SCORE_RIGHT as u8 + 1, color));
+    }
+    if won {
+        changes.extend((top..=bottom).map(|x| LedChange::new(x, 0, color)));
+    }
+    changes
+}
+
+/// Moves text drawn at the left edge `columns` to the right, dropping what falls off the matrix
+fn shifted(changes: Vec<LedChange>, columns: usize) -> Vec<LedChange> {
+    changes
+        .into_iter()
+        .filter_map(|change| {
+            let y = change.y as usize + columns;
+            (y < BOARD_SIZE).then_some(LedChange {
+                y: y as u8,
+                ..change
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lit(changes: &[LedChange], x: u8, y: u8) -> Option<Rgb> {
+        changes
+            .iter()
+            .find(|change| (change.x, change.y) == (x, y))
+            .map(|change| change.color)
+    }
+
+    /// The bottom row of a score line
+    fn bottom(top: u8) -> u8 {
+        top + Font::Small.height() as u8 - 1
+    }
+
+    #[test]
+    fn half_points_are_a_dot_after_the_digits() {
+        let dot = SCORE_RIGHT as u8 + 1;
+        let changes = score_board(&Score {
+            black: 10.0,
+            white: 6.5,
+        });
+        assert_eq!(lit(&changes, bottom(BLACK_SCORE_ROW), dot), None);
+        assert_eq!(
+            lit(&changes, bottom(WHITE_SCORE_ROW), dot),
+            Some(stone_color(Some(Stone::White)))
+        );
+        // the dot is the only thing right of the digits
+        assert!(changes
+            .iter()
+            .all(|change| change.y < SCORE_RIGHT as u8 || change.y == dot));
+    }
+
+    #[test]
+    fn the_winner_is_brighter_with_a_bar() {
+        let changes = score_board(&Score {
+            black: 4.0,
+            white: 12.5,
+        });
+        let bright = last_move_color(Stone::White);
+        for x in WHITE_SCORE_ROW..=bottom(WHITE_SCORE_ROW) {
+            assert_eq!(lit(&changes, x, 0), Some(bright));
+        }
+        assert_eq!(lit(&changes, BLACK_SCORE_ROW, 0), None);
+        // the last digit ends right before the dot column
+        assert_eq!(
+            lit(&changes, bottom(WHITE_SCORE_ROW), SCORE_RIGHT as u8 - 1),
+            Some(bright)
+        );
+        assert!(changes
+            .iter()
+            .filter(|change| (BLACK_SCORE_ROW..=bottom(BLACK_SCORE_ROW)).contains(&change.x))
+            .all(|change| change.color == stone_color(Some(Stone::Black))));
+    }
+
+    #[test]
+    fn a_draw_has_no_winner() {
+        let changes = score_board(&Score {
+            black: 6.0,
+            white: 6.0,
+        });
+        assert!(changes.iter().all(|change| change.y != 0));
+    }
+}
@@ -0,0 +1,7 @@
+pub mod animation;
+pub mod frame;
+pub mod go_board;
+pub mod led_ctrl;
+pub mod led_font;
+pub mod rgb;
+pub mod viewport;
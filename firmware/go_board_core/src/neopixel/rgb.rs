@@ -59,6 +59,30 @@ impl Rgb {
     pub fn is_off(&self) -> bool {
         self.r == 0 && self.g == 0 && self.b == 0
     }
+
+    /// `self` drawn over `below`, an `opacity` of 255 hides `below` completely
+    pub fn over(self, below: Rgb, opacity: u8) -> Self {
+        let mix = |top: u8, below: u8| {
+            let opacity = opacity as u16;
+            ((top as u16 * opacity + below as u16 * (255 - opacity)) / 255) as u8
+        };
+        Self {
+            r: mix(self.r, below.r),
+            g: mix(self.g, below.g),
+            b: mix(self.b, below.b),
+        }
+    }
+
+    /// `self` scaled by `opacity` and added to `below`, channels saturate at 255
+    pub fn add(self, below: Rgb, opacity: u8) -> Self {
+        let add =
+            |top: u8, below: u8| below.saturating_add((top as u16 * opacity as u16 / 255) as u8);
+        Self {
+            r: add(self.r, below.r),
+            g: add(self.g, below.g),
+            b: add(self.b, below.b),
+        }
+    }
 }
 impl From<Rgb> for u32 {
     /// Convert RGB to u32 color value (24bit)
@@ -82,7 +106,6 @@ pub const GREEN: Rgb = Rgb::new(0, 64, 0);
 pub const WHITE: Rgb = Rgb::new(40, 40, 40);
 
 /// used to correct to the right color/brigthness
-
 const GAMMA8: [u8; 256] = [
     0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1,
     1, 1, 1, 1, 1, 1, 1, 1, 1, 2, 2, 2, 2, 2, 2, 2, 2, 3, 3, 3, 3, 3, 3, 3, 4, 4, 4, 4, 4, 5, 5, 5,
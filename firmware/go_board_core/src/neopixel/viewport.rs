@@ -0,0 +1,279 @@
+use crate::encoder::SpinDirection;
+use crate::game::board::{Board, Point};
+use crate::BOARD_SIZE;
+
+/// how close (in points) the followed point may get to the edge of the window before it scrolls
+const FOLLOW_MARGIN: usize = 2;
+
+/// The part of a board shown on the led matrix
+///
+/// Boards that fit on the matrix are shown whole and centered with a frame around them,
+/// larger ones (ie 19x19) are shown a `BOARD_SIZE` x `BOARD_SIZE` window at a time
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Viewport {
+    board_height: usize,
+    board_width: usize,
+    /// the board point shown on the first led of the board area
+    origin: Point,
+    /// the led the board area starts at, non zero when the board is centered on the matrix
+    offset: (u8, u8),
+}
+
+/// A side of the window
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Edge {
+    /// led x == 0
+    Top,
+    /// led x == BOARD_SIZE - 1
+    Bottom,
+    /// led y == 0
+    Left,
+    /// led y == BOARD_SIZE - 1
+    Right,
+}
+
+/// New origin along one axis so `position` stays `FOLLOW_MARGIN` away from the window's edges
+fn follow_axis(origin: usize, position: usize, board_len: usize) -> usize {
+    let max_origin = board_len.saturating_sub(BOARD_SIZE);
+    let origin = if position < origin + FOLLOW_MARGIN {
+        position.saturating_sub(FOLLOW_MARGIN)
+    } else if position + FOLLOW_MARGIN >= origin + BOARD_SIZE {
+        position + FOLLOW_MARGIN + 1 - BOARD_SIZE
+    } else {
+        origin
+    };
+    origin.min(max_origin)
+}
+
+/// Leds before a board of `board_len` points along one axis so it sits in the middle of the matrix
+fn center_offset(board_len: usize) -> u8 {
+    (BOARD_SIZE.saturating_sub(board_len) / 2) as u8
+}
+
+/// Where a led falls along one axis of a centered board
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Band {
+    Board,
+    /// the led just past the first or last line
+    Frame,
+    Outside,
+}
+
+fn band(led: u8, offset: u8, board_len: usize) -> Band {
+    let (led, offset) = (led as usize, offset as usize);
+    if led >= offset && led < offset + board_len {
+        Band::Board
+    } else if led + 1 == offset || led == offset + board_len {
+        Band::Frame
+    } else {
+        Band::Outside
+    }
+}
+
+impl Viewport {
+    pub fn new(board: &Board) -> Self {
+        Self::for_size(board.height(), board.width())
+    }
+
+    pub fn for_size(board_height: usize, board_width: usize) -> Self {
+        Self {
+            board_height,
+            board_width,
+            origin: Point::new(0, 0),
+            offset: (center_offset(board_height), center_offset(board_width)),
+        }
+    }
+
+    pub fn origin(&self) -> Point {
+        self.origin
+    }
+
+    /// true if the board is larger than the matrix
+    pub fn is_scrollable(&self) -> bool {
+        self.board_height > BOARD_SIZE || self.board_width > BOARD_SIZE
+    }
+
+    fn max_origin(&self) -> Point {
+        Point::new(
+            self.board_height.saturating_sub(BOARD_SIZE) as u8,
+            self.board_width.saturating_sub(BOARD_SIZE) as u8,
+        )
+    }
+
+    /// Scrolls just enough to keep `point` (ie the cursor or the last move) on screen
+    pub fn follow(&mut self, point: Point) {
+        self.origin = Point::new(
+            follow_axis(self.origin.x as usize, point.x as usize, self.board_height) as u8,
+            follow_axis(self.origin.y as usize, point.y as usize, self.board_width) as u8,
+        );
+    }
+
+    /// Moves the window one point, clockwise goes right then wraps down a row like reading text,
+    /// counter-clockwise goes the other way
+    pub fn pan(&mut self, direction: SpinDirection) {
+        let max = self.max_origin();
+        let (columns, rows) = (max.y as usize + 1, max.x as usize + 1);
+        let positions = columns * rows;
+        let current = self.origin.x as usize * columns + self.origin.y as usize;
+        let next = match direction {
+            SpinDirection::Clockwise => (current + 1) % positions,
+            SpinDirection::CounterClockwise => (current + positions - 1) % positions,
+        };
+        self.origin = Point::new((next / columns) as u8, (next % columns) as u8);
+    }
+
+    /// The board point shown on the led at (`x`, `y`), `None` if that led is off the board
+    pub fn to_board(&self, x: u8, y: u8) -> Option<Point> {
+        let x = x.checked_sub(self.offset.0)?;
+        let y = y.checked_sub(self.offset.1)?;
+        let point = Point::new(x + self.origin.x, y + self.origin.y);
+        ((point.x as usize) < self.board_height && (point.y as usize) < self.board_width)
+            .then_some(point)
+    }
+
+    /// The led showing `point`, `None` if it is outside the window
+    pub fn to_led(&self, point: Point) -> Option<(u8, u8)> {
+        let x = point.x.checked_sub(self.origin.x)? + self.offset.0;
+        let y = point.y.checked_sub(self.origin.y)? + self.offset.1;
+        ((x as usize) < BOARD_SIZE && (y as usize) < BOARD_SIZE).then_some((x, y))
+    }
+
+    /// true if the led at (`x`, `y`) is part of the frame drawn just outside the edge of
+    /// a board smaller than the matrix
+    pub fn is_frame(&self, x: u8, y: u8) -> bool {
+        !matches!(
+            (
+                band(x, self.offset.0, self.board_height),
+                band(y, self.offset.1, self.board_width),
+            ),
+            (Band::Board, Band::Board) | (Band::Outside, _) | (_, Band::Outside)
+        )
+    }
+
+    /// true if part of the board is hidden past `edge`
+    pub fn has_more_past(&self, edge: Edge) -> bool {
+        let max = self.max_origin();
+        match edge {
+            Edge::Top => self.origin.x > 0,
+            Edge::Bottom => self.origin.x < max.x,
+            Edge::Left => self.origin.y > 0,
+            Edge::Right => self.origin.y < max.y,
+        }
+    }
+
+    /// The edges of the window the led at (`x`, `y`) sits on
+    pub fn edges_at(x: u8, y: u8) -> impl Iterator<Item = Edge> {
+        let last = BOARD_SIZE as u8 - 1;
+        [
+            (x == 0).then_some(Edge::Top),
+            (x == last).then_some(Edge::Bottom),
+            (y == 0).then_some(Edge::Left),
+            (y == last).then_some(Edge::Right),
+        ]
+        .into_iter()
+        .flatten()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edges_with_more(viewport: &Viewport) -> Vec<Edge> {
+        [Edge::Top, Edge::Bottom, Edge::Left, Edge::Right]
+            .into_iter()
+            .filter(|edge| viewport.has_more_past(*edge))
+            .collect()
+    }
+
+    #[test]
+    fn large_boards_follow_the_point_and_stay_clamped() {
+        let mut viewport = Viewport::for_size(19, 19);
+        assert!(viewport.is_scrollable());
+        viewport.follow(Point::new(10, 10));
+        assert_eq!(viewport.origin(), Point::new(0, 0));
+        // scrolls once the point gets within the margin of the edge
+        viewport.follow(Point::new(14, 14));
+        assert_eq!(viewport.origin(), Point::new(1, 1));
+        viewport.follow(Point::new(18, 18));
+        assert_eq!(viewport.origin(), Point::new(3, 3));
+        viewport.follow(Point::new(4, 18));
+        assert_eq!(viewport.origin(), Point::new(2, 3));
+        viewport.follow(Point::new(0, 0));
+        assert_eq!(viewport.origin(), Point::new(0, 0));
+    }
+
+    #[test]
+    fn large_boards_map_leds_through_the_window() {
+        let mut viewport = Viewport::for_size(19, 19);
+        viewport.follow(Point::new(18, 18));
+        assert_eq!(viewport.to_led(Point::new(3, 3)), Some((0, 0)));
+        assert_eq!(viewport.to_led(Point::new(2, 3)), None);
+        assert_eq!(viewport.to_board(15, 15), Some(Point::new(18, 18)));
+        // no frame, the board reaches the edge of the matrix
+        assert!(!viewport.is_frame(0, 0));
+    }
+
+    #[test]
+    fn panning_reads_the_windows_like_text_and_wraps() {
+        let mut viewport = Viewport::for_size(19, 19);
+        viewport.pan(SpinDirection::CounterClockwise);
+        assert_eq!(viewport.origin(), Point::new(3, 3));
+        viewport.pan(SpinDirection::Clockwise);
+        assert_eq!(viewport.origin(), Point::new(0, 0));
+        for _ in 0..4 {
+            viewport.pan(SpinDirection::Clockwise);
+        }
+        assert_eq!(viewport.origin(), Point::new(1, 0));
+    }
+
+    #[test]
+    fn the_edges_with_more_board_past_them_are_marked() {
+        let mut viewport = Viewport::for_size(19, 19);
+        assert_eq!(edges_with_more(&viewport), vec![Edge::Bottom, Edge::Right]);
+        viewport.follow(Point::new(10, 18));
+        assert_eq!(edges_with_more(&viewport), vec![Edge::Bottom, Edge::Left]);
+        viewport.follow(Point::new(18, 18));
+        assert_eq!(edges_with_more(&viewport), vec![Edge::Top, Edge::Left]);
+
+        let last = BOARD_SIZE as u8 - 1;
+        assert_eq!(
+            Viewport::edges_at(0, last).collect::<Vec<_>>(),
+            vec![Edge::Top, Edge::Right]
+        );
+        assert_eq!(Viewport::edges_at(5, 5).count(), 0);
+    }
+
+    #[test]
+    fn small_boards_are_centered_in_a_frame() {
+        let mut viewport = Viewport::for_size(9, 9);
+        assert!(!viewport.is_scrollable());
+        assert!(edges_with_more(&viewport).is_empty());
+        // (16 - 9) / 2 leds before the first line
+        assert_eq!(viewport.to_led(Point::new(0, 0)), Some((3, 3)));
+        assert_eq!(viewport.to_led(Point::new(8, 8)), Some((11, 11)));
+        assert_eq!(viewport.to_board(3, 3), Some(Point::new(0, 0)));
+        assert_eq!(viewport.to_board(2, 3), None);
+        assert_eq!(viewport.to_board(12, 12), None);
+        // following never scrolls a board that fits
+        viewport.follow(Point::new(8, 8));
+        assert_eq!(viewport.origin(), Point::new(0, 0));
+
+        assert!(viewport.is_frame(2, 2));
+        assert!(viewport.is_frame(2, 5));
+        assert!(viewport.is_frame(12, 5));
+        assert!(!viewport.is_frame(5, 5));
+        assert!(!viewport.is_frame(1, 5));
+        assert!(!viewport.is_frame(13, 13));
+    }
+
+    #[test]
+    fn thirteen_by_thirteen_leaves_a_led_for_the_frame() {
+        let viewport = Viewport::for_size(13, 13);
+        assert_eq!(viewport.to_led(Point::new(0, 0)), Some((1, 1)));
+        assert_eq!(viewport.to_led(Point::new(12, 12)), Some((13, 13)));
+        assert!(viewport.is_frame(0, 0));
+        assert!(viewport.is_frame(14, 7));
+        assert!(!viewport.is_frame(15, 7));
+    }
+}
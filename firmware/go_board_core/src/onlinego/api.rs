@@ -0,0 +1,1287 @@
+use super::auth_token::AuthToken;
+use super::error::{OgsError, OgsErrorKind};
+use super::https::{request, send_request, RequestType, RetryPolicy};
+use super::rate_limit;
+use super::status_codes::StatusCode;
+use super::token_manager::TokenManager;
+use super::transport::MAX_JSON_SIZE;
+use crate::game::board::{Board, Point, Stone};
+use crate::game::rules::{Move, Position};
+use crate::game::sgf::SgfGame;
+use crate::game::zobrist::{KoRule, PositionHistory};
+use anyhow::{anyhow, Result};
+use lazy_static::lazy_static;
+use log::warn;
+use postcard::experimental::max_size::MaxSize;
+use serde::de::{DeserializeOwned, IgnoredAny, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::error::Error;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::sync::RwLock;
+
+/// The online-go the api talks to and the oauth client the board logs in as, see [`set_server`]
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct OnlineGoServer {
+    /// without a trailing slash, ie [`OnlineGoServer::ONLINE_GO_URL`]
+    pub base_url: String,
+    pub client_id: String,
+}
+
+impl OnlineGoServer {
+    pub const ONLINE_GO_URL: &'static str = "https://online-go.com";
+}
+
+lazy_static! {
+    static ref SERVER: RwLock<OnlineGoServer> = RwLock::new(OnlineGoServer {
+        base_url: OnlineGoServer::ONLINE_GO_URL.to_string(),
+        client_id: String::new(),
+    });
+}
+
+/// Every request goes to `server` from now on, ie a local stand-in for online-go
+pub fn set_server(server: OnlineGoServer) {
+    // the lock only guards a plain value, a panic while holding it leaves nothing half done
+    *SERVER.write().unwrap_or_else(|e| e.into_inner()) = server;
+}
+
+fn server() -> OnlineGoServer {
+    SERVER.read().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+fn api_url() -> String {
+    format!("{}/api/v1/", server().base_url)
+}
+
+fn termination_api_url() -> String {
+    format!("{}/termination-api/", server().base_url)
+}
+
+fn token_url() -> String {
+    format!("{}/oauth2/token/", server().base_url)
+}
+
+/// PASSWORD AUTH
+#[derive(Serialize, Deserialize, Debug)]
+pub struct OauthResponseError {
+    pub error: String, // will be empty string if all went well
+    pub error_description: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct OauthResponseErrorWithStatusCode {
+    pub response: OauthResponseError,
+    pub status_code: StatusCode,
+}
+
+impl Display for OauthResponseErrorWithStatusCode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{:#?}", self)
+    }
+}
+
+impl Error for OauthResponseErrorWithStatusCode {}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct OauthResponseValid {
+    pub access_token: AuthToken, //DQTDfpBbE7pBh2E5GqwzxYSkb4AT1u
+    pub expires_in: i32,
+    pub token_type: String,
+    pub refresh_token: String,
+    // raw_scope: String, // must still be parsed, not necessary at the moment
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, PartialOrd, Eq, Ord, Debug, Hash, MaxSize)]
+pub struct OnlineGoLoginInfo {
+    pub username: heapless::String<100>,
+    pub password: heapless::String<100>,
+}
+/// The answer of online-go's token endpoint, a refused grant is the inner error
+pub type OauthResult =
+    Result<Result<OauthResponseValid, OauthResponseErrorWithStatusCode>, OgsError>;
+
+impl OnlineGoLoginInfo {
+    pub fn auth_with_password(&self) -> OauthResult {
+        auth_with_password(server().client_id, &self.username, &self.password)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct AuthPasswordData<'s> {
+    client_id: &'s str,
+    username: &'s str,
+    grant_type: &'static str,
+    password: &'s str,
+}
+
+fn auth_with_password(
+    client_id: impl AsRef<str>,
+    username: impl AsRef<str>,
+    password: impl AsRef<str>,
+) -> OauthResult {
+    let context = "log in to online-go with the password";
+    let data = form(
+        &AuthPasswordData {
+            client_id: client_id.as_ref(),
+            username: username.as_ref(),
+            grant_type: "password",
+            password: password.as_ref(),
+        },
+        context,
+    )?;
+    let (status_code, s) = request(RequestType::Post {
+        url: token_url().as_str(),
+        data: data.as_str(),
+    })
+    .map_err(|e| OgsError::from_request(context, e))?;
+    parse_oauth_response(status_code, &s, context)
+}
+
+fn parse_oauth_response(status_code: StatusCode, s: &str, context: &str) -> OauthResult {
+    let possible_valid_oauth = serde_json::from_str::<OauthResponseValid>(s);
+
+    match possible_valid_oauth {
+        Err(_) => {
+            // neither a valid oauth json nor a valid error json
+            let possible_error = serde_json::from_str::<OauthResponseError>(s)
+                .map_err(|e| OgsError::from_body(context, status_code, e.into()))?;
+            Ok(Err(OauthResponseErrorWithStatusCode {
+                response: possible_error,
+                status_code,
+            }))
+        }
+        Ok(valid_oauth) => Ok(Ok(valid_oauth)),
+    }
+}
+
+// END PASSWORD AUTH
+
+/// REFRESH TOKEN AUTH
+#[derive(Serialize, Deserialize, Debug)]
+struct AuthRefreshTokenData<'s> {
+    client_id: &'s str,
+    grant_type: &'static str,
+    refresh_token: &'s str,
+}
+
+/// Gets a new access token (and refresh token) without sending the password
+pub fn auth_with_refresh_token(refresh_token: &str) -> OauthResult {
+    let context = "renew the online-go token";
+    let data = form(
+        &AuthRefreshTokenData {
+            client_id: &server().client_id,
+            grant_type: "refresh_token",
+            refresh_token,
+        },
+        context,
+    )?;
+    let (status_code, s) = request(RequestType::Post {
+        url: token_url().as_str(),
+        data: data.as_str(),
+    })
+    .map_err(|e| OgsError::from_request(context, e))?;
+    parse_oauth_response(status_code, &s, context)
+}
+
+// END REFRESH TOKEN AUTH
+
+/// PLAYER INFO
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct Player {
+    id: i64,
+    username: String,
+    #[serde(rename = "ranking")]
+    raw_ranking: f32,
+}
+
+impl Player {
+    pub fn id(&self) -> i64 {
+        self.id
+    }
+
+    pub fn username(&self) -> &str {
+        &self.username
+    }
+
+    pub fn ranking(&self) -> String {
+        if self.raw_ranking < 30.0 {
+            format!("{} kyu", (30.0 - self.raw_ranking + 0.5).round() as i32)
+        } else {
+            format!(
+                "{} dan",
+                ((self.raw_ranking - 30.0 + 0.5).round() + 1.0) as i32
+            )
+        }
+    }
+}
+
+impl Display for Player {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{} ({})", self.username, self.ranking())
+    }
+}
+
+/// Encodes the form data of a post
+fn form(data: &impl Serialize, context: &str) -> Result<String, OgsError> {
+    serde_qs::to_string(data).map_err(|e| OgsError::from_encoding(context, e))
+}
+
+/// Gets `url` and parses the json response, `context` describes the request in errors.
+/// The request is sent again if the connection drops while the body is read
+fn get_json<T: DeserializeOwned>(
+    url: impl AsRef<str>,
+    tokens: &TokenManager,
+    context: &str,
+) -> Result<T, OgsError> {
+    let url = url.as_ref();
+    send_request(
+        RequestType::AuthorizedGet { url, tokens },
+        &RetryPolicy::DEFAULT,
+        |response| {
+            let status_code = response.status;
+            if status_code.is_success() {
+                response.json::<T>(MAX_JSON_SIZE)
+            } else {
+                let body = response.text()?;
+                Err(OgsError::from_response(context, status_code, &body).into())
+            }
+        },
+    )
+    .map_err(|e| OgsError::from_request(context, e))
+}
+
+pub fn get_current_player(tokens: &TokenManager) -> Result<Player, OgsError> {
+    get_json(format!("{}me", api_url()), tokens, "get current player")
+}
+
+// END PLAYER
+
+// START GAME
+
+/// List of games from api
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GameList {
+    #[serde(rename = "results")]
+    pub games: Vec<GameListData>,
+    /// the url of the next page, `None` on the last one
+    #[serde(default)]
+    pub next: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GameListData {
+    pub id: i64,
+    pub name: String,
+    pub width: i32,
+    pub height: i32,
+    pub players: PlayerList,
+    /// when the game was started
+    pub started: String,
+    pub black_lost: bool,
+    pub white_lost: bool,
+    /// the rule set, ie `japanese`, `chinese`, `aga`
+    #[serde(default)]
+    pub rules: String,
+    #[serde(default)]
+    pub ranked: bool,
+    /// the average time per move the time control allows, in seconds, 0 if there is no limit
+    #[serde(default)]
+    pub time_per_move: i64,
+    /// not part of the list, filled in by [`get_games`]
+    #[serde(default, skip_deserializing)]
+    pub clock: Option<GameClock>,
+}
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PlayerList {
+    pub white: Player,
+    pub black: Player,
+}
+
+impl GameListData {
+    // Description returns a formatted description of the game.
+    pub fn description(&self) -> String {
+        let ended = if self.is_game_over() { " (ended)" } else { "" };
+        format!(
+            "{} (B) vs {} (W) ({}x{}){}",
+            self.players.black, self.players.white, self.width, self.height, ended
+        )
+    }
+
+    // GameOver returns true if the game has ended, otherwise false.
+    pub fn is_game_over(&self) -> bool {
+        // If a game is over, one of these will be false
+        !self.black_lost || !self.white_lost
+    }
+
+    /// The color `player` plays in this game, `None` if they are not playing in it
+    pub fn color_of(&self, player: &Player) -> Option<Stone> {
+        if player.id == self.players.black.id {
+            Some(Stone::Black)
+        } else if player.id == self.players.white.id {
+            Some(Stone::White)
+        } else {
+            None
+        }
+    }
+
+    /// How repeated positions are treated under this game's rule set
+    pub fn ko_rule(&self) -> KoRule {
+        KoRule::from_ogs_rules(&self.rules)
+    }
+
+    /// Live or correspondence, online-go counts anything slower than an hour per move as
+    /// correspondence
+    pub fn speed(&self) -> GameSpeed {
+        if self.time_per_move > 0 && self.time_per_move < CORRESPONDENCE_TIME_PER_MOVE {
+            GameSpeed::Live
+        } else {
+            GameSpeed::Correspondence
+        }
+    }
+
+    /// `None` until the clock is fetched, see [`get_games`]
+    pub fn is_turn_of(&self, player: &Player) -> Option<bool> {
+        self.clock
+            .as_ref()
+            .map(|clock| clock.current_player == player.id)
+    }
+
+    pub fn get_detail(&self, tokens: &TokenManager) -> Result<BoardState, OgsError> {
+        get_game_data(self.id, tokens)
+    }
+
+    pub fn get_record(&self, tokens: &TokenManager) -> Result<GameRecord, OgsError> {
+        get_game_record(self.id, tokens)
+    }
+}
+
+/// seconds per move from which online-go calls a game correspondence
+const CORRESPONDENCE_TIME_PER_MOVE: i64 = 60 * 60;
+
+/// stops following `next` after this many pages, in case the server keeps sending one
+const MAX_GAME_LIST_PAGES: usize = 10;
+
+/// Every unfinished game of the current player, all the pages of the list
+pub fn get_current_player_games(tokens: &TokenManager) -> Result<GameList, OgsError> {
+    let api_url = api_url();
+    let current_games_url = format!("{api_url}me/games?ended__isnull=true&page_size=50");
+    let mut list: GameList = get_json(current_games_url, tokens, "get current players games")?;
+    for page in 2..=MAX_GAME_LIST_PAGES {
+        let Some(next) = list.next.take() else {
+            return Ok(list);
+        };
+        // the token is only sent to online-go
+        if !next.starts_with(&api_url) {
+            warn!("Not following the game list to {next}");
+            return Ok(list);
+        }
+        let mut next_page: GameList = get_json(
+            &next,
+            tokens,
+            &format!("get page {page} of the current players games"),
+        )?;
+        list.games.append(&mut next_page.games);
+        list.next = next_page.next;
+    }
+    if list.next.take().is_some() {
+        warn!("Game list is longer than {MAX_GAME_LIST_PAGES} pages, the rest is left out");
+    }
+    Ok(list)
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum GameSpeed {
+    /// blitz and live games, played in one sitting
+    Live,
+    Correspondence,
+}
+
+/// Which games [`get_games`] returns, `None` and `false` let every game through
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug, Default)]
+pub struct GameFilter {
+    /// only games where it is the player's move
+    pub my_turn: bool,
+    /// only boards with no side longer than this, ie what the viewport can pan around
+    pub max_size: Option<i32>,
+    pub ranked: Option<bool>,
+    pub speed: Option<GameSpeed>,
+}
+
+impl GameFilter {
+    /// The part of the filter that does not need the clock
+    fn matches_listing(&self, game: &GameListData) -> bool {
+        self.max_size
+            .is_none_or(|max_size| game.width <= max_size && game.height <= max_size)
+            && self.ranked.is_none_or(|ranked| game.ranked == ranked)
+            && self.speed.is_none_or(|speed| game.speed() == speed)
+    }
+}
+
+/// each clock is a request of its own, past this many games the rest are listed without one.
+/// Leaves room in the request burst for the player, the listing and opening the picked game
+const MAX_CLOCK_FETCHES: usize = rate_limit::BURST as usize - 4;
+
+/// The unfinished games of `player` that pass `filter`, with their clocks filled in.
+/// The games where it is the player's move come first, each part ordered by whose time runs
+/// out first, games without a time limit last.
+///
+/// A game whose clock could not be fetched (or that is past [`MAX_CLOCK_FETCHES`]) is kept
+/// without one and sorted last, as it is not known whose move it is
+pub fn get_games(
+    player: &Player,
+    filter: &GameFilter,
+    tokens: &TokenManager,
+) -> Result<Vec<GameListData>, OgsError> {
+    let mut games = Vec::new();
+    let mut fetches = 0;
+    for mut game in get_current_player_games(tokens)?.games {
+        if !filter.matches_listing(&game) {
+            continue;
+        }
+        if fetches < MAX_CLOCK_FETCHES {
+            fetches += 1;
+            match get_game_clock(game.id, tokens) {
+                Ok(clock) => game.clock = Some(clock),
+                // the other clocks would be refused as well
+                Err(e) if e.kind() == OgsErrorKind::Unauthorized => return Err(e),
+                Err(e) => {
+                    warn!("Listing game {} without its clock: {e}", game.id);
+                    // the budget is spent, waiting for the other clocks would only delay the list
+                    if e.kind() == OgsErrorKind::RateLimited {
+                        fetches = MAX_CLOCK_FETCHES;
+                    }
+                }
+            }
+        }
+        if filter.my_turn && game.is_turn_of(player) == Some(false) {
+            continue;
+        }
+        games.push(game);
+    }
+    sort_by_urgency(&mut games, player);
+    Ok(games)
+}
+
+/// The games where it is `player`'s move first, then by whose time runs out first
+fn sort_by_urgency(games: &mut [GameListData], player: &Player) {
+    games.sort_by(|a, b| {
+        let urgency = |game: &GameListData| {
+            let clock = game.clock.as_ref();
+            (
+                clock.is_none_or(|clock| clock.current_player != player.id),
+                clock
+                    .and_then(|clock| clock.expiration)
+                    .unwrap_or(f64::INFINITY),
+            )
+        };
+        let (a_waiting, a_expiration) = urgency(a);
+        let (b_waiting, b_expiration) = urgency(b);
+        a_waiting
+            .cmp(&b_waiting)
+            .then(a_expiration.total_cmp(&b_expiration))
+    });
+}
+
+/// The clock of a running game, part of the game record
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct GameClock {
+    /// the id of the player to move
+    pub current_player: i64,
+    /// when the player to move runs out of time, in milliseconds since the epoch,
+    /// `None` for games without a time limit
+    #[serde(default)]
+    pub expiration: Option<f64>,
+}
+
+fn get_game_clock(game_id: i64, tokens: &TokenManager) -> Result<GameClock, OgsError> {
+    /// only the clock of `/api/v1/games/{id}`, the moves are skipped while parsing
+    #[derive(Deserialize)]
+    struct ClockRecord {
+        gamedata: ClockGameData,
+    }
+    #[derive(Deserialize)]
+    struct ClockGameData {
+        clock: GameClock,
+    }
+
+    let url = format!("{}games/{game_id}", api_url());
+    let record: ClockRecord = get_json(url, tokens, &format!("get clock for {game_id}"))?;
+    Ok(record.gamedata.clock)
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BoardState {
+    pub move_number: i32,
+    pub player_to_move: i64,
+    pub phase: String,
+    pub board: Vec<Vec<i32>>,
+    pub outcome: String,
+    pub removal: Vec<Vec<i32>>,
+    pub last_move: LastMove,
+}
+
+pub enum BoardColor {
+    Empty = 0,
+    Black = 1,
+    White = 2,
+}
+
+impl TryFrom<i32> for BoardColor {
+    type Error = anyhow::Error;
+
+    fn try_from(v: i32) -> Result<Self, Self::Error> {
+        match v {
+            x if x == BoardColor::Black as i32 => Ok(BoardColor::Black),
+            x if x == BoardColor::White as i32 => Ok(BoardColor::White),
+            x if x == BoardColor::Empty as i32 => Ok(BoardColor::Empty),
+            _ => Err(anyhow!("Failed to convert board color! {}", v)),
+        }
+    }
+}
+#[derive(Serialize, Deserialize, Debug)]
+pub struct LastMove {
+    pub x: i32,
+    pub y: i32,
+}
+
+impl BoardState {
+    pub fn finished(&self) -> bool {
+        self.phase == "finished"
+    }
+
+    pub fn is_stone_removal(&self) -> bool {
+        self.phase == "stone removal"
+    }
+
+    pub fn height(&self) -> usize {
+        self.board.len()
+    }
+
+    pub fn width(&self) -> usize {
+        if self.height() == 0 {
+            0
+        } else {
+            self.board[0].len()
+        }
+    }
+
+    pub fn board_iter(&self) -> impl Iterator<Item = (u8, u8, &i32)> {
+        self.board
+            .iter()
+            .enumerate()
+            .map(|(x, v)| (x, v.iter().enumerate()))
+            .flat_map(|(x, iter)| iter.map(move |(y, v)| (x as u8, y as u8, v)))
+    }
+
+    /// The board as a [`Board`] that the rules engine can check moves against
+    pub fn to_board(&self) -> Result<Board> {
+        Board::from_rows(&self.board)
+    }
+}
+
+fn get_game_data(game_id: i64, tokens: &TokenManager) -> Result<BoardState, OgsError> {
+    let url = format!("{}game/{game_id}/state", termination_api_url());
+    get_json(url, tokens, &format!("get game data for {game_id}"))
+}
+
+/// Full record of a game from `/api/v1/games/{id}`, unlike [`BoardState`] it has every move
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GameRecord {
+    pub id: i64,
+    pub gamedata: GameData,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GameData {
+    pub width: usize,
+    pub height: usize,
+    pub komi: f32,
+    /// the rule set, ie `japanese`, `chinese`, `aga`
+    pub rules: String,
+    #[serde(default)]
+    pub handicap: u32,
+    /// `black` or `white`
+    #[serde(default)]
+    pub initial_player: String,
+    #[serde(default)]
+    pub initial_state: InitialState,
+    pub moves: Vec<GameMove>,
+    #[serde(default)]
+    pub players: Option<GameDataPlayers>,
+    /// ie `Resignation`, `Timeout`, `12.5 points`, empty while the game is being played
+    #[serde(default)]
+    pub outcome: String,
+    /// player id of the winner
+    #[serde(default)]
+    pub winner: Option<i64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GameDataPlayers {
+    pub black: GameDataPlayer,
+    pub white: GameDataPlayer,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GameDataPlayer {
+    pub id: i64,
+    pub username: String,
+}
+
+/// Setup stones, each point is two letters, column then row (`aa` is the top left)
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct InitialState {
+    #[serde(default)]
+    pub black: String,
+    #[serde(default)]
+    pub white: String,
+}
+
+/// A move as online-go sends it, `x` is the column and `y` the row, `-1, -1` is a pass
+///
+/// On the wire it is an array `[x, y, time_ms, ...]`, everything after `y` is ignored
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GameMove {
+    pub x: i32,
+    pub y: i32,
+}
+
+impl GameMove {
+    pub fn to_move(&self) -> Move {
+        if self.x < 0 || self.y < 0 {
+            Move::Pass
+        } else {
+            // online-go's rows are the outer index of the board
+            Move::Place(Point::new(self.y as u8, self.x as u8))
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for GameMove {
+    fn deserialize<D>(deserializer: D) -> Result<GameMove, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_seq(GameMoveVisitor)
+    }
+}
+
+struct GameMoveVisitor;
+
+impl<'de> Visitor<'de> for GameMoveVisitor {
+    type Value = GameMove;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an array that starts with the x and y of a move")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let x = seq
+            .next_element::<i32>()?
+            .ok_or_else(|| serde::de::Error::invalid_length(0, &self))?;
+        let y = seq
+            .next_element::<i32>()?
+            .ok_or_else(|| serde::de::Error::invalid_length(1, &self))?;
+        // time taken, player info etc.
+        while seq.next_element::<IgnoredAny>()?.is_some() {}
+        Ok(GameMove { x, y })
+    }
+}
+
+/// Parses online-go's letter coordinates (`aabbcc`), each pair is column then row
+fn points_from_letters(letters: &str) -> Result<Vec<Point>> {
+    let bytes = letters.as_bytes();
+    if !bytes.len().is_multiple_of(2) {
+        return Err(anyhow!("Odd number of coordinate letters in {letters}"));
+    }
+    bytes
+        .chunks(2)
+        .map(|pair| match (pair[0], pair[1]) {
+            (column @ b'a'..=b'z', row @ b'a'..=b'z') => Ok(Point::new(row - b'a', column - b'a')),
+            _ => Err(anyhow!("Invalid coordinate letters in {letters}")),
+        })
+        .collect()
+}
+
+/// Inverse of [`points_from_letters`]
+fn letters_from_points(points: &[Point]) -> String {
+    points
+        .iter()
+        .flat_map(|point| [(b'a' + point.y) as char, (b'a' + point.x) as char])
+        .collect()
+}
+
+/// How the game was won in SGF's terms, from online-go's `outcome`. A disconnected player ran
+/// out of the time to come back, and reasons SGF has no letter for are left out (`B+`)
+fn result_reason(outcome: &str) -> &str {
+    let points = outcome
+        .strip_suffix(" points")
+        .or_else(|| outcome.strip_suffix(" point"));
+    if let Some(points) = points.filter(|points| points.parse::<f32>().is_ok()) {
+        return points;
+    }
+    match outcome {
+        "Resignation" => "R",
+        "Timeout" | "Disconnection" => "T",
+        "Disqualification" | "Abandonment" | "Moderator Decision" => "F",
+        _ => "",
+    }
+}
+
+impl GameRecord {
+    /// The handicap stones black places before white's first move, online-go sends them as
+    /// the first moves of the game
+    fn handicap_moves(&self) -> usize {
+        if self.gamedata.handicap > 1 {
+            (self.gamedata.handicap as usize).min(self.gamedata.moves.len())
+        } else {
+            0
+        }
+    }
+
+    /// The board before the first regular move, setup and handicap stones included
+    pub fn initial_position(&self) -> Result<Position> {
+        let GameData {
+            width,
+            height,
+            initial_state,
+            initial_player,
+            moves,
+            ..
+        } = &self.gamedata;
+        let mut board = Board::new(*height, *width)?;
+        let handicap_stones = moves[..self.handicap_moves()]
+            .iter()
+            .filter_map(|game_move| match game_move.to_move() {
+                Move::Place(point) => Some((point, Stone::Black)),
+                Move::Pass => None,
+            });
+        let setup_stones = points_from_letters(&initial_state.black)?
+            .into_iter()
+            .map(|point| (point, Stone::Black))
+            .chain(
+                points_from_letters(&initial_state.white)?
+                    .into_iter()
+                    .map(|point| (point, Stone::White)),
+            );
+        for (point, stone) in setup_stones.chain(handicap_stones) {
+            if !board.contains(point) {
+                return Err(anyhow!("Setup stone {point} is not on the board"));
+            }
+            board.set(point, Some(stone));
+        }
+        let to_move = if self.handicap_moves() > 0 || initial_player == "white" {
+            Stone::White
+        } else {
+            Stone::Black
+        };
+        Ok(Position::new(board, to_move))
+    }
+
+    /// Every move after the handicap stones
+    pub fn moves(&self) -> impl Iterator<Item = Move> + '_ {
+        self.gamedata.moves[self.handicap_moves()..]
+            .iter()
+            .map(GameMove::to_move)
+    }
+
+    /// The outcome the way go players write it and SGF stores it, ie `B+R`, `W+12.5`, `Void`
+    /// for a cancelled game. `None` until the game has a winner
+    pub fn result(&self) -> Option<String> {
+        let GameData {
+            players,
+            outcome,
+            winner,
+            ..
+        } = &self.gamedata;
+        if outcome == "Cancellation" {
+            return Some("Void".to_string());
+        }
+        let players = players.as_ref()?;
+        let winner = match winner {
+            Some(id) if *id == players.black.id => "B",
+            Some(id) if *id == players.white.id => "W",
+            _ => return None,
+        };
+        Some(format!("{winner}+{}", result_reason(outcome)))
+    }
+
+    /// Exports the game, handicap stones become `AB` setup stones
+    pub fn to_sgf(&self) -> Result<SgfGame> {
+        let initial = self.initial_position()?;
+        let mut sgf = SgfGame::new(self.gamedata.height, self.gamedata.width);
+        sgf.komi = self.gamedata.komi;
+        sgf.handicap = self.gamedata.handicap;
+        sgf.rules = Some(self.gamedata.rules.clone());
+        sgf.black_name = self
+            .gamedata
+            .players
+            .as_ref()
+            .map(|players| players.black.username.clone());
+        sgf.white_name = self
+            .gamedata
+            .players
+            .as_ref()
+            .map(|players| players.white.username.clone());
+        sgf.result = self.result();
+        for (point, stone) in initial.board.iter() {
+            match stone {
+                Some(Stone::Black) => sgf.setup_black.push(point),
+                Some(Stone::White) => sgf.setup_white.push(point),
+                None => {}
+            }
+        }
+        if initial.to_move == Stone::White && sgf.handicap <= 1 {
+            sgf.first_player = Some(Stone::White);
+        }
+        let mut to_move = initial.to_move;
+        for mv in self.moves() {
+            sgf.moves.push((to_move, mv));
+            to_move = to_move.opponent();
+        }
+        Ok(sgf)
+    }
+
+    /// Plays every move through the rules engine, giving the final position and its captures
+    pub fn replay(&self) -> Result<PositionHistory> {
+        let mut history = PositionHistory::new(
+            self.initial_position()?,
+            KoRule::from_ogs_rules(&self.gamedata.rules),
+        );
+        for (move_number, mv) in self.moves().enumerate() {
+            history
+                .play(mv)
+                .map_err(|e| anyhow!(e).context(format!("Failed to replay move {move_number}")))?;
+        }
+        Ok(history)
+    }
+}
+
+fn get_game_record(game_id: i64, tokens: &TokenManager) -> Result<GameRecord, OgsError> {
+    let url = format!("{}games/{game_id}", api_url());
+    get_json(url, tokens, &format!("get game record for {game_id}"))
+}
+
+/// STONE REMOVAL
+
+#[derive(Serialize, Deserialize, Debug)]
+struct RemovedStonesData {
+    removed: bool,
+    stones: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct AcceptRemovedStonesData {
+    stones: String,
+    strict_seki_mode: bool,
+}
+
+fn post_game_action(url: String, data: String, tokens: &TokenManager) -> Result<(), OgsError> {
+    let context = format!("post to {url}");
+    let (status_code, value) = request(RequestType::AuthorizedPost {
+        url: url.as_str(),
+        data: data.as_str(),
+        tokens,
+    })
+    .map_err(|e| OgsError::from_request(&context, e))?;
+    if status_code.is_success() {
+        Ok(())
+    } else {
+        Err(OgsError::from_response(context, status_code, &value))
+    }
+}
+
+/// Marks (or unmarks) `stones` as dead in the stone removal phase
+pub fn set_removed_stones(
+    game_id: i64,
+    stones: &[Point],
+    removed: bool,
+    tokens: &TokenManager,
+) -> Result<(), OgsError> {
+    let url = format!("{}games/{game_id}/removed_stones/", api_url());
+    let data = form(
+        &RemovedStonesData {
+            removed,
+            stones: letters_from_points(stones),
+        },
+        &format!("post to {url}"),
+    )?;
+    post_game_action(url, data, tokens)
+}
+
+/// Agrees to the dead stones in the stone removal phase, `stones` must match the current proposal
+pub fn accept_removed_stones(
+    game_id: i64,
+    stones: &[Point],
+    tokens: &TokenManager,
+) -> Result<(), OgsError> {
+    let url = format!("{}games/{game_id}/removed_stones/accept/", api_url());
+    let data = form(
+        &AcceptRemovedStonesData {
+            stones: letters_from_points(stones),
+            strict_seki_mode: false,
+        },
+        &format!("post to {url}"),
+    )?;
+    post_game_action(url, data, tokens)
+}
+
+/// Rejects the stone removal proposal, the game goes back to being played
+pub fn reject_removed_stones(game_id: i64, tokens: &TokenManager) -> Result<(), OgsError> {
+    post_game_action(
+        format!("{}games/{game_id}/removed_stones/reject/", api_url()),
+        String::new(),
+        tokens,
+    )
+}
+
+// END STONE REMOVAL
+
+// START PLAYING
+
+/// Why online-go refused a move, pass or resignation
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MoveRejectionReason {
+    NotYourTurn,
+    /// occupied point, suicide, ko...
+    IllegalMove,
+    /// the game already ended (or is in stone removal)
+    GameOver,
+    /// the logged in player is not playing in this game
+    NotAPlayer,
+    Other,
+}
+
+#[derive(Debug)]
+pub struct MoveRejection {
+    pub reason: MoveRejectionReason,
+    pub status_code: StatusCode,
+    /// the error message sent by online-go, or the whole body if it is not json
+    pub message: String,
+}
+
+impl MoveRejection {
+    fn from_response(status_code: StatusCode, body: &str) -> Self {
+        #[derive(Deserialize)]
+        struct ErrorBody {
+            #[serde(alias = "detail")]
+            error: String,
+        }
+        let message = serde_json::from_str::<ErrorBody>(body)
+            .map(|body| body.error)
+            .unwrap_or_else(|_| body.to_string());
+        let lowercase = message.to_lowercase();
+        let reason = if lowercase.contains("not your turn") || lowercase.contains("not_your_turn") {
+            MoveRejectionReason::NotYourTurn
+        } else if ["illegal", "invalid move", "suicide", "occupied"]
+            .iter()
+            .any(|word| lowercase.contains(word))
+            || lowercase
+                .split(|c: char| !c.is_alphanumeric())
+                .any(|word| word == "ko" || word == "superko")
+        {
+            MoveRejectionReason::IllegalMove
+        } else if ["game is over", "finished", "not active", "has ended"]
+            .iter()
+            .any(|word| lowercase.contains(word))
+        {
+            MoveRejectionReason::GameOver
+        } else if status_code.as_u16() == 403 || lowercase.contains("not a player") {
+            MoveRejectionReason::NotAPlayer
+        } else {
+            MoveRejectionReason::Other
+        };
+        Self {
+            reason,
+            status_code,
+            message,
+        }
+    }
+}
+
+impl Display for MoveRejection {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:?}: {} ({})",
+            self.reason, self.message, self.status_code
+        )
+    }
+}
+
+impl Error for MoveRejection {}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct MoveData {
+    #[serde(rename = "move")]
+    coordinates: String,
+}
+
+/// online-go writes a pass as two dots instead of coordinate letters
+const PASS_LETTERS: &str = "..";
+
+/// online-go answers a move it refuses with one of these, other errors are not about the move
+fn is_move_rejection(status_code: StatusCode) -> bool {
+    matches!(status_code.as_u16(), 400 | 403 | 409)
+}
+
+/// Errors only for failed requests, a move refused by online-go is returned as a [`MoveRejection`].
+/// A missing game (404) or a token refused even after renewing it (401) are errors
+fn post_move_action(
+    url: String,
+    data: String,
+    tokens: &TokenManager,
+) -> Result<Result<(), MoveRejection>, OgsError> {
+    let context = format!("post to {url}");
+    let (status_code, value) = request(RequestType::AuthorizedPost {
+        url: url.as_str(),
+        data: data.as_str(),
+        tokens,
+    })
+    .map_err(|e| OgsError::from_request(&context, e))?;
+    if status_code.is_success() {
+        Ok(Ok(()))
+    } else if is_move_rejection(status_code) {
+        Ok(Err(MoveRejection::from_response(status_code, &value)))
+    } else {
+        Err(OgsError::from_response(context, status_code, &value))
+    }
+}
+
+/// Plays `mv` in the game, the move shows up in the game once this returns `Ok(Ok(()))`
+pub fn submit_move(
+    game_id: i64,
+    mv: Move,
+    tokens: &TokenManager,
+) -> Result<Result<(), MoveRejection>, OgsError> {
+    let coordinates = match mv {
+        Move::Place(point) => letters_from_points(&[point]),
+        Move::Pass => PASS_LETTERS.to_string(),
+    };
+    let url = format!("{}games/{game_id}/move/", api_url());
+    let data = form(&MoveData { coordinates }, &format!("post to {url}"))?;
+    post_move_action(url, data, tokens)
+}
+
+pub fn pass(game_id: i64, tokens: &TokenManager) -> Result<Result<(), MoveRejection>, OgsError> {
+    submit_move(game_id, Move::Pass, tokens)
+}
+
+pub fn resign(game_id: i64, tokens: &TokenManager) -> Result<Result<(), MoveRejection>, OgsError> {
+    post_move_action(
+        format!("{}games/{game_id}/resign/", api_url()),
+        String::new(),
+        tokens,
+    )
+}
+
+// END PLAYING
+
+// END GAME
+
+pub fn test_connection(login: OnlineGoLoginInfo) -> Result<BoardState> {
+    // let url = Url::parse_with_params("https://httpbun.org/post",
+    //                                  &[("lang", "rust"), ("browser", "servo")])?;//"
+
+    let oauth = login.auth_with_password()??;
+    let tokens = TokenManager::new(login, oauth, None);
+    let games = get_current_player_games(&tokens)?;
+    let game_data = get_game_data(games.games[0].id, &tokens)?;
+    println!("{game_data:?}");
+    Ok(game_data)
+    // let player = get_current_player(&s.access_token)?;
+    // println!("{player}");
+
+    // Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status(code: u16) -> StatusCode {
+        StatusCode::from_u16(code).unwrap()
+    }
+
+    #[test]
+    fn only_some_client_errors_are_move_rejections() {
+        for code in [400, 403, 409] {
+            assert!(is_move_rejection(status(code)), "{code}");
+        }
+        for code in [401, 404, 429, 500, 503] {
+            assert!(!is_move_rejection(status(code)), "{code}");
+        }
+    }
+
+    #[test]
+    fn rejections_are_sorted_by_their_message() {
+        let reason = |code, body| MoveRejection::from_response(status(code), body).reason;
+        assert_eq!(
+            reason(400, r#"{"error": "Not your turn"}"#),
+            MoveRejectionReason::NotYourTurn
+        );
+        assert_eq!(
+            reason(400, r#"{"error": "Illegal move, the point is occupied"}"#),
+            MoveRejectionReason::IllegalMove
+        );
+        assert_eq!(
+            reason(400, r#"{"detail": "Illegal Ko Move"}"#),
+            MoveRejectionReason::IllegalMove
+        );
+        assert_eq!(
+            reason(400, r#"{"error": "The game is over"}"#),
+            MoveRejectionReason::GameOver
+        );
+        assert_eq!(
+            reason(403, r#"{"detail": "Permission denied"}"#),
+            MoveRejectionReason::NotAPlayer
+        );
+        assert_eq!(reason(409, "conflict"), MoveRejectionReason::Other);
+        // bodies that are not json are kept whole
+        let rejection = MoveRejection::from_response(status(400), "bad move");
+        assert_eq!(rejection.message, "bad move");
+    }
+    fn player(id: i64) -> Player {
+        Player {
+            id,
+            username: format!("player{id}"),
+            raw_ranking: 20.0,
+        }
+    }
+
+    /// A 9x9 unranked correspondence game between players 1 (black) and 2
+    fn game(id: i64) -> GameListData {
+        GameListData {
+            id,
+            name: format!("game {id}"),
+            width: 9,
+            height: 9,
+            players: PlayerList {
+                black: player(1),
+                white: player(2),
+            },
+            started: String::new(),
+            black_lost: true,
+            white_lost: true,
+            rules: "japanese".to_string(),
+            ranked: false,
+            time_per_move: 24 * 60 * 60,
+            clock: None,
+        }
+    }
+
+    fn with_clock(
+        mut game: GameListData,
+        current_player: i64,
+        expiration: Option<f64>,
+    ) -> GameListData {
+        game.clock = Some(GameClock {
+            current_player,
+            expiration,
+        });
+        game
+    }
+
+    #[test]
+    fn the_listing_filter_checks_size_ranking_and_speed() {
+        assert!(GameFilter::default().matches_listing(&game(1)));
+
+        let small = GameFilter {
+            max_size: Some(9),
+            ..GameFilter::default()
+        };
+        assert!(small.matches_listing(&game(1)));
+        let wide = GameListData {
+            width: 13,
+            ..game(1)
+        };
+        assert!(!small.matches_listing(&wide));
+
+        let ranked = GameFilter {
+            ranked: Some(true),
+            ..GameFilter::default()
+        };
+        assert!(!ranked.matches_listing(&game(1)));
+        assert!(ranked.matches_listing(&GameListData {
+            ranked: true,
+            ..game(1)
+        }));
+
+        let live = GameFilter {
+            speed: Some(GameSpeed::Live),
+            ..GameFilter::default()
+        };
+        assert!(!live.matches_listing(&game(1)));
+        let blitz = GameListData {
+            time_per_move: 10,
+            ..game(1)
+        };
+        assert!(live.matches_listing(&blitz));
+        // no time limit is correspondence
+        let unlimited = GameListData {
+            time_per_move: 0,
+            ..game(1)
+        };
+        assert!(!live.matches_listing(&unlimited));
+    }
+
+    #[test]
+    fn games_are_sorted_by_urgency() {
+        let mut games = vec![
+            game(1),
+            with_clock(game(2), 2, Some(1_000.0)),
+            with_clock(game(3), 1, None),
+            with_clock(game(4), 1, Some(5_000.0)),
+            with_clock(game(5), 1, Some(2_000.0)),
+            with_clock(game(6), 2, None),
+        ];
+        sort_by_urgency(&mut games, &player(1));
+        let order: Vec<_> = games.iter().map(|game| game.id).collect();
+        // my move by expiration, then theirs, the ones without a time limit or clock last
+        assert_eq!(order, [5, 4, 3, 2, 1, 6]);
+    }
+
+    /// A finished 9x9 game between players 1 (black) and 2
+    fn finished(outcome: &str, winner: Option<i64>) -> GameRecord {
+        serde_json::from_value(serde_json::json!({
+            "id": 1,
+            "gamedata": {
+                "width": 9,
+                "height": 9,
+                "komi": 6.5,
+                "rules": "japanese",
+                "moves": [],
+                "players": {
+                    "black": { "id": 1, "username": "black" },
+                    "white": { "id": 2, "username": "white" }
+                },
+                "outcome": outcome,
+                "winner": winner
+            }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn results_are_written_like_sgf() {
+        let result = |outcome, winner| finished(outcome, winner).result();
+        assert_eq!(result("12.5 points", Some(2)).as_deref(), Some("W+12.5"));
+        assert_eq!(result("1 point", Some(1)).as_deref(), Some("B+1"));
+        assert_eq!(result("Resignation", Some(1)).as_deref(), Some("B+R"));
+        assert_eq!(result("Timeout", Some(2)).as_deref(), Some("W+T"));
+        assert_eq!(result("Disconnection", Some(2)).as_deref(), Some("W+T"));
+        assert_eq!(result("Disqualification", Some(1)).as_deref(), Some("B+F"));
+        assert_eq!(result("Abandonment", Some(2)).as_deref(), Some("W+F"));
+        // a reason SGF has no letter for is left out
+        assert_eq!(
+            result("Stone Removal Timeout", Some(1)).as_deref(),
+            Some("B+")
+        );
+        assert_eq!(result("Cancellation", None).as_deref(), Some("Void"));
+        assert_eq!(result("", None), None);
+    }
+}
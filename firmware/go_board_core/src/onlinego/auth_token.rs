@@ -1,10 +1,8 @@
-use crate::storage::SaveInNvs;
-use postcard::experimental::max_size::MaxSize;
 use serde::de::{Error, Visitor};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::fmt;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct AuthToken {
     token: String,
     header_val: String,
@@ -18,6 +16,9 @@ impl AuthToken {
             header_val,
         }
     }
+    pub fn token(&self) -> &str {
+        &self.token
+    }
     pub fn auth_header(&self) -> (&'static str, &str) {
         ("Authorization", self.header_val.as_str())
     }
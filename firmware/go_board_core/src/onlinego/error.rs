@@ -0,0 +1,192 @@
+use super::rate_limit::Throttled;
+use super::status_codes::StatusCode;
+use super::transport::ResponseTooLarge;
+use serde::Deserialize;
+use std::error::Error;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+
+/// What went wrong talking to online-go, callers pick how to recover from this
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+pub enum OgsErrorKind {
+    /// the token was refused even after renewing it, the login info needs to be fixed
+    Unauthorized,
+    /// the login works but may not do this (403), ie act in a game it is not playing
+    Forbidden,
+    /// too many requests (429)
+    RateLimited,
+    NotFound,
+    /// any other 4xx, the request itself is wrong
+    Rejected,
+    /// 5xx, online-go is having trouble
+    Server,
+    /// the request never got a response (wifi, dns, tls...)
+    Network,
+    /// the response did not match the expected json (or the request could not be encoded),
+    /// the api probably changed
+    Schema,
+    /// the response was larger than the board is willing to read
+    TooLarge,
+}
+
+impl OgsErrorKind {
+    /// true if trying again later may work without changing anything
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            OgsErrorKind::RateLimited | OgsErrorKind::Server | OgsErrorKind::Network
+        )
+    }
+
+    /// Too large or not the expected json, any other failure to read is the network
+    fn from_body_error(error: &anyhow::Error) -> Self {
+        if error.is::<ResponseTooLarge>() {
+            OgsErrorKind::TooLarge
+        } else if error.is::<serde_json::Error>() {
+            OgsErrorKind::Schema
+        } else {
+            OgsErrorKind::Network
+        }
+    }
+
+    fn from_status(status_code: StatusCode) -> Self {
+        match status_code.as_u16() {
+            401 => OgsErrorKind::Unauthorized,
+            403 => OgsErrorKind::Forbidden,
+            404 => OgsErrorKind::NotFound,
+            429 => OgsErrorKind::RateLimited,
+            500..=599 => OgsErrorKind::Server,
+            _ => OgsErrorKind::Rejected,
+        }
+    }
+}
+
+/// The json online-go sends with errors, which field is set depends on the endpoint
+#[derive(Deserialize, Clone, PartialEq, Eq, Debug, Default)]
+pub struct OgsErrorBody {
+    #[serde(default)]
+    pub error: Option<String>,
+    #[serde(default)]
+    pub detail: Option<String>,
+    #[serde(default)]
+    pub error_description: Option<String>,
+}
+
+impl OgsErrorBody {
+    /// The most descriptive message in the body
+    pub fn message(&self) -> Option<&str> {
+        self.error_description
+            .as_deref()
+            .or(self.detail.as_deref())
+            .or(self.error.as_deref())
+    }
+}
+
+#[derive(Debug)]
+pub struct OgsError {
+    pub kind: OgsErrorKind,
+    /// what was being done, ie `get game record for 123`
+    pub context: String,
+    /// `None` if no response was received
+    pub status_code: Option<StatusCode>,
+    /// `None` if there was no response or its body is not an online-go error
+    pub body: Option<OgsErrorBody>,
+    source: Option<anyhow::Error>,
+}
+
+impl OgsError {
+    pub fn kind(&self) -> OgsErrorKind {
+        self.kind
+    }
+
+    /// An unsuccessful response
+    pub fn from_response(context: impl Into<String>, status_code: StatusCode, body: &str) -> Self {
+        Self {
+            kind: OgsErrorKind::from_status(status_code),
+            context: context.into(),
+            status_code: Some(status_code),
+            body: serde_json::from_str(body).ok(),
+            source: None,
+        }
+    }
+
+    /// Reading or parsing the body of a successful response failed
+    pub fn from_body(
+        context: impl Into<String>,
+        status_code: StatusCode,
+        error: anyhow::Error,
+    ) -> Self {
+        Self {
+            kind: OgsErrorKind::from_body_error(&error),
+            context: context.into(),
+            status_code: Some(status_code),
+            body: None,
+            source: Some(error),
+        }
+    }
+
+    /// The token could not be renewed
+    pub fn unauthorized(context: impl Into<String>, error: impl Into<anyhow::Error>) -> Self {
+        Self {
+            kind: OgsErrorKind::Unauthorized,
+            context: context.into(),
+            status_code: None,
+            body: None,
+            source: Some(error.into()),
+        }
+    }
+
+    /// The form data of a request could not be encoded
+    pub fn from_encoding(context: impl Into<String>, error: impl Into<anyhow::Error>) -> Self {
+        Self {
+            kind: OgsErrorKind::Schema,
+            context: context.into(),
+            status_code: None,
+            body: None,
+            source: Some(error.into()),
+        }
+    }
+
+    /// `request` failed, keeps the error if it already is an [`OgsError`] (ie from renewing
+    /// the token), a spent request budget is rate limiting, a body that could not be parsed
+    /// is classified like in [`OgsError::from_body`]
+    pub fn from_request(context: impl Into<String>, error: anyhow::Error) -> Self {
+        match error.downcast::<OgsError>() {
+            Ok(error) => error,
+            Err(error) => Self {
+                kind: if error.is::<Throttled>() {
+                    OgsErrorKind::RateLimited
+                } else {
+                    OgsErrorKind::from_body_error(&error)
+                },
+                context: context.into(),
+                status_code: None,
+                body: None,
+                source: Some(error),
+            },
+        }
+    }
+}
+
+impl Display for OgsError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "Failed to {} ({:?}", self.context, self.kind)?;
+        if let Some(status_code) = self.status_code {
+            write!(f, ", {status_code}")?;
+        }
+        f.write_str(")")?;
+        if let Some(message) = self.body.as_ref().and_then(OgsErrorBody::message) {
+            write!(f, ": {message}")?;
+        }
+        if let Some(source) = &self.source {
+            write!(f, ": {source}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for OgsError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.source.as_ref().map(|source| source.as_ref())
+    }
+}
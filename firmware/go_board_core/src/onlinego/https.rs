@@ -0,0 +1,356 @@
+use super::auth_token::AuthToken;
+use super::error::OgsError;
+use super::rate_limit::{self, Throttled};
+use super::token_manager::TokenManager;
+use super::transport::{transport, HttpRequest, HttpResponse, Method};
+use crate::onlinego::status_codes::StatusCode;
+use anyhow::Result;
+use log::warn;
+use std::io;
+use std::sync::RwLock;
+use std::thread;
+use std::time::Duration;
+
+pub enum RequestType<'at, S>
+where
+    S: AsRef<str>,
+{
+    Get {
+        url: S,
+    },
+    AuthorizedGet {
+        url: S,
+        tokens: &'at TokenManager,
+    },
+    Post {
+        url: S,
+        data: S,
+    },
+    AuthorizedPost {
+        url: S,
+        data: S,
+        tokens: &'at TokenManager,
+    },
+}
+
+impl<S: AsRef<str>> RequestType<'_, S> {
+    fn url(&self) -> &str {
+        match self {
+            RequestType::Get { url }
+            | RequestType::AuthorizedGet { url, .. }
+            | RequestType::Post { url, .. }
+            | RequestType::AuthorizedPost { url, .. } => url.as_ref(),
+        }
+    }
+
+    /// Sending it twice does the same as sending it once, so it can be retried after any failure
+    fn is_idempotent(&self) -> bool {
+        matches!(
+            self,
+            RequestType::Get { .. } | RequestType::AuthorizedGet { .. }
+        )
+    }
+}
+
+/// How a failed request is retried
+///
+/// GETs are retried after network errors, 429 and 5xx. POSTs are only retried after a 429,
+/// as the server did not act on them, anything else may have been applied already.
+/// All the waiting blocks the calling thread, so the delays are kept short and a longer
+/// `Retry-After` is returned to the caller as a 429 instead
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// including the first one
+    pub max_attempts: u32,
+    /// the wait before the first retry, doubled for every retry after it
+    pub base_delay: Duration,
+    /// the longest wait between attempts, for the backoff, `Retry-After` and the request budget
+    pub max_delay: Duration,
+}
+
+impl RetryPolicy {
+    pub const DEFAULT: RetryPolicy = RetryPolicy {
+        max_attempts: 4,
+        base_delay: Duration::from_millis(500),
+        max_delay: Duration::from_secs(8),
+    };
+
+    /// A single attempt, the request budget is still respected
+    pub const NONE: RetryPolicy = RetryPolicy {
+        max_attempts: 1,
+        ..RetryPolicy::DEFAULT
+    };
+
+    /// Exponential backoff with jitter so a crowd of boards does not retry in lockstep,
+    /// the wait is picked between half and all of the backoff
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let backoff = self
+            .base_delay
+            .saturating_mul(2_u32.saturating_pow(retry))
+            .min(self.max_delay);
+        let half = backoff / 2;
+        half + half.mul_f64(random() as f64 / u32::MAX as f64)
+    }
+}
+
+/// Good enough for jitter, std hashers are randomly seeded
+fn hashed_random() -> u32 {
+    use std::hash::{BuildHasher, Hasher};
+    std::collections::hash_map::RandomState::new()
+        .build_hasher()
+        .finish() as u32
+}
+
+static JITTER_SOURCE: RwLock<fn() -> u32> = RwLock::new(hashed_random);
+
+/// Where the backoff jitter comes from from now on, ie the board's hardware random number
+/// generator. Until then the std hasher's random seed is used
+pub fn set_jitter_source(source: fn() -> u32) {
+    // the lock only guards a plain value, a panic while holding it leaves nothing half done
+    *JITTER_SOURCE.write().unwrap_or_else(|e| e.into_inner()) = source;
+}
+
+fn random() -> u32 {
+    JITTER_SOURCE.read().unwrap_or_else(|e| e.into_inner())()
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy::DEFAULT
+    }
+}
+
+pub fn request(request_type: RequestType<impl AsRef<str>>) -> Result<(StatusCode, String)> {
+    request_with_policy(request_type, &RetryPolicy::DEFAULT)
+}
+
+pub fn request_with_policy(
+    request_type: RequestType<impl AsRef<str>>,
+    policy: &RetryPolicy,
+) -> Result<(StatusCode, String)> {
+    send_request(request_type, policy, |response| {
+        Ok((response.status, response.text()?))
+    })
+}
+
+/// Like [`request_with_policy`] but the body is handed to `read`, for responses that are parsed
+/// as they come in with [`HttpResponse::json`]. Reading is part of the attempt, a GET whose
+/// connection drops while `read` reads it (an io error) is sent again
+pub fn send_request<T>(
+    request_type: RequestType<impl AsRef<str>>,
+    policy: &RetryPolicy,
+    mut read: impl FnMut(HttpResponse) -> Result<T>,
+) -> Result<T> {
+    let url = request_type.url();
+    let mut attempt = 1;
+    loop {
+        rate_limit::acquire(url, policy.max_delay)?;
+        let result = send_once(&request_type);
+        let last_attempt = attempt >= policy.max_attempts;
+        let delay = match &result {
+            Ok(response) => {
+                let status = response.status.as_u16();
+                if let (429 | 503, Some(retry_after)) = (status, response.retry_after) {
+                    // every request to the host waits, not just this one
+                    rate_limit::block(url, retry_after)?;
+                }
+                let retry = match status {
+                    429 => true,
+                    500..=599 => request_type.is_idempotent(),
+                    _ => false,
+                };
+                match response.retry_after {
+                    _ if !retry || last_attempt => None,
+                    Some(retry_after) if retry_after > policy.max_delay => None,
+                    Some(retry_after) => Some(retry_after),
+                    None => Some(policy.backoff(attempt - 1)),
+                }
+            }
+            // an `OgsError` comes from renewing the token and `Throttled` from the budget,
+            // those already had their retries
+            Err(e) if e.is::<OgsError>() || e.is::<Throttled>() => None,
+            Err(_) if !request_type.is_idempotent() || last_attempt => None,
+            Err(_) => Some(policy.backoff(attempt - 1)),
+        };
+        let Some(delay) = delay else {
+            match read(result?) {
+                Err(e) if e.is::<io::Error>() && request_type.is_idempotent() && !last_attempt => {
+                    let delay = policy.backoff(attempt - 1);
+                    warn!(
+                        "Reading the body of {url} failed: {e}, attempt {attempt}/{} retrying in {delay:?}",
+                        policy.max_attempts
+                    );
+                    thread::sleep(delay);
+                    attempt += 1;
+                    continue;
+                }
+                result => return result,
+            }
+        };
+        match &result {
+            Ok(response) => warn!(
+                "{url} answered {}, attempt {attempt}/{} retrying in {delay:?}",
+                response.status, policy.max_attempts
+            ),
+            Err(e) => warn!(
+                "{url} failed: {e:?}, attempt {attempt}/{} retrying in {delay:?}",
+                policy.max_attempts
+            ),
+        }
+        // a response being retried still holds its connection
+        drop(result);
+        thread::sleep(delay);
+        attempt += 1;
+    }
+}
+
+fn send_once(request_type: &RequestType<impl AsRef<str>>) -> Result<HttpResponse> {
+    match request_type {
+        RequestType::Get { url } => send(Method::Get, url.as_ref(), None, None),
+        RequestType::AuthorizedGet { url, tokens } => authorized(tokens, |auth_token| {
+            send(Method::Get, url.as_ref(), None, Some(auth_token))
+        }),
+        RequestType::Post { url, data } => {
+            send(Method::Post, url.as_ref(), Some(data.as_ref()), None)
+        }
+        RequestType::AuthorizedPost { url, data, tokens } => authorized(tokens, |auth_token| {
+            send(
+                Method::Post,
+                url.as_ref(),
+                Some(data.as_ref()),
+                Some(auth_token),
+            )
+        }),
+    }
+}
+
+/// Sends with the current token, if the server refuses it the token is renewed and the
+/// request is sent once more
+fn authorized(
+    tokens: &TokenManager,
+    send: impl Fn(&AuthToken) -> Result<HttpResponse>,
+) -> Result<HttpResponse> {
+    let auth_token = tokens.token()?;
+    let response = send(&auth_token)?;
+    if response.status == StatusCode::UNAUTHORIZED {
+        // renewing the token sends a request of its own, the refused one's connection goes first
+        drop(response);
+        send(&tokens.token_rejected(&auth_token)?)
+    } else {
+        Ok(response)
+    }
+}
+
+fn send(
+    method: Method,
+    url: &str,
+    data: Option<&str>,
+    auth_token: Option<&AuthToken>,
+) -> Result<HttpResponse> {
+    const POST_CONTENT_URL_ENCODED: (&str, &str) =
+        ("Content-Type", "application/x-www-form-urlencoded");
+    // const ACCEPT_CONTENT_HEADER_JSON: (&str, &str) = ("Accept", "application/json");
+
+    let mut headers = heapless::Vec::<(&str, &str), 2>::new();
+    if data.is_some() {
+        headers.push(POST_CONTENT_URL_ENCODED).ok();
+    }
+    if let Some(auth_token) = auth_token {
+        headers.push(auth_token.auth_header()).ok();
+    }
+    transport()?.send(&HttpRequest {
+        method,
+        url,
+        headers: &headers,
+        body: data,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::onlinego::transport::{set_transport, HttpTransport};
+    use std::collections::HashSet;
+    use std::io::Read;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Mutex;
+
+    /// A body whose connection drops before any of it arrives
+    struct Dropped;
+
+    impl Read for Dropped {
+        fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+            Err(io::ErrorKind::ConnectionReset.into())
+        }
+    }
+
+    static SENT: AtomicU32 = AtomicU32::new(0);
+
+    /// Drops the body of the first response to each url
+    #[derive(Default)]
+    struct DropsFirstBody {
+        seen: Mutex<HashSet<String>>,
+    }
+
+    impl HttpTransport for DropsFirstBody {
+        fn send(&self, request: &HttpRequest) -> Result<HttpResponse> {
+            SENT.fetch_add(1, Ordering::SeqCst);
+            let first = self.seen.lock().unwrap().insert(request.url.to_string());
+            let body: Box<dyn Read> = if first {
+                Box::new(Dropped)
+            } else {
+                Box::new(&b"{\"a\":1}"[..])
+            };
+            Ok(HttpResponse {
+                status: StatusCode::OK,
+                retry_after: None,
+                body,
+            })
+        }
+    }
+
+    #[test]
+    fn dropped_bodies_are_sent_again_for_gets_only() {
+        set_transport(DropsFirstBody::default()).unwrap();
+        let policy = RetryPolicy {
+            base_delay: Duration::from_millis(1),
+            ..RetryPolicy::DEFAULT
+        };
+        let read = |response: HttpResponse| response.json::<serde_json::Value>(64);
+
+        let url = "http://dropped-bodies.test/get";
+        let value = send_request(RequestType::Get { url }, &policy, read).unwrap();
+        assert_eq!(value, serde_json::json!({ "a": 1 }));
+        assert_eq!(SENT.load(Ordering::SeqCst), 2);
+
+        let (url, data) = ("http://dropped-bodies.test/post", "a=1");
+        let error = send_request(RequestType::Post { url, data }, &policy, read).unwrap_err();
+        assert!(error.is::<io::Error>(), "{error:?}");
+        assert_eq!(SENT.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_the_longest_delay() {
+        let policy = RetryPolicy::DEFAULT;
+        for retry in 0..12 {
+            let full = policy
+                .base_delay
+                .saturating_mul(2_u32.pow(retry))
+                .min(policy.max_delay);
+            let backoff = policy.backoff(retry);
+            assert!(
+                backoff >= full / 2 && backoff <= full,
+                "{retry}: {backoff:?}"
+            );
+        }
+
+        // the jitter picks between half and all of the backoff
+        set_jitter_source(|| 0);
+        assert_eq!(policy.backoff(0), Duration::from_millis(250));
+        assert_eq!(policy.backoff(2), Duration::from_secs(1));
+        set_jitter_source(|| u32::MAX);
+        assert_eq!(policy.backoff(2), Duration::from_secs(2));
+        assert_eq!(policy.backoff(10), policy.max_delay);
+        set_jitter_source(hashed_random);
+    }
+}
@@ -0,0 +1,8 @@
+pub mod api;
+pub mod auth_token;
+pub mod error;
+pub mod https;
+pub mod rate_limit;
+pub mod status_codes;
+pub mod token_manager;
+pub mod transport;
@@ -0,0 +1,169 @@
+//! A request budget shared by every request to the same server
+//!
+//! Each host (and port) gets `BURST` requests right away and one more every `REQUEST_INTERVAL` after
+//! that, so however often a loop polls, online-go never sees more than that rate from the
+//! board. A `Retry-After` from the server pauses every request to that host until it passes.
+
+use anyhow::{anyhow, Result};
+use lazy_static::lazy_static;
+use log::warn;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// requests that can be sent back to back before the budget starts spacing them out
+pub(crate) const BURST: u32 = 10;
+/// the sustained rate, one request every this long
+const REQUEST_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Copy)]
+struct Budget {
+    /// when the next request would be on time if there was no burst allowance
+    next_request: Instant,
+    /// set from `Retry-After`, nothing is sent before this
+    blocked_until: Instant,
+}
+
+lazy_static! {
+    static ref BUDGETS: Mutex<HashMap<String, Budget>> = Mutex::new(HashMap::new());
+}
+
+/// The budget of a host would only allow a request after longer than the caller wanted to wait
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Throttled {
+    pub host: String,
+    pub retry_in: Duration,
+}
+
+impl Display for Throttled {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Request budget for {} is spent, retry in {:?}",
+            self.host, self.retry_in
+        )
+    }
+}
+
+impl Error for Throttled {}
+
+/// `host:port`, so servers sharing a host (ie mocks on localhost) get their own budget
+fn host_of(url: &str) -> Result<String> {
+    let url = url::Url::parse(url)?;
+    let host = url.host_str().ok_or_else(|| anyhow!("{url} has no host"))?;
+    match url.port_or_known_default() {
+        Some(port) => Ok(format!("{host}:{port}")),
+        None => Ok(host.to_string()),
+    }
+}
+
+/// Blocks until the host of `url` may be sent another request, fails with [`Throttled`]
+/// instead if that is more than `max_wait` away
+pub fn acquire(url: &str, max_wait: Duration) -> Result<()> {
+    let host = host_of(url)?;
+    let wait = {
+        let mut budgets = BUDGETS
+            .lock()
+            .map_err(|_| anyhow!("request budget lock is poisoned"))?;
+        let now = Instant::now();
+        let budget = budgets.entry(host.clone()).or_insert(Budget {
+            next_request: now,
+            blocked_until: now,
+        });
+        let burst = REQUEST_INTERVAL * (BURST - 1);
+        let start = now
+            .max(budget.next_request.checked_sub(burst).unwrap_or(now))
+            .max(budget.blocked_until);
+        let wait = start - now;
+        if wait > max_wait {
+            return Err(Throttled {
+                host,
+                retry_in: wait,
+            }
+            .into());
+        }
+        // the slot is taken before sleeping so other threads queue up behind it
+        budget.next_request = budget.next_request.max(start) + REQUEST_INTERVAL;
+        wait
+    };
+    if !wait.is_zero() {
+        warn!("Request budget for {host} is spent, waiting {wait:?}");
+        thread::sleep(wait);
+    }
+    Ok(())
+}
+
+/// The server asked to wait `delay` before sending it anything else
+pub fn block(url: &str, delay: Duration) -> Result<()> {
+    let host = host_of(url)?;
+    let mut budgets = BUDGETS
+        .lock()
+        .map_err(|_| anyhow!("request budget lock is poisoned"))?;
+    let now = Instant::now();
+    let budget = budgets.entry(host).or_insert(Budget {
+        next_request: now,
+        blocked_until: now,
+    });
+    budget.blocked_until = budget.blocked_until.max(now + delay);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::onlinego::error::{OgsError, OgsErrorKind};
+
+    // the budgets are kept per host for the whole process, so every test uses its own port
+
+    fn throttled(url: &str) -> Throttled {
+        acquire(url, Duration::ZERO)
+            .unwrap_err()
+            .downcast::<Throttled>()
+            .unwrap()
+    }
+
+    #[test]
+    fn the_burst_goes_out_right_away_then_requests_are_spaced() {
+        let url = "http://budget.test:1/api/v1/me";
+        for _ in 0..BURST {
+            acquire(url, Duration::ZERO).unwrap();
+        }
+        let throttled = throttled(url);
+        assert_eq!(throttled.host, "budget.test:1");
+        assert!(throttled.retry_in > REQUEST_INTERVAL - Duration::from_millis(500));
+        assert!(throttled.retry_in <= REQUEST_INTERVAL);
+        // a refused request does not take a slot
+        assert!(throttled.retry_in >= self::throttled(url).retry_in);
+        // and reaches the caller as rate limiting
+        let error = acquire(url, Duration::ZERO).unwrap_err();
+        let error = OgsError::from_request("get the player", error);
+        assert_eq!(error.kind(), OgsErrorKind::RateLimited);
+    }
+
+    #[test]
+    fn each_port_has_its_own_budget() {
+        for _ in 0..BURST {
+            acquire("http://budget.test:2/", Duration::ZERO).unwrap();
+        }
+        acquire("http://budget.test:3/", Duration::ZERO).unwrap();
+    }
+
+    #[test]
+    fn retry_after_blocks_the_whole_host() {
+        let url = "http://budget.test:4/api/v1/me";
+        block(url, Duration::from_secs(30)).unwrap();
+        let throttled = throttled("http://budget.test:4/api/v1/games/1");
+        assert!(throttled.retry_in > Duration::from_secs(29));
+
+        // a short block is waited out when the caller allows it
+        let url = "http://budget.test:5/";
+        block(url, Duration::from_millis(50)).unwrap();
+        let start = Instant::now();
+        acquire(url, Duration::from_secs(1)).unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(50));
+    }
+}
@@ -0,0 +1,194 @@
+use super::api::{auth_with_refresh_token, OauthResponseValid, OnlineGoLoginInfo};
+use super::auth_token::AuthToken;
+use super::error::OgsError;
+use anyhow::{anyhow, Result};
+use log::{error, info, warn};
+use postcard::experimental::max_size::MaxSize;
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// tokens are renewed this long before they expire so a request never goes out with a dead one
+const REFRESH_MARGIN: Duration = Duration::from_secs(5 * 60);
+/// any earlier time means the clock was not set by SNTP yet
+const CLOCK_SET_AFTER_UNIX: u64 = 1_577_836_800; // 2020-01-01
+
+/// Seconds since the unix epoch, `None` until the clock is set
+fn unix_now() -> Option<u64> {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .ok()
+        .map(|now| now.as_secs())
+        .filter(|now| *now > CLOCK_SET_AFTER_UNIX)
+}
+
+/// The online-go tokens kept across reboots so the password is only sent when the
+/// refresh token stops working
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug, MaxSize)]
+pub struct SavedTokens {
+    /// the tokens are only reused for the account they were issued to
+    pub username: heapless::String<100>,
+    pub access_token: heapless::String<64>,
+    pub refresh_token: heapless::String<64>,
+    /// unix seconds, 0 if the clock was not set when the tokens were received
+    pub expires_at: u64,
+}
+
+/// Where the tokens are kept across reboots, on the board that is nvs
+pub trait TokenStore: Send + Sync {
+    fn load(&self) -> Result<Option<SavedTokens>>;
+    fn save(&self, tokens: &SavedTokens) -> Result<()>;
+}
+
+#[derive(Debug, Clone)]
+struct Tokens {
+    access_token: AuthToken,
+    refresh_token: String,
+    expires_at: Instant,
+    /// `expires_at` as unix seconds, 0 if the clock was not set
+    expires_at_unix: u64,
+}
+
+impl From<OauthResponseValid> for Tokens {
+    fn from(oauth: OauthResponseValid) -> Self {
+        let expires_in = oauth.expires_in.max(0) as u64;
+        Self {
+            access_token: oauth.access_token,
+            refresh_token: oauth.refresh_token,
+            expires_at: Instant::now() + Duration::from_secs(expires_in),
+            expires_at_unix: unix_now().map_or(0, |now| now + expires_in),
+        }
+    }
+}
+
+impl From<SavedTokens> for Tokens {
+    fn from(saved: SavedTokens) -> Self {
+        // without a clock there is no telling how long the token has left, so it is
+        // treated as expired and refreshed before the first request
+        let expires_in = match unix_now() {
+            Some(now) if saved.expires_at > 0 => saved.expires_at.saturating_sub(now),
+            _ => 0,
+        };
+        Self {
+            access_token: AuthToken::new(saved.access_token.to_string()),
+            refresh_token: saved.refresh_token.to_string(),
+            expires_at: Instant::now() + Duration::from_secs(expires_in),
+            expires_at_unix: saved.expires_at,
+        }
+    }
+}
+
+impl Tokens {
+    fn to_saved(&self, username: &heapless::String<100>) -> Result<SavedTokens> {
+        Ok(SavedTokens {
+            username: username.clone(),
+            access_token: self
+                .access_token
+                .token()
+                .parse()
+                .map_err(|_| anyhow!("online-go access token is too long to save"))?,
+            refresh_token: self
+                .refresh_token
+                .parse()
+                .map_err(|_| anyhow!("online-go refresh token is too long to save"))?,
+            expires_at: self.expires_at_unix,
+        })
+    }
+}
+
+/// Keeps the online-go access token valid, every authorized request gets its token from here
+///
+/// The token is renewed with the refresh token grant shortly before it expires (or when the
+/// server rejects it), the password grant is only used when the refresh token is refused too.
+/// Every new token is saved in the [`TokenStore`] (when given one) to be reused after a reboot
+pub struct TokenManager {
+    login: OnlineGoLoginInfo,
+    tokens: Mutex<Tokens>,
+    store: Option<Box<dyn TokenStore>>,
+}
+
+impl TokenManager {
+    /// `oauth` is the response of the grant that was used to log in
+    pub fn new(
+        login: OnlineGoLoginInfo,
+        oauth: OauthResponseValid,
+        store: Option<Box<dyn TokenStore>>,
+    ) -> Self {
+        let manager = Self {
+            login,
+            tokens: Mutex::new(oauth.into()),
+            store,
+        };
+        if let Ok(tokens) = manager.lock() {
+            manager.save(&tokens);
+        }
+        manager
+    }
+
+    /// Uses the tokens saved by a previous boot, `None` if there are none for this account
+    pub fn restore(login: OnlineGoLoginInfo, store: Box<dyn TokenStore>) -> Result<Option<Self>> {
+        let saved = store
+            .load()?
+            .filter(|saved| saved.username == login.username);
+        Ok(saved.map(|saved| Self {
+            login,
+            tokens: Mutex::new(saved.into()),
+            store: Some(store),
+        }))
+    }
+
+    /// A token that is valid for at least `REFRESH_MARGIN`
+    pub fn token(&self) -> Result<AuthToken> {
+        let mut tokens = self.lock()?;
+        if Instant::now() + REFRESH_MARGIN >= tokens.expires_at {
+            info!("online-go access token is about to expire, renewing it");
+            self.renew(&mut tokens)?;
+        }
+        Ok(tokens.access_token.clone())
+    }
+
+    /// Called when the server refused `rejected`, renews the token unless another request
+    /// already did
+    pub fn token_rejected(&self, rejected: &AuthToken) -> Result<AuthToken> {
+        let mut tokens = self.lock()?;
+        if tokens.access_token == *rejected {
+            warn!("online-go refused the access token, renewing it");
+            self.renew(&mut tokens)?;
+        }
+        Ok(tokens.access_token.clone())
+    }
+
+    fn lock(&self) -> Result<std::sync::MutexGuard<'_, Tokens>> {
+        self.tokens
+            .lock()
+            .map_err(|_| anyhow!("online-go token lock is poisoned"))
+    }
+
+    fn renew(&self, tokens: &mut Tokens) -> Result<()> {
+        let oauth = match auth_with_refresh_token(&tokens.refresh_token)? {
+            Ok(oauth) => oauth,
+            Err(err) => {
+                warn!("Failed to refresh the online-go token: {err}, logging in again");
+                self.login.auth_with_password()?.map_err(|err| {
+                    OgsError::unauthorized("log in to online-go with the password", err)
+                })?
+            }
+        };
+        *tokens = oauth.into();
+        self.save(tokens);
+        Ok(())
+    }
+
+    /// Failing to save only costs a password login at the next boot, so it is just logged
+    fn save(&self, tokens: &Tokens) {
+        let Some(store) = &self.store else {
+            return;
+        };
+        let result = tokens
+            .to_saved(&self.login.username)
+            .and_then(|saved| store.save(&saved));
+        if let Err(e) = result {
+            error!("Failed to save the online-go tokens: {e:?}");
+        }
+    }
+}
@@ -0,0 +1,397 @@
+//! The http client under [`super::https`]
+//!
+//! On the board the firmware sets esp-idf's client with [`set_transport`]. Until then, and
+//! everywhere else, a small blocking client over `std::net` is used, it only speaks plain http,
+//! which is enough to run the api against a local stand-in for online-go (see [`super::api::set_server`])
+
+use crate::onlinego::status_codes::StatusCode;
+use anyhow::{anyhow, bail, Result};
+use lazy_static::lazy_static;
+use serde::de::DeserializeOwned;
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+/// a request that gets no response within this long fails (and may be retried)
+pub const REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// The methods the api sends
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Method {
+    Get,
+    Post,
+}
+
+impl Method {
+    pub fn name(self) -> &'static str {
+        match self {
+            Method::Get => "GET",
+            Method::Post => "POST",
+        }
+    }
+}
+
+pub struct HttpRequest<'a> {
+    pub method: Method,
+    pub url: &'a str,
+    pub headers: &'a [(&'a str, &'a str)],
+    pub body: Option<&'a str>,
+}
+
+/// Bodies read with [`HttpResponse::text`] are cut off at this size, only error messages and
+/// small answers are read as text
+pub const MAX_TEXT_SIZE: u64 = 16 * 1024;
+
+/// Bodies parsed with [`HttpResponse::json`] are cut off at this size, a game record of a
+/// long game is well under it
+pub const MAX_JSON_SIZE: u64 = 256 * 1024;
+
+/// A response with the body still to be read, the connection stays open until it is dropped
+pub struct HttpResponse {
+    pub status: StatusCode,
+    /// the `Retry-After` header, only the delay in seconds form is understood
+    pub retry_after: Option<Duration>,
+    pub body: Box<dyn Read>,
+}
+
+impl HttpResponse {
+    /// Reads the whole body, at most [`MAX_TEXT_SIZE`] bytes of it
+    pub fn text(self) -> Result<String> {
+        let mut body = Vec::new();
+        CappedReader::new(self.body, MAX_TEXT_SIZE)
+            .read_to_end(&mut body)
+            .map_err(unwrap_too_large)?;
+        Ok(String::from_utf8(body)?)
+    }
+
+    /// Parses the body as it is read, without holding all of it in memory. Fails with
+    /// [`ResponseTooLarge`] if the body is longer than `max_size`
+    pub fn json<T: DeserializeOwned>(self, max_size: u64) -> Result<T> {
+        let reader = BufReader::new(CappedReader::new(self.body, max_size));
+        serde_json::from_reader(reader).map_err(|e| {
+            if e.is_io() {
+                unwrap_too_large(e.into())
+            } else {
+                e.into()
+            }
+        })
+    }
+}
+
+/// The body was longer than the caller was willing to read
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResponseTooLarge {
+    pub limit: u64,
+}
+
+impl Display for ResponseTooLarge {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "Response is larger than {} bytes", self.limit)
+    }
+}
+
+impl Error for ResponseTooLarge {}
+
+/// Takes the [`ResponseTooLarge`] back out of the `io::Error` it was wrapped in to get
+/// through `Read`
+fn unwrap_too_large(error: io::Error) -> anyhow::Error {
+    let too_large = error
+        .get_ref()
+        .and_then(|inner| inner.downcast_ref::<ResponseTooLarge>())
+        .copied();
+    match too_large {
+        Some(too_large) => too_large.into(),
+        None => error.into(),
+    }
+}
+
+/// Reads up to `limit` bytes and fails if there are more, unlike `Read::take` which would
+/// quietly cut the body off
+struct CappedReader<R> {
+    inner: R,
+    remaining: u64,
+    limit: u64,
+}
+
+impl<R: Read> CappedReader<R> {
+    fn new(inner: R, limit: u64) -> Self {
+        Self {
+            inner,
+            remaining: limit,
+            limit,
+        }
+    }
+}
+
+impl<R: Read> Read for CappedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        if self.remaining == 0 {
+            // the body may end right at the limit
+            return match self.inner.read(&mut [0])? {
+                0 => Ok(0),
+                _ => Err(io::Error::other(ResponseTooLarge { limit: self.limit })),
+            };
+        }
+        let max = buf
+            .len()
+            .min(self.remaining.try_into().unwrap_or(usize::MAX));
+        let size = self.inner.read(&mut buf[..max])?;
+        self.remaining -= size as u64;
+        Ok(size)
+    }
+}
+
+/// Sends a single request, retrying and authorization are handled by [`super::https`]
+pub trait HttpTransport: Send + Sync {
+    fn send(&self, request: &HttpRequest) -> Result<HttpResponse>;
+}
+
+lazy_static! {
+    static ref TRANSPORT: RwLock<Arc<dyn HttpTransport>> = RwLock::new(default_transport());
+}
+
+fn default_transport() -> Arc<dyn HttpTransport> {
+    Arc::new(HostTransport::default())
+}
+
+/// The transport every request goes through from now on
+pub fn set_transport(transport: impl HttpTransport + 'static) -> Result<()> {
+    *TRANSPORT
+        .write()
+        .map_err(|_| anyhow!("http transport lock is poisoned"))? = Arc::new(transport);
+    Ok(())
+}
+
+pub fn transport() -> Result<Arc<dyn HttpTransport>> {
+    Ok(TRANSPORT
+        .read()
+        .map_err(|_| anyhow!("http transport lock is poisoned"))?
+        .clone())
+}
+
+pub fn parse_retry_after(value: &str) -> Option<Duration> {
+    value.trim().parse().ok().map(Duration::from_secs)
+}
+
+/// Plain http/1.1 over a `TcpStream`, one connection per request. No tls, so only for
+/// talking to a local server
+pub struct HostTransport {
+    pub timeout: Duration,
+}
+
+impl Default for HostTransport {
+    fn default() -> Self {
+        Self {
+            timeout: REQUEST_TIMEOUT,
+        }
+    }
+}
+
+impl HttpTransport for HostTransport {
+    fn send(&self, request: &HttpRequest) -> Result<HttpResponse> {
+        let url = url::Url::parse(request.url)?;
+        if url.scheme() != "http" {
+            bail!("The host transport only speaks plain http, not {}", url);
+        }
+        let host = url.host_str().ok_or_else(|| anyhow!("{url} has no host"))?;
+        let port = url.port_or_known_default().unwrap_or(80);
+        let address = (host, port)
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| anyhow!("{host} did not resolve"))?;
+
+        let mut stream = TcpStream::connect_timeout(&address, self.timeout)?;
+        stream.set_read_timeout(Some(self.timeout))?;
+        stream.set_write_timeout(Some(self.timeout))?;
+
+        let body = request.body.unwrap_or_default();
+        let mut head = format!(
+            "{} {}",
+            request.method.name(),
+            &url[url::Position::BeforePath..url::Position::AfterQuery]
+        );
+        head.push_str(" HTTP/1.1\r\n");
+        head.push_str(&format!("Host: {host}:{port}\r\nConnection: close\r\n"));
+        head.push_str(&format!("Content-Length: {}\r\n", body.len()));
+        for (name, value) in request.headers {
+            head.push_str(&format!("{name}: {value}\r\n"));
+        }
+        head.push_str("\r\n");
+        stream.write_all(head.as_bytes())?;
+        stream.write_all(body.as_bytes())?;
+        stream.flush()?;
+
+        read_response(BufReader::new(stream))
+    }
+}
+
+fn read_response(mut reader: impl BufRead + 'static) -> Result<HttpResponse> {
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    // HTTP/1.1 200 OK
+    let status = line
+        .split_whitespace()
+        .nth(1)
+        .ok_or_else(|| anyhow!("Malformed status line {line:?}"))?;
+    let status = StatusCode::from_bytes(status.as_bytes())?;
+
+    let mut content_length = None;
+    let mut chunked = false;
+    let mut retry_after = None;
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            bail!("Connection closed in the response headers");
+        }
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        let Some((name, value)) = header.split_once(':') else {
+            bail!("Malformed header {header:?}");
+        };
+        let value = value.trim();
+        match name.trim().to_ascii_lowercase().as_str() {
+            "content-length" => content_length = Some(value.parse::<u64>()?),
+            "transfer-encoding" => chunked = value.eq_ignore_ascii_case("chunked"),
+            "retry-after" => retry_after = parse_retry_after(value),
+            _ => {}
+        }
+    }
+
+    let body: Box<dyn Read> = if chunked {
+        Box::new(ChunkedReader {
+            inner: reader,
+            remaining: 0,
+            done: false,
+        })
+    } else if let Some(content_length) = content_length {
+        Box::new(reader.take(content_length))
+    } else {
+        Box::new(reader)
+    };
+
+    Ok(HttpResponse {
+        status,
+        retry_after,
+        body,
+    })
+}
+
+/// Decodes a `Transfer-Encoding: chunked` body as it is read
+struct ChunkedReader<R> {
+    inner: R,
+    /// what is left of the current chunk
+    remaining: u64,
+    /// the last, empty, chunk was read
+    done: bool,
+}
+
+impl<R: BufRead> Read for ChunkedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.done || buf.is_empty() {
+            return Ok(0);
+        }
+        let mut line = String::new();
+        if self.remaining == 0 {
+            self.inner.read_line(&mut line)?;
+            // the size may be followed by extensions
+            let size = line.trim_end().split(';').next().unwrap_or_default();
+            self.remaining = u64::from_str_radix(size, 16)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            if self.remaining == 0 {
+                self.done = true;
+                return Ok(0);
+            }
+        }
+        let max = buf
+            .len()
+            .min(self.remaining.try_into().unwrap_or(usize::MAX));
+        let size = self.inner.read(&mut buf[..max])?;
+        if size == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        self.remaining -= size as u64;
+        if self.remaining == 0 {
+            // the crlf after the chunk
+            line.clear();
+            self.inner.read_line(&mut line)?;
+        }
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(raw: &'static str) -> HttpResponse {
+        read_response(BufReader::new(raw.as_bytes())).unwrap()
+    }
+
+    #[test]
+    fn bodies_up_to_the_limit_are_read() {
+        let mut body = String::new();
+        CappedReader::new("0123456789".as_bytes(), 10)
+            .read_to_string(&mut body)
+            .unwrap();
+        assert_eq!(body, "0123456789");
+    }
+
+    #[test]
+    fn bodies_over_the_limit_are_refused() {
+        let error = CappedReader::new("0123456789".as_bytes(), 9)
+            .read_to_end(&mut Vec::new())
+            .unwrap_err();
+        let error = unwrap_too_large(error);
+        assert_eq!(
+            error.downcast_ref::<ResponseTooLarge>(),
+            Some(&ResponseTooLarge { limit: 9 })
+        );
+    }
+
+    #[test]
+    fn too_large_json_is_not_a_parse_error() {
+        let response = response("HTTP/1.1 200 OK\r\nContent-Length: 13\r\n\r\n{\"a\":[1,2,3]}");
+        let error = response.json::<serde_json::Value>(8).unwrap_err();
+        assert!(error.is::<ResponseTooLarge>(), "{error:?}");
+    }
+
+    #[test]
+    fn chunked_bodies_are_decoded() {
+        let response = response(concat!(
+            "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\nRetry-After: 3\r\n\r\n",
+            "5\r\nhello\r\n",
+            "7;name=value\r\n, world\r\n",
+            "0\r\n\r\n",
+        ));
+        assert_eq!(response.status.as_u16(), 200);
+        assert_eq!(response.retry_after, Some(Duration::from_secs(3)));
+        assert_eq!(response.text().unwrap(), "hello, world");
+    }
+
+    #[test]
+    fn cut_off_chunks_fail() {
+        let response = response(concat!(
+            "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n",
+            "a\r\nhello",
+        ));
+        let error = response.text().unwrap_err();
+        let error = error.downcast_ref::<io::Error>().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn malformed_chunk_sizes_fail() {
+        let response = response("HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\nzz\r\n");
+        let error = response.text().unwrap_err();
+        let error = error.downcast_ref::<io::Error>().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,261 @@
+//! Runs the online-go api against the mock in `../mock-online-go`, one mock per test
+
+use go_board_core::game::board::{Point, Stone};
+use go_board_core::game::rules::Move;
+use go_board_core::onlinego::api::{
+    auth_with_refresh_token, get_current_player, get_current_player_games, get_games, submit_move,
+    GameFilter, MoveRejectionReason, OnlineGoLoginInfo, OnlineGoServer,
+};
+use go_board_core::onlinego::error::OgsErrorKind;
+use go_board_core::onlinego::token_manager::{SavedTokens, TokenManager, TokenStore};
+use mock_online_go::scenario::Scenario;
+use mock_online_go::MockServer;
+use serde_json::json;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+const BASIC: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/../mock-online-go/scenarios/basic.json"
+);
+
+/// The server is set for the whole process, so the tests take turns
+static SERVER: Mutex<()> = Mutex::new(());
+
+fn login() -> OnlineGoLoginInfo {
+    OnlineGoLoginInfo {
+        username: "board".into(),
+        password: "board-password".into(),
+    }
+}
+
+/// Boots the mock and points the api at it
+fn serve(scenario: Scenario) -> MutexGuard<'static, ()> {
+    let guard = SERVER
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    let port = MockServer::bind(scenario, "127.0.0.1:0")
+        .unwrap()
+        .spawn()
+        .unwrap();
+    go_board_core::onlinego::api::set_server(OnlineGoServer {
+        base_url: format!("http://127.0.0.1:{port}"),
+        client_id: "board".to_string(),
+    });
+    guard
+}
+
+/// Boots the mock, points the api at it and logs in
+fn start(scenario: Scenario) -> (MutexGuard<'static, ()>, TokenManager) {
+    let guard = serve(scenario);
+    let oauth = login().auth_with_password().unwrap().unwrap();
+    (guard, TokenManager::new(login(), oauth, None))
+}
+
+fn basic() -> Scenario {
+    Scenario::load(BASIC).unwrap()
+}
+
+/// basic.json with `script` run on top of it
+fn basic_with(script: serde_json::Value) -> Scenario {
+    let mut scenario: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(BASIC).unwrap()).unwrap();
+    scenario["script"] = script;
+    serde_json::from_value(scenario).unwrap()
+}
+
+/// basic.json with tokens that expire within the renewal margin
+fn short_lived_tokens() -> Scenario {
+    let mut scenario: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(BASIC).unwrap()).unwrap();
+    scenario["token_lifetime_secs"] = json!(60);
+    serde_json::from_value(scenario).unwrap()
+}
+
+/// Keeps the saved tokens where the test can look at them, like nvs on the board
+#[derive(Clone, Default)]
+struct MemoryStore(Arc<Mutex<Option<SavedTokens>>>);
+
+impl MemoryStore {
+    fn saved(&self) -> SavedTokens {
+        self.0.lock().unwrap().clone().expect("nothing was saved")
+    }
+}
+
+impl TokenStore for MemoryStore {
+    fn load(&self) -> anyhow::Result<Option<SavedTokens>> {
+        Ok(self.0.lock().unwrap().clone())
+    }
+
+    fn save(&self, tokens: &SavedTokens) -> anyhow::Result<()> {
+        *self.0.lock().unwrap() = Some(tokens.clone());
+        Ok(())
+    }
+}
+
+#[test]
+fn wrong_password_is_refused() {
+    let (_guard, _tokens) = start(basic());
+    let wrong = OnlineGoLoginInfo {
+        password: "wrong".into(),
+        ..login()
+    };
+    let refused = wrong.auth_with_password().unwrap().unwrap_err();
+    assert_eq!(refused.status_code.as_u16(), 401);
+    assert_eq!(refused.response.error, "invalid_grant");
+}
+
+#[test]
+fn games_are_listed_with_their_clock() {
+    let (_guard, tokens) = start(basic());
+    let player = get_current_player(&tokens).unwrap();
+    assert_eq!(player.username(), "board");
+
+    let list = get_current_player_games(&tokens).unwrap();
+    assert_eq!(list.games.len(), 1);
+    let games = get_games(&player, &GameFilter::default(), &tokens).unwrap();
+    assert_eq!(games.len(), 1);
+    let game = &games[0];
+    assert_eq!((game.id, game.width, game.height), (100, 9, 9));
+    assert_eq!(game.color_of(&player), Some(Stone::Black));
+    assert_eq!(game.is_turn_of(&player), Some(true));
+}
+
+#[test]
+fn games_are_listed_when_their_clock_fails() {
+    let (_guard, tokens) = start(basic_with(json!([
+        { "step": "fail", "path": "/api/v1/games/100", "status": 404 }
+    ])));
+    let player = get_current_player(&tokens).unwrap();
+    let games = get_games(&player, &GameFilter::default(), &tokens).unwrap();
+    assert_eq!(games.len(), 1);
+    assert!(games[0].clock.is_none());
+    assert_eq!(games[0].is_turn_of(&player), None);
+}
+
+#[test]
+fn moves_are_played_and_refused() {
+    let (_guard, tokens) = start(basic());
+    let game = get_current_player_games(&tokens).unwrap().games.remove(0);
+
+    submit_move(game.id, Move::Place(Point::new(4, 4)), &tokens)
+        .unwrap()
+        .unwrap();
+    let record = game.get_record(&tokens).unwrap();
+    // the move and the opponent's auto reply
+    assert_eq!(record.gamedata.moves.len(), 4);
+    let replay = record.replay().unwrap();
+    let board = &replay.position().board;
+    assert_eq!(board.get(Point::new(4, 4)), Some(Stone::Black));
+    assert_eq!(board.get(Point::new(0, 0)), Some(Stone::White));
+
+    let rejection = submit_move(game.id, Move::Place(Point::new(4, 4)), &tokens)
+        .unwrap()
+        .unwrap_err();
+    assert_eq!(rejection.reason, MoveRejectionReason::IllegalMove);
+
+    let mut missing = game.clone();
+    missing.id = 999;
+    let error = missing.get_record(&tokens).unwrap_err();
+    assert_eq!(error.kind(), OgsErrorKind::NotFound);
+    // a move in a game that does not exist is an error, not a refused move
+    let error = submit_move(missing.id, Move::Pass, &tokens).unwrap_err();
+    assert_eq!(error.kind(), OgsErrorKind::NotFound);
+}
+
+#[test]
+fn refused_tokens_are_renewed() {
+    let (_guard, tokens) = start(basic_with(json!([
+        { "step": "fail", "path": "/api/v1/me", "status": 401, "count": 1 }
+    ])));
+    let player = get_current_player(&tokens).unwrap();
+    assert_eq!(player.username(), "board");
+}
+
+#[test]
+fn moves_fail_when_the_token_stays_refused() {
+    let (_guard, tokens) = start(basic_with(json!([
+        { "step": "fail", "path": "/api/v1/games/100/move", "status": 401, "count": 2 }
+    ])));
+    let error = submit_move(100, Move::Pass, &tokens).unwrap_err();
+    assert_eq!(error.kind(), OgsErrorKind::Unauthorized);
+}
+
+#[test]
+fn forbidden_is_not_a_login_problem() {
+    let (_guard, tokens) = start(basic_with(json!([
+        { "step": "fail", "path": "/api/v1/me", "status": 403 }
+    ])));
+    let error = get_current_player(&tokens).unwrap_err();
+    assert_eq!(error.kind(), OgsErrorKind::Forbidden);
+    assert!(!error.kind().is_transient());
+}
+
+#[test]
+fn server_errors_are_retried() {
+    let (_guard, tokens) = start(basic_with(json!([
+        { "step": "fail", "path": "/api/v1/me/games", "status": 503, "count": 2 }
+    ])));
+    let list = get_current_player_games(&tokens).unwrap();
+    assert_eq!(list.games.len(), 1);
+}
+
+#[test]
+fn tokens_are_renewed_with_the_refresh_token_before_they_expire() {
+    let _guard = serve(short_lived_tokens());
+    let oauth = login().auth_with_password().unwrap().unwrap();
+    let (first_token, refresh_token) = (oauth.access_token.clone(), oauth.refresh_token.clone());
+    let store = MemoryStore::default();
+    let tokens = TokenManager::new(login(), oauth, Some(Box::new(store.clone())));
+    assert_eq!(store.saved().access_token.as_str(), first_token.token());
+
+    // 60 seconds left is within the margin, so the token is renewed before it is handed out
+    let renewed = tokens.token().unwrap();
+    assert_ne!(renewed, first_token);
+    assert_eq!(store.saved().access_token.as_str(), renewed.token());
+    // it was the refresh token that got used up, not the password
+    assert!(auth_with_refresh_token(&refresh_token).unwrap().is_err());
+    assert_eq!(get_current_player(&tokens).unwrap().username(), "board");
+}
+
+#[test]
+fn the_password_is_sent_when_the_refresh_token_is_refused() {
+    let _guard = serve(short_lived_tokens());
+    let oauth = login().auth_with_password().unwrap().unwrap();
+    let first_token = oauth.access_token.clone();
+    // refresh tokens only work once, the manager is left holding a spent one
+    auth_with_refresh_token(&oauth.refresh_token)
+        .unwrap()
+        .unwrap();
+    let store = MemoryStore::default();
+    let tokens = TokenManager::new(login(), oauth, Some(Box::new(store.clone())));
+
+    let renewed = tokens.token().unwrap();
+    assert_ne!(renewed, first_token);
+    assert_eq!(store.saved().access_token.as_str(), renewed.token());
+    assert_eq!(get_current_player(&tokens).unwrap().username(), "board");
+}
+
+#[test]
+fn saved_tokens_are_restored_for_the_same_account_only() {
+    let _guard = serve(basic());
+    let oauth = login().auth_with_password().unwrap().unwrap();
+    let store = MemoryStore::default();
+    TokenManager::new(login(), oauth, Some(Box::new(store.clone())));
+
+    let restored = TokenManager::restore(login(), Box::new(store.clone()))
+        .unwrap()
+        .expect("the tokens were saved");
+    assert_eq!(
+        restored.token().unwrap().token(),
+        store.saved().access_token.as_str()
+    );
+    assert_eq!(get_current_player(&restored).unwrap().username(), "board");
+
+    let other = OnlineGoLoginInfo {
+        username: "someone-else".into(),
+        ..login()
+    };
+    assert!(TokenManager::restore(other, Box::new(store))
+        .unwrap()
+        .is_none());
+}
@@ -0,0 +1,92 @@
+//! Parses the records in `fixtures/`, writes them back out and parses the result again
+
+use go_board_core::game::board::{Point, Stone};
+use go_board_core::game::rules::Move;
+use go_board_core::game::sgf::SgfGame;
+
+/// Parses `sgf` and checks writing it out gives back the same game
+fn round_trip(sgf: &str) -> SgfGame {
+    let game = SgfGame::parse(sgf).unwrap();
+    let written = game.to_sgf();
+    let reparsed = SgfGame::parse(&written).unwrap();
+    assert_eq!(reparsed, game, "{written}");
+    assert_eq!(reparsed.to_sgf(), written);
+    game
+}
+
+#[test]
+fn handicap_stones_are_setup() {
+    let game = round_trip(include_str!("fixtures/handicap.sgf"));
+    assert_eq!((game.width, game.height, game.handicap), (19, 19, 4));
+    assert_eq!(game.komi, 0.5);
+    assert_eq!(
+        game.setup_black,
+        [(3, 3), (3, 15), (15, 3), (15, 15)].map(|(x, y)| Point::new(x, y))
+    );
+    assert_eq!(game.first_player, Some(Stone::White));
+    assert_eq!(game.moves.len(), 8);
+    assert_eq!(
+        game.moves[0],
+        (Stone::White, Move::Place(Point::new(5, 16)))
+    );
+
+    let history = game.replay().unwrap();
+    let position = history.position();
+    assert_eq!(
+        position.board.iter().filter(|(_, s)| s.is_some()).count(),
+        12
+    );
+    assert_eq!(position.to_move, Stone::White);
+}
+
+#[test]
+fn passes_are_kept() {
+    let game = round_trip(include_str!("fixtures/passes.sgf"));
+    assert_eq!(game.result.as_deref(), Some("B+3.5"));
+    let passes = game.moves.iter().filter(|(_, mv)| *mv == Move::Pass);
+    // `tt` is read as a pass too, and written back as `[]`
+    assert_eq!(passes.count(), 3);
+    assert!(game.to_sgf().contains(";W[];B[];W[])"));
+    game.replay().unwrap();
+}
+
+#[test]
+fn comments_and_escapes_are_read() {
+    let game = round_trip(include_str!("fixtures/comments.sgf"));
+    assert_eq!(game.black_name.as_deref(), Some("Sm]ith"));
+    assert_eq!(game.white_name.as_deref(), Some("Back\\slash"));
+    let written = game.to_sgf();
+    assert!(written.contains(r"PB[Sm\]ith]PW[Back\\slash]"), "{written}");
+    // only the first variation is followed, the comments are dropped
+    assert_eq!(
+        game.moves,
+        [
+            (3, 3, Stone::Black),
+            (9, 9, Stone::White),
+            (9, 3, Stone::Black),
+            (3, 9, Stone::White)
+        ]
+        .map(|(x, y, stone)| (stone, Move::Place(Point::new(x, y))))
+    );
+    assert!(!written.contains("C["));
+}
+
+#[test]
+fn boards_need_not_be_square() {
+    let game = round_trip(include_str!("fixtures/rectangular.sgf"));
+    assert_eq!((game.width, game.height), (7, 5));
+    assert!(game.to_sgf().contains("SZ[7:5]"));
+    // `aa:bb` is the 2x2 corner
+    assert_eq!(game.setup_black.len(), 4);
+    assert_eq!(game.setup_white, [Point::new(3, 6)]);
+    let board = game.final_board().unwrap();
+    assert_eq!((board.height(), board.width()), (5, 7));
+    assert_eq!(board.get(Point::new(4, 5)), Some(Stone::White));
+    assert_eq!(board.get(Point::new(2, 2)), Some(Stone::Black));
+}
+
+#[test]
+fn oversized_boards_are_refused() {
+    assert!(SgfGame::parse("(;FF[4]SZ[26])").is_err());
+    assert!(SgfGame::parse("(;FF[4]SZ[9];B[jj])").is_err());
+}
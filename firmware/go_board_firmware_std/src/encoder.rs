@@ -1,25 +1,19 @@
 use anyhow::Result;
 use esp_idf_svc::hal::gpio::{AnyIOPin, AnyInputPin, Input, InterruptType, Level, PinDriver, Pull};
-use std::fmt::{Display, Formatter};
+use log::debug;
 use std::sync::Arc;
+use std::time::Duration;
+use tokio::select;
 use tokio::sync::broadcast::Sender;
 use tokio::sync::Notify;
+use tokio::time::Instant;
 
-#[derive(Clone, Copy, PartialEq, PartialOrd, Eq, Ord, Debug, Hash)]
-pub enum SpinDirection {
-    CounterClockwise,
-    Clockwise,
-}
-pub type EncoderInfo = (i32, SpinDirection);
+pub use go_board_core::encoder::{ButtonPress, EncoderInfo, SpinDirection};
 
-impl Display for SpinDirection {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        match self {
-            SpinDirection::CounterClockwise => f.write_str("(SD:Counter Clockwise)"),
-            SpinDirection::Clockwise => f.write_str("(SD:Clockwise)"),
-        }
-    }
-}
+/// presses shorter than this are contact bounce
+const DEBOUNCE: Duration = Duration::from_millis(30);
+/// presses held at least this long are long presses
+const LONG_PRESS: Duration = Duration::from_millis(800);
 
 pub struct RotaryEncoderState<'a> {
     clk: PinDriver<'a, AnyInputPin, Input>,
@@ -38,7 +32,8 @@ impl<'a> RotaryEncoderState<'a> {
         let (button_notify, button) = {
             let mut button = PinDriver::input(rotary_encoder_btn)?;
             button.set_pull(Pull::Up)?;
-            button.set_interrupt_type(InterruptType::PosEdge)?;
+            // both edges so the length of the press can be measured
+            button.set_interrupt_type(InterruptType::AnyEdge)?;
             let notify = Arc::new(Notify::new());
             let notifier = notify.clone();
             /// Make sure to call  `button.enable_interrupt()?;` before waiting for notification
@@ -80,23 +75,50 @@ impl<'a> RotaryEncoderState<'a> {
         self.button.get_level() == Level::Low
     }
 
-    pub async fn monitor_encoder_spin(&mut self, on_change: Sender<EncoderInfo>) -> Result<()> {
+    pub async fn monitor_encoder(
+        &mut self,
+        on_change: Sender<EncoderInfo>,
+        on_press: Sender<ButtonPress>,
+    ) -> Result<()> {
         let mut current_direction;
         let mut counter = 0;
+        let mut pressed_at: Option<Instant> = None;
+        let clk_notify = self.clk_notify.clone();
+        let button_notify = self.button_notify.clone();
 
         loop {
             self.clk.enable_interrupt()?;
-            self.clk_notify.notified().await;
-            if self.clk.get_level() != self.dt.get_level() {
-                current_direction = SpinDirection::Clockwise;
-                counter += 1;
-            } else {
-                current_direction = SpinDirection::CounterClockwise;
-                counter -= 1;
+            self.button.enable_interrupt()?;
+            select! {
+                _ = clk_notify.notified() => {
+                    if self.clk.get_level() != self.dt.get_level() {
+                        current_direction = SpinDirection::Clockwise;
+                        counter += 1;
+                    } else {
+                        current_direction = SpinDirection::CounterClockwise;
+                        counter -= 1;
+                    }
+                    debug!("counter: {}, direction: {}", counter, current_direction);
+                    on_change.send((counter, current_direction))?;
+                }
+                _ = button_notify.notified() => {
+                    if self.is_button_pressed() {
+                        pressed_at.get_or_insert_with(Instant::now);
+                    } else if let Some(pressed_at) = pressed_at.take() {
+                        let held = pressed_at.elapsed();
+                        if held < DEBOUNCE {
+                            continue;
+                        }
+                        let press = if held >= LONG_PRESS {
+                            ButtonPress::Long
+                        } else {
+                            ButtonPress::Short
+                        };
+                        debug!("button press: {press:?} ({held:?})");
+                        on_press.send(press)?;
+                    }
+                }
             }
-            println!("counter: {}, direction: {}", counter, current_direction);
-            on_change.send((counter, current_direction))?;
         }
-        Ok(())
     }
 }
@@ -4,21 +4,32 @@ use std::iter::{Enumerate, FlatMap, Map};
 use std::num::NonZeroU32;
 use std::slice::Iter;
 use std::str;
-use std::sync::Arc;
 
-use crate::encoder::{EncoderInfo, RotaryEncoderState};
-use crate::neopixel::led_ctrl::{led_ctrl, DisplayOnLeds, LedChange, LedOverlay};
-use crate::neopixel::led_font::score_board;
-use crate::neopixel::rgb::Rgb;
+use crate::encoder::{ButtonPress, EncoderInfo, RotaryEncoderState, SpinDirection};
+use crate::game::move_entry::{Input, MoveEntry, Rejection, Submission};
+use crate::game::picker::{GamePicker, SelectedGame};
+use crate::game::rules::Move;
+use crate::game::removal::{RemovalAction, StoneRemoval};
+use crate::game::replay::GameReplay;
+use crate::game::scoring::{dead_stones_from_removal, score, ScoringRules};
+use crate::neopixel::animation::Animation;
+use crate::neopixel::frame::{Frame, LedDisplay, Region};
+use crate::neopixel::go_board::{animate_markers, CURSOR_BLINK, LAST_MOVE_PULSE};
+use crate::neopixel::led_ctrl::{led_ctrl, DisplayOnLeds, Layer, LedChange};
+use crate::neopixel::led_font::{score_board, Font, Marquee};
+use crate::neopixel::rgb::{Rgb, RED, WHITE};
 use crate::onlinego::api;
 use crate::onlinego::api::{
-    test_connection, BoardColor, BoardState, GameListData, OauthResponseValid, OnlineGoLoginInfo,
-    Player,
+    test_connection, BoardColor, BoardState, GameFilter, GameListData, GameRecord,
+    MoveRejectionReason, OnlineGoLoginInfo, Player,
 };
-use crate::onlinego::auth_token::AuthToken;
+use crate::onlinego::client::{unblock, OgsClient};
+use crate::onlinego::error::{OgsError, OgsErrorKind};
+use crate::onlinego::token_manager::TokenManager;
+use crate::onlinego::websocket::{OgsSocket, SocketEvent};
 use crate::restart_recovery::{restart_with_recover_option, RecoverOption};
 use crate::setup::setup;
-use crate::storage::SaveInNvs;
+use crate::storage::{NvsTokenStore, SaveInNvs};
 use crate::wifi::{WifiCredentials, WifiLoop};
 use anyhow::anyhow;
 use anyhow::bail;
@@ -33,14 +44,15 @@ use esp_idf_svc::hal::gpio::Pull;
 use esp_idf_svc::hal::prelude::*;
 use esp_idf_svc::nvs::EspDefaultNvsPartition;
 use esp_idf_svc::sys;
+use esp_idf_svc::sntp::EspSntp;
 use esp_idf_svc::sys::{esp, esp_app_desc};
 use esp_idf_svc::timer::EspTaskTimerService;
 use esp_idf_svc::wifi::{AsyncWifi, EspWifi};
-use log::{error, info};
+use log::{error, info, warn};
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::net::{TcpListener, TcpStream};
 use tokio::sync::broadcast::{Receiver as BrReceiver, Sender as BrSender};
-use tokio::sync::mpsc::{Receiver, Sender};
+use tokio::sync::mpsc::Receiver;
 use tokio::sync::{broadcast, mpsc};
 use tokio::time::sleep;
 use tokio::time::Duration;
@@ -55,8 +67,13 @@ mod setup;
 mod storage;
 mod wifi;
 
-const BOARD_SIZE: usize = 16;
+use go_board_core::{game, BOARD_SIZE};
+
 const CHANNEL_SIZE: usize = BOARD_SIZE * 2;
+/// the largest games listed, boards larger than the matrix are panned around
+const MAX_GAME_SIZE: i32 = 19;
+/// how often the leds are redrawn while an animation plays
+const LED_FPS: u32 = 30;
 
 // To test, run `cargo run`, then when the settings is up, use `nc -v espressif 12345` from
 // a machine on the same Wi-Fi network.
@@ -66,16 +83,27 @@ const TCP_LISTENING_PORT: u16 = 12345;
 // esp_app_desc!();
 
 fn main() -> Result<()> {
+    onlinego::configure()?;
     let (
         (wifi_creds, wifi),
-        (rotary_encoder_state, encoder_info_tx, encoder_info_rx),
-        (led_change_rx, led_change_tx, board_led_grid_pin, rmt_channel0),
+        (
+            rotary_encoder_state,
+            encoder_info_tx,
+            encoder_info_rx,
+            button_press_tx,
+            button_press_rx,
+        ),
+        (frame_rx, led_display, board_led_grid_pin, rmt_channel0),
         nvs,
     ) = setup()?;
 
     info!("Starting async run loop");
     tokio::runtime::Builder::new_current_thread()
         .enable_all()
+        // the blocking pool sends the online-go requests (see `OgsClient`), one at a time is
+        // plenty and every thread needs a stack big enough for the tls handshake
+        .max_blocking_threads(1)
+        .thread_stack_size(16 * 1024)
         .build()?
         .block_on(async move {
             let mut wifi_loop = WifiLoop::new(wifi);
@@ -96,28 +124,43 @@ fn main() -> Result<()> {
                 },
                 Some(tok) => tok,
             };
-            let OauthResponseValid { access_token, .. } = match login_info.auth_with_password()? {
-                Ok(token) => token,
-                Err(err) => {
-                    error!("Failed to log in to online-go: {:?} \n restarting...", err);
-                    restart_with_recover_option(RecoverOption::ForceSettingsPanel, nvs.clone())?;
-                }
+            // the clock tells how long the tokens saved by the last boot have left,
+            // keep it around or else the SNTP service will stop
+            let _sntp = EspSntp::new_default()?;
+            // renews the access token before it expires, the password is only sent
+            // when there are no saved tokens or the refresh token is refused
+            let store = || Box::new(NvsTokenStore(nvs.clone()));
+            let tokens = match TokenManager::restore(login_info.clone(), store())? {
+                Some(tokens) => tokens,
+                None => match unblock({
+                    let login_info = login_info.clone();
+                    move || login_info.auth_with_password()
+                })
+                .await??
+                {
+                    Ok(oauth) => TokenManager::new(login_info, oauth, Some(store())),
+                    Err(err) => {
+                        error!("Failed to log in to online-go: {:?} \n restarting...", err);
+                        restart_with_recover_option(RecoverOption::ForceSettingsPanel, nvs.clone())?;
+                    }
+                },
             };
-            // todo: handle getting new token when first expires
-            let auth_token = access_token;
 
             // Check for current
 
             info!("Preparing to launch rotary encoder monitor...");
             let mut rc = tokio::spawn(async {
                 let mut rotary_encoder = rotary_encoder_state;
-                rotary_encoder.monitor_encoder_spin(encoder_info_tx).await
+                rotary_encoder
+                    .monitor_encoder(encoder_info_tx, button_press_tx)
+                    .await
             });
             info!("Preparing to launch led blinker...");
             let mut led = tokio::spawn(led_ctrl::<{ BOARD_SIZE * BOARD_SIZE }, { BOARD_SIZE }>(
                 board_led_grid_pin,
                 rmt_channel0,
-                led_change_rx,
+                frame_rx,
+                LED_FPS,
             ));
             // info!("Preparing to launch echo settings...");
             // tokio::spawn(echo_server(tx.clone()));
@@ -125,10 +168,12 @@ fn main() -> Result<()> {
             info!("Entering main Wi-Fi run loop...");
             let mut wifi = tokio::spawn(wifi_loop.stay_connected());
             info!("starting main loop");
-            let mut main_loop = tokio::spawn(main_loop(
-                led_change_tx.clone(),
+            let mut main_loop = tokio::spawn(run_main_loop(
+                led_display.clone(),
                 encoder_info_rx,
-                auth_token,
+                button_press_rx,
+                OgsClient::new(tokens),
+                nvs.clone(),
             ));
 
             return select! {
@@ -154,108 +199,583 @@ fn main() -> Result<()> {
     Ok(())
 }
 
-const BLACK_SPOT: Rgb = Rgb::new(50, 0, 0);
-const WHITE_SPOT: Rgb = Rgb::new(0, 50, 0);
-
-const EMPTY_SPOT: Rgb = Rgb::new(0, 0, 0);
+/// how long to wait before starting over after online-go or the network had trouble
+const MAIN_LOOP_RETRY_DELAY: Duration = Duration::from_secs(30);
+/// how long any other error is shown before going back to the game list
+const ERROR_SHOWN: Duration = Duration::from_secs(10);
+const ERROR_BLINK: Animation = Animation::Blink {
+    period: Duration::from_secs(1),
+};
 
-async fn main_loop(
-    led_tx: Sender<LedChange>,
+/// Runs [`main_loop`] over and over, it returns when the game moved on or the player went back
+/// to the game list. Starts over in the same game after errors that may go away on their own,
+/// goes back to the game list after any other error (ie a refused move or an unknown api
+/// response) and to the settings panel when the online-go login stopped working. Only the
+/// leds failing ends it
+async fn run_main_loop(
+    display: LedDisplay,
     mut encoder_rx: BrReceiver<EncoderInfo>,
-    auth_token: AuthToken,
+    mut button_rx: BrReceiver<ButtonPress>,
+    client: OgsClient,
+    nvs: EspDefaultNvsPartition,
 ) -> Result<()> {
-    let current_player = api::get_current_player(&auth_token)?;
+    loop {
+        let Err(error) = main_loop(&display, &mut encoder_rx, &mut button_rx, &client, &nvs).await
+        else {
+            continue;
+        };
+        let (message, delay) = match error.downcast_ref::<OgsError>().map(OgsError::kind) {
+            Some(OgsErrorKind::Unauthorized) => {
+                error!("online-go refused the login: {error:?} \n restarting...");
+                restart_with_recover_option(RecoverOption::ForceSettingsPanel, nvs.clone())?
+            }
+            Some(kind) if kind.is_transient() => {
+                error!("main loop failed: {error:?}, starting over in {MAIN_LOOP_RETRY_DELAY:?}");
+                (format!("{kind:?} error"), MAIN_LOOP_RETRY_DELAY)
+            }
+            _ => {
+                error!("main loop failed: {error:?}, going back to the game list");
+                // reopening the game would most likely fail the same way
+                if let Err(e) = SelectedGame::remove_saved_in_nvs(nvs.clone()) {
+                    warn!("Couldn't forget the selected game: {e:?}");
+                }
+                (error.to_string(), ERROR_SHOWN)
+            }
+        };
+        display.show_on(Layer::Error, error_corners()).await?;
+        display.animate(Layer::Error, ERROR_BLINK).await?;
+        // what went wrong scrolls by until the main loop starts over
+        Marquee::centered(message, Font::Large, RED)
+            .play(&display, Layer::Notification, delay)
+            .await?;
+        display.clear(Layer::Notification).await?;
+        display.clear(Layer::Error).await?;
+    }
+}
+
+/// Red corners over whatever is showing while the main loop waits to start over
+fn error_corners() -> Frame {
+    let last = BOARD_SIZE as u8 - 1;
+    Frame::from_changes(
+        [(0, 0), (0, last), (last, 0), (last, last)].map(|(x, y)| LedChange::new(x, y, RED)),
+    )
+}
 
-    let game_list = api::get_current_player_games(&auth_token)?;
+/// how long the score screen takes to fade over the replay and back
+const SCORE_FADE: Duration = Duration::from_millis(400);
+
+/// What is shown over the replay of a finished game, stepping past its last move
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum ReplayEnd {
+    /// just the replay
+    Moves,
+    Score,
+    /// online-go's result, like `W+12.5`
+    Outcome,
+}
 
-    // TODO:Select A specific game, rn just picks the first in the list
+/// How [`active_game`] ended
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum GameExit {
+    /// the game left the playing phase, ie it was resigned or both players passed
+    PhaseChanged,
+    /// the player went back to the game list
+    Left,
+}
 
-    if let None = game_list.games.first() {
+async fn main_loop(
+    display: &LedDisplay,
+    encoder_rx: &mut BrReceiver<EncoderInfo>,
+    button_rx: &mut BrReceiver<ButtonPress>,
+    client: &OgsClient,
+    nvs: &EspDefaultNvsPartition,
+) -> Result<()> {
+    let current_player = client.call(api::get_current_player).await?;
+
+    let player = current_player.clone();
+    let filter = GameFilter {
+        max_size: Some(MAX_GAME_SIZE),
+        ..Default::default()
+    };
+    let games = client
+        .call(move |tokens| api::get_games(&player, &filter, tokens))
+        .await?;
+
+    if games.is_empty() {
         return Err(anyhow!(
-            "Failed to get a game in game list, game list len  < 1"
+            "No running game of at most {MAX_GAME_SIZE}x{MAX_GAME_SIZE}"
         ));
     }
-    // TODO: Make sure this ARC<game> is required, prob could get away with local refs
-    let current_game = Arc::new(game_list.games.first().unwrap().clone());
-
-    let game_board_data = current_game.get_detail(&auth_token)?;
-    // TODO: handle the errors in a way that the user can see, maybe store in nvs?
-
-    let overlay = LedOverlay::<{ BOARD_SIZE }, { BOARD_SIZE }, { 2 }>::new();
-
-    // is the game complete?
-    if (current_game.is_game_over()
-        //TODO: Remove this after testing
-        || true)
-    {
-        let gameboard_changes: Vec<LedChange> = game_board_data
-            .board_iter()
-            .map(|(x, y, v)| {
-                let color: BoardColor = {
-                    let res = (*v).try_into();
-                    res.unwrap_or_else(|err| {
-                        error!("Unknown Board Color:{err}");
-                        BoardColor::Empty
-                    })
-                };
-
-                let rgb = match color {
-                    BoardColor::Empty => EMPTY_SPOT,
-                    BoardColor::Black => BLACK_SPOT,
-                    BoardColor::White => WHITE_SPOT,
-                };
-
-                LedChange::new(x, y, rgb)
-            })
-            .collect();
-        let score_changes: heapless::Vec<LedChange, 68> = score_board(0, 0, 123, 432);
-
-        // todo: create score display for led panel
-        // let score_changes = current_game.
-
-        let mut show_board = true;
-        loop {
-            // wait for encoder to move
-            let _ = encoder_rx.recv().await?;
-            // alternate between showing the end board state and the score
-            if (show_board) {
-                for change in &gameboard_changes {
-                    led_tx.send(*change).await?;
+    let saved_game_id = SelectedGame::get_saved_in_nvs(nvs.clone())
+        .unwrap_or_else(|e| {
+            warn!("Couldn't read the selected game: {e:?}");
+            None
+        })
+        .map(|saved| saved.game_id);
+
+    // the game open before a reboot is reopened while it is still running
+    let current_game = match games.iter().find(|game| Some(game.id) == saved_game_id) {
+        Some(game) => game.clone(),
+        None => {
+            let game = pick_game(
+                display,
+                encoder_rx,
+                button_rx,
+                &current_player,
+                games,
+                saved_game_id,
+            )
+            .await?;
+            if let Err(e) = (SelectedGame { game_id: game.id }).set_saved_in_nvs(nvs.clone()) {
+                warn!("Couldn't save the selected game: {e:?}");
+            }
+            game
+        }
+    };
+    open_game(
+        display,
+        encoder_rx,
+        button_rx,
+        client,
+        &current_player,
+        &current_game,
+    )
+    .await?;
+    info!("leaving game {}", current_game.id);
+    // so the game list is shown when the main loop starts over
+    if let Err(e) = SelectedGame::remove_saved_in_nvs(nvs.clone()) {
+        warn!("Couldn't forget the selected game: {e:?}");
+    }
+    Ok(())
+}
+
+/// Shows `game` in the phase online-go has it in and follows it from playing through stone
+/// removal to finished, returns once the player went back to the game list
+async fn open_game(
+    display: &LedDisplay,
+    encoder_rx: &mut BrReceiver<EncoderInfo>,
+    button_rx: &mut BrReceiver<ButtonPress>,
+    client: &OgsClient,
+    player: &Player,
+    game: &GameListData,
+) -> Result<()> {
+    loop {
+        let state = get_detail(client, game).await?;
+        if state.finished() {
+            return finished_game(display, encoder_rx, button_rx, client, game, state).await;
+        }
+        if state.is_stone_removal() {
+            stone_removal(display, encoder_rx, button_rx, client, game, state).await?;
+            continue;
+        }
+        if state.phase != "play" {
+            bail!("Game {} is in a phase the board doesn't know: {}", game.id, state.phase);
+        }
+        let (socket_tx, mut socket_rx) = mpsc::channel(CHANNEL_SIZE);
+        let socket = OgsSocket::spawn(player.id(), socket_tx);
+        socket.connect_game(game.id).await?;
+        let exit = active_game(
+            display,
+            encoder_rx,
+            button_rx,
+            client,
+            &mut socket_rx,
+            player,
+            game,
+        )
+        .await?;
+        if exit == GameExit::Left {
+            return Ok(());
+        }
+    }
+}
+
+/// Replays a finished game with its score and result, returns once the player went back to
+/// the game list
+async fn finished_game(
+    display: &LedDisplay,
+    encoder_rx: &mut BrReceiver<EncoderInfo>,
+    button_rx: &mut BrReceiver<ButtonPress>,
+    client: &OgsClient,
+    game: &GameListData,
+    state: BoardState,
+) -> Result<()> {
+    let record = get_record(client, game).await?;
+    let final_position = record.replay()?.position().clone();
+    let final_score = score(
+        &state.to_board()?,
+        &dead_stones_from_removal(&state.removal),
+        final_position.black_captures,
+        final_position.white_captures,
+        record.gamedata.komi,
+        ScoringRules::from_ogs_rules(&record.gamedata.rules),
+    );
+    info!("final score: {final_score:?}");
+    let score_frame = Frame::from_changes(score_board(&final_score));
+    let mut outcome = record
+        .result()
+        .map(|result| Marquee::centered(result, Font::Large, WHITE));
+
+    let mut replay = GameReplay::new(record.initial_position()?, record.moves().collect())?;
+    replay.jump();
+    replay.display(display).await?;
+    display.animate(Layer::LastMove, LAST_MOVE_PULSE).await?;
+
+    // clockwise steps forward a move, stepping past the last move shows the score and then
+    // online-go's result, counter-clockwise steps back and a long press jumps to the start
+    // or end. a short press switches to panning the window around boards larger than the matrix,
+    // a long press on the score or result goes back to the game list
+    let mut end = ReplayEnd::Moves;
+    let mut shown = ReplayEnd::Moves;
+    let mut panning = false;
+    loop {
+        let move_number = replay.move_number();
+        let timeout = match &outcome {
+            Some(outcome) if end == ReplayEnd::Outcome => outcome.timeout(),
+            _ => None,
+        };
+        select! {
+            spin = encoder_rx.recv() => match spin?.1 {
+                direction if panning => replay.pan(direction),
+                SpinDirection::Clockwise if replay.is_at_end() => {
+                    end = match end {
+                        ReplayEnd::Moves => ReplayEnd::Score,
+                        _ if outcome.is_some() => ReplayEnd::Outcome,
+                        end => end,
+                    };
+                }
+                SpinDirection::Clockwise => {
+                    replay.forward();
+                }
+                SpinDirection::CounterClockwise => match end {
+                    ReplayEnd::Outcome => {
+                        end = ReplayEnd::Score;
+                        outcome.iter_mut().for_each(Marquee::reset);
+                    }
+                    ReplayEnd::Score => end = ReplayEnd::Moves,
+                    ReplayEnd::Moves => {
+                        replay.back();
+                    }
+                },
+            },
+            press = button_rx.recv() => match press? {
+                ButtonPress::Short => panning = !panning && end == ReplayEnd::Moves,
+                ButtonPress::Long if end != ReplayEnd::Moves => break,
+                ButtonPress::Long => {
+                    panning = false;
+                    replay.jump();
+                }
+            },
+            _ = async {
+                match timeout {
+                    Some(timeout) => sleep(timeout).await,
+                    None => std::future::pending().await,
                 }
-            } else {
-                for change in &score_changes {
-                    led_tx.send(*change).await?;
+            } => {
+                if let Some(outcome) = &mut outcome {
+                    outcome.tick();
                 }
             }
-            show_board = !show_board;
         }
-    } else {
+        match (end, shown) {
+            (ReplayEnd::Moves, ReplayEnd::Moves) => {}
+            (ReplayEnd::Moves, _) => display.fade_out(Layer::Notification, SCORE_FADE).await?,
+            (ReplayEnd::Score, ReplayEnd::Moves) => {
+                display
+                    .fade_in(Layer::Notification, score_frame, SCORE_FADE)
+                    .await?
+            }
+            (ReplayEnd::Score, _) => display.show_on(Layer::Notification, score_frame).await?,
+            (ReplayEnd::Outcome, _) => {
+                if let Some(outcome) = &outcome {
+                    let frame = Frame::from_changes(outcome.render());
+                    display.show_on(Layer::Notification, frame).await?;
+                }
+            }
+        }
+        shown = end;
+        if replay.move_number() == move_number {
+            replay.display(display).await?;
+        } else {
+            replay.display_move(display).await?;
+        }
     }
+    display.clear(Layer::Notification).await?;
     Ok(())
 }
 
-async fn requester(tx: Sender<LedChange>) -> Result<()> {
+/// Shows the games on the matrix until one is opened with a press
+async fn pick_game(
+    display: &LedDisplay,
+    encoder_rx: &mut BrReceiver<EncoderInfo>,
+    button_rx: &mut BrReceiver<ButtonPress>,
+    player: &Player,
+    games: Vec<GameListData>,
+    selected_id: Option<i64>,
+) -> Result<GameListData> {
+    let mut picker = GamePicker::new(games, player, selected_id);
+    // the markers of the game shown before
+    display.clear(Layer::LastMove).await?;
+    display.clear(Layer::Cursor).await?;
+    loop {
+        picker.display(display).await?;
+        let timeout = picker.timeout();
+        select! {
+            spin = encoder_rx.recv() => picker.on_spin(spin?.1),
+            press = button_rx.recv() => {
+                if let Some(game) = picker.on_press(press?) {
+                    info!("opening game {}: {}", game.id, game.description());
+                    return Ok(game.clone());
+                }
+            }
+            _ = async {
+                match timeout {
+                    Some(timeout) => sleep(timeout).await,
+                    None => std::future::pending().await,
+                }
+            } => picker.tick(),
+        }
+    }
+}
+
+/// how often the game is re-fetched in case the socket missed a move
+const GAME_POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Fetches the game and plays the moves that were not played yet, fading them in
+async fn catch_up(
+    display: &LedDisplay,
+    entry: &mut MoveEntry,
+    client: &OgsClient,
+    game: &GameListData,
+    moves_played: &mut usize,
+) -> Result<()> {
+    let record = get_record(client, game).await?;
+    let missed = record.gamedata.moves.get(*moves_played..).unwrap_or_default();
+    for game_move in missed {
+        entry.play(game_move.to_move())?;
+        *moves_played += 1;
+    }
+    if !missed.is_empty() {
+        entry.display_move(display).await?;
+    }
+    Ok(())
+}
+
+async fn get_detail(client: &OgsClient, game: &GameListData) -> Result<BoardState> {
+    let game = game.clone();
+    client.call(move |tokens| game.get_detail(tokens)).await
+}
+
+async fn get_record(client: &OgsClient, game: &GameListData) -> Result<GameRecord> {
+    let game = game.clone();
+    client.call(move |tokens| game.get_record(tokens)).await
+}
+
+/// Lets the player enter moves with the encoder, returns once the game left the playing phase
+/// or the player left the game
+async fn active_game(
+    display: &LedDisplay,
+    encoder_rx: &mut BrReceiver<EncoderInfo>,
+    button_rx: &mut BrReceiver<ButtonPress>,
+    client: &OgsClient,
+    socket_rx: &mut Receiver<SocketEvent>,
+    player: &Player,
+    game: &GameListData,
+) -> Result<GameExit> {
+    let game_id = game.id;
+    let player_color = game
+        .color_of(player)
+        .ok_or_else(|| anyhow!("{player} is not playing in game {}", game.id))?;
+    let record = get_record(client, game).await?;
+    // every move online-go knows of, including handicap stones, like `SocketEvent::Move`
+    let mut moves_played = record.gamedata.moves.len();
+    let last_move = record.moves().last().and_then(|mv| match mv {
+        Move::Place(point) => Some(point),
+        Move::Pass => None,
+    });
+    let mut entry = MoveEntry::new(
+        record.replay()?,
+        player_color,
+        last_move,
+        record.gamedata.komi,
+        ScoringRules::from_ogs_rules(&record.gamedata.rules),
+    );
+    entry.display(display).await?;
+    animate_markers(display).await?;
+    let mut poll = tokio::time::interval(GAME_POLL_INTERVAL);
+    loop {
+        entry.display(display).await?;
+        let timeout = entry.timeout();
+        let input = select! {
+            spin = encoder_rx.recv() => Input::Spin(spin?),
+            press = button_rx.recv() => Input::Press(press?),
+            _ = async {
+                match timeout {
+                    Some(timeout) => sleep(timeout).await,
+                    None => std::future::pending().await,
+                }
+            } => Input::Timeout,
+            event = socket_rx.recv() => {
+                match event.ok_or_else(|| anyhow!("Online-go socket stopped"))? {
+                    SocketEvent::Move { game_id, move_number, mv } if game_id == game.id => {
+                        // our own moves come back too, they were already played
+                        if move_number == moves_played + 1 {
+                            entry.play(mv)?;
+                            moves_played += 1;
+                            entry.display_move(display).await?;
+                        } else if move_number > moves_played {
+                            catch_up(display, &mut entry, client, game, &mut moves_played).await?;
+                        }
+                    }
+                    SocketEvent::Phase { game_id, phase } if game_id == game.id && phase != "play" => {
+                        return Ok(GameExit::PhaseChanged);
+                    }
+                    // moves may have been missed while disconnected
+                    SocketEvent::Connected => {
+                        catch_up(display, &mut entry, client, game, &mut moves_played).await?;
+                    }
+                    _ => {}
+                }
+                continue;
+            }
+            _ = poll.tick() => {
+                catch_up(display, &mut entry, client, game, &mut moves_played).await?;
+                if get_detail(client, game).await?.phase != "play" {
+                    return Ok(GameExit::PhaseChanged);
+                }
+                continue;
+            }
+        };
+        match entry.handle(input) {
+            None => {}
+            Some(Submission::Play(mv)) => match client
+                .call(move |tokens| api::submit_move(game_id, mv, tokens))
+                .await?
+            {
+                Ok(()) => {
+                    entry.play(mv)?;
+                    moves_played += 1;
+                    entry.display_move(display).await?;
+                }
+                Err(rejection) => {
+                    let played = match mv {
+                        Move::Place(point) => point.go_coordinate(game.height as usize),
+                        Move::Pass => "pass".to_string(),
+                    };
+                    info!("online-go refused {played}: {rejection}");
+                    entry.refuse(match rejection.reason {
+                        MoveRejectionReason::NotYourTurn => Rejection::NotYourTurn,
+                        _ => Rejection::Refused,
+                    });
+                }
+            },
+            // the game is over, the main loop shows how it ended
+            Some(Submission::Resign) => match client
+                .call(move |tokens| api::resign(game_id, tokens))
+                .await?
+            {
+                Ok(()) => return Ok(GameExit::PhaseChanged),
+                Err(rejection) => {
+                    info!("online-go refused the resignation: {rejection}");
+                    entry.refuse(Rejection::Refused);
+                }
+            },
+            Some(Submission::Leave) => return Ok(GameExit::Left),
+        }
+        if let Some(rejection) = entry.rejection() {
+            info!("move refused: {rejection}");
+        }
+    }
+}
+
+/// how often the proposal is re-fetched during stone removal to pick up the opponent's changes
+const REMOVAL_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Lets the player mark dead stones and accept or reject the proposal,
+/// returns once the game has left the stone removal phase
+async fn stone_removal(
+    display: &LedDisplay,
+    encoder_rx: &mut BrReceiver<EncoderInfo>,
+    button_rx: &mut BrReceiver<ButtonPress>,
+    client: &OgsClient,
+    game: &GameListData,
+    state: BoardState,
+) -> Result<()> {
+    let game_id = game.id;
+    let mut removal = StoneRemoval::new(state.to_board()?, &state.removal);
+    removal.display(display).await?;
+    display.animate(Layer::Cursor, CURSOR_BLINK).await?;
+    let mut poll = tokio::time::interval(REMOVAL_POLL_INTERVAL);
+    loop {
+        removal.display(display).await?;
+        select! {
+            spin = encoder_rx.recv() => {
+                let (_, direction) = spin?;
+                removal.on_spin(direction);
+            }
+            press = button_rx.recv() => match removal.on_press(press?) {
+                None => {}
+                Some(RemovalAction::SetRemoved { stones, removed }) => {
+                    let set = move |tokens: &TokenManager| {
+                        api::set_removed_stones(game_id, &stones, removed, tokens)
+                    };
+                    client.call(set).await?;
+                }
+                Some(RemovalAction::Accept { removed }) => {
+                    info!("accepting {} removed stones", removed.len());
+                    client
+                        .call(move |tokens| api::accept_removed_stones(game_id, &removed, tokens))
+                        .await?;
+                }
+                Some(RemovalAction::Reject) => {
+                    info!("rejecting removed stones, back to playing");
+                    client
+                        .call(move |tokens| api::reject_removed_stones(game_id, tokens))
+                        .await?;
+                    return Ok(());
+                }
+            },
+            _ = poll.tick() => {
+                let state = get_detail(client, game).await?;
+                if !state.is_stone_removal() {
+                    return Ok(());
+                }
+                removal.update_removal(&state.removal);
+            }
+        }
+    }
+}
+
+async fn requester(display: LedDisplay) -> Result<()> {
     let mut t = true;
     // loop {
     // get("https://google.com")?;
 
-    let bs = test_connection()?;
+    let login = onlinego::env_login()?;
+    let bs = unblock(move || test_connection(login)).await??;
 
-    neopixel::go_board::show_board(&tx, &bs.board, bs.height(), bs.width()).await?;
+    neopixel::go_board::show_board(&display, &bs.board).await?;
 
     //     sleep(Duration::from_millis(5000)).await;
     // }
+    let corner = Region {
+        x: 0,
+        y: 0,
+        height: 1,
+        width: 1,
+    };
     loop {
-        tx.send(LedChange::new(0, 0, Rgb::new(0, 0, 50))).await?;
+        let blink = Frame::from_changes([LedChange::new(0, 0, Rgb::new(0, 0, 50))]);
+        display.show_region(blink, corner).await?;
         sleep(Duration::from_millis(1000)).await;
-        tx.send(LedChange::new(0, 0, Rgb::new(0, 0, 0))).await?;
+        display.show_region(Frame::new(), corner).await?;
         sleep(Duration::from_millis(1000)).await;
         info!("looping reqs");
     }
     Ok(())
 }
 
-async fn echo_server(tx: Sender<LedChange>) -> Result<()> {
+async fn echo_server(display: LedDisplay) -> Result<()> {
     let addr = format!("0.0.0.0:{TCP_LISTENING_PORT}");
 
     info!("Binding to {addr}...");
@@ -1,5 +1,3 @@
+pub use go_board_core::neopixel::{animation, frame, go_board, led_font, rgb, viewport};
 pub mod led_ctrl;
-pub mod rgb;
 pub mod strip;
- pub(crate) mod go_board;
-pub mod led_font;
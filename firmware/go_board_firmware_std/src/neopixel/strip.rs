@@ -1,5 +1,6 @@
 use std::time::Duration;
 
+use super::frame::Frame;
 use super::led_ctrl::LedChange;
 use super::rgb::Rgb;
 use anyhow::{anyhow, Result};
@@ -8,10 +9,15 @@ use esp_idf_svc::hal::peripheral::Peripheral;
 use esp_idf_svc::hal::rmt::config::TransmitConfig;
 use esp_idf_svc::hal::rmt::{PinState, Pulse, RmtChannel, TxRmtDriver, VariableLengthSignal};
 
+/// Double buffered: drawing goes to the back buffer, [`LedStrip::refresh`] sends it to the
+/// leds and keeps it as the front buffer
 pub struct LedStrip<'tx, const SIZE: usize> {
     config: TransmitConfig,
     tx: TxRmtDriver<'tx>,
-    data: [Rgb; SIZE],
+    /// what the leds show, `None` until the first refresh
+    front: Option<[Rgb; SIZE]>,
+    /// the next picture, being drawn
+    back: [Rgb; SIZE],
 }
 
 impl<'tx, const SIZE: usize> LedStrip<'tx, SIZE> {
@@ -24,15 +30,25 @@ impl<'tx, const SIZE: usize> LedStrip<'tx, SIZE> {
         Ok(LedStrip {
             config,
             tx,
-            data: [Rgb::new(0, 0, 0); SIZE],
+            front: None,
+            back: [Rgb::new(0, 0, 0); SIZE],
         })
     }
 
     pub fn clear(&mut self) {
         for i in 0..SIZE {
-            self.data[i] = Rgb::new(0, 0, 0);
+            self.back[i] = Rgb::new(0, 0, 0);
         }
     }
+
+    /// Draws a frame into the back buffer, `size` is the side of the square matrix
+    pub fn draw(&mut self, frame: &Frame, size: usize) -> Result<()> {
+        for change in frame.changes() {
+            self.set_led_change(&change, size)?;
+        }
+        Ok(())
+    }
+
     pub fn set_led_change(&mut self, change: &LedChange, size: usize) -> Result<()> {
         let LedChange { x, y, color } = *change;
         let x: usize = x.into();
@@ -47,7 +63,6 @@ impl<'tx, const SIZE: usize> LedStrip<'tx, SIZE> {
         } else {
             (x + 1) * size - y - 1
         };
-        println!("({x},{y}) -> {index}");
         self.set_led(index, color)
     }
 
@@ -55,12 +70,17 @@ impl<'tx, const SIZE: usize> LedStrip<'tx, SIZE> {
         if index >= SIZE {
             return Err(anyhow!("index: {index} out of range of led strip!"));
         }
-        self.data[index] = rgb;
+        self.back[index] = rgb;
 
         Ok(())
     }
 
-    pub fn refresh(&mut self) -> Result<()> {
+    /// Sends the back buffer to the leds, nothing is sent if they already show it.
+    /// Returns true if the leds were updated
+    pub fn refresh(&mut self) -> Result<bool> {
+        if self.front == Some(self.back) {
+            return Ok(false);
+        }
         let ticks_hz = self.tx.counter_clock()?;
 
         let (t0h, t0l, t1h, t1l) = (
@@ -74,7 +94,7 @@ impl<'tx, const SIZE: usize> LedStrip<'tx, SIZE> {
         let mut s: [[&Pulse; 2]; 24] = [[&zero, &zero]; 24];
         let mut signal = VariableLengthSignal::new();
 
-        for rgb in self.data {
+        for rgb in self.back {
             let color: u32 = rgb.into();
             for i in (0..24).rev() {
                 let p = 2_u32.pow(i);
@@ -86,6 +106,7 @@ impl<'tx, const SIZE: usize> LedStrip<'tx, SIZE> {
             }
         }
         self.tx.start_blocking(&signal)?;
-        Ok(())
+        self.front = Some(self.back);
+        Ok(true)
     }
 }
@@ -0,0 +1,41 @@
+//! The api's requests block, they sleep between retries and while the request budget is spent.
+//! On the single threaded runtime that would freeze the leds, the encoder and the socket, so
+//! async code sends them from tokio's blocking pool through [`OgsClient`]
+
+use super::token_manager::TokenManager;
+use anyhow::Result;
+use std::error::Error;
+use std::sync::Arc;
+use tokio::task::spawn_blocking;
+
+/// Shares the tokens with the blocking pool, cloning it is cheap
+#[derive(Clone)]
+pub struct OgsClient {
+    tokens: Arc<TokenManager>,
+}
+
+impl OgsClient {
+    pub fn new(tokens: TokenManager) -> Self {
+        Self {
+            tokens: Arc::new(tokens),
+        }
+    }
+
+    /// Runs `request` on the blocking pool, ie `client.call(api::get_current_player).await?`
+    pub async fn call<T, E>(
+        &self,
+        request: impl FnOnce(&TokenManager) -> Result<T, E> + Send + 'static,
+    ) -> Result<T>
+    where
+        T: Send + 'static,
+        E: Error + Send + Sync + 'static,
+    {
+        let tokens = self.tokens.clone();
+        Ok(spawn_blocking(move || request(&tokens)).await??)
+    }
+}
+
+/// Runs a request that does not need the tokens (ie logging in) on the blocking pool
+pub async fn unblock<T: Send + 'static>(request: impl FnOnce() -> T + Send + 'static) -> Result<T> {
+    Ok(spawn_blocking(request).await?)
+}
@@ -0,0 +1,72 @@
+//! esp-idf's http client as the [`HttpTransport`] of the api, set in `main` before the first
+//! request
+
+use super::status_codes::StatusCode;
+use super::transport::{
+    parse_retry_after, HttpRequest, HttpResponse, HttpTransport, Method, REQUEST_TIMEOUT,
+};
+use anyhow::Result;
+use embedded_svc::http::Method as EspMethod;
+use esp_idf_svc::http::client::{Configuration as HttpConfiguration, EspHttpConnection};
+use esp_idf_svc::io::Write;
+use log::debug;
+use std::io;
+use std::time::Duration;
+
+/// esp-idf's http client, https is checked against the certificate bundle
+pub struct EspTransport {
+    pub timeout: Duration,
+}
+
+impl Default for EspTransport {
+    fn default() -> Self {
+        Self {
+            timeout: REQUEST_TIMEOUT,
+        }
+    }
+}
+
+/// The body is read straight off the connection, which is closed when this is dropped
+struct EspBody(EspHttpConnection);
+
+impl io::Read for EspBody {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        // a timed out or dropped connection ends up here
+        self.0.read(buf).map_err(io::Error::other)
+    }
+}
+
+impl HttpTransport for EspTransport {
+    fn send(&self, request: &HttpRequest) -> Result<HttpResponse> {
+        // 1. Create a new EspHttpConnection.
+        let mut connection = EspHttpConnection::new(&HttpConfiguration {
+            use_global_ca_store: true,
+            crt_bundle_attach: Some(esp_idf_svc::sys::esp_crt_bundle_attach),
+            timeout: Some(self.timeout),
+            ..Default::default()
+        })?;
+
+        // 2. Open a request to `url` and write the body
+        let method = match request.method {
+            Method::Get => EspMethod::Get,
+            Method::Post => EspMethod::Post,
+        };
+        connection.initiate_request(method, request.url, request.headers)?;
+        if let Some(body) = request.body {
+            connection.write_all(body.as_bytes())?;
+        }
+
+        // 3. Submit the request and read the status and headers, the body is left on the
+        // connection for the caller to read
+        connection.initiate_response()?;
+        let status = StatusCode::from_u16(connection.status())?;
+        let retry_after = connection.header("Retry-After").and_then(parse_retry_after);
+        debug!("{} {} answered {status}", request.method.name(), request.url);
+
+        Ok(HttpResponse {
+            status,
+            retry_after,
+            body: Box::new(EspBody(connection)),
+        })
+    }
+}
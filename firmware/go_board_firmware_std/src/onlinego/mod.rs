@@ -1,6 +1,49 @@
-pub mod api;
-pub mod auth_token;
-pub mod https;
-pub mod status_codes;
+pub use go_board_core::onlinego::{
+    api, auth_token, error, https, rate_limit, status_codes, token_manager, transport,
+};
+pub mod client;
+pub mod esp_transport;
 pub mod websocket;
 
+use anyhow::{anyhow, Result};
+use api::{OnlineGoLoginInfo, OnlineGoServer};
+use esp_transport::EspTransport;
+
+const ONLINE_GO_CLIENT_ID: &str = env!("ONLINE_GO_CLIENT_ID");
+const ONLINE_GO_USERNAME: &str = env!("ONLINE_GO_USERNAME");
+const ONLINE_GO_PASSWORD: &str = env!("ONLINE_GO_PASSWORD");
+
+/// set `ONLINE_GO_BASE_URL` (ie in `.env`) to run against a local stand-in for online-go
+const BASE_URL: &str = match option_env!("ONLINE_GO_BASE_URL") {
+    Some(base_url) => base_url,
+    None => OnlineGoServer::ONLINE_GO_URL,
+};
+
+/// Sends the api's requests through esp-idf to the server in `.env`, before anything else
+/// talks to online-go
+pub fn configure() -> Result<()> {
+    api::set_server(OnlineGoServer {
+        base_url: BASE_URL.to_string(),
+        client_id: ONLINE_GO_CLIENT_ID.to_string(),
+    });
+    // the hardware rng, so a crowd of boards booted together does not retry in lockstep
+    https::set_jitter_source(|| unsafe { esp_idf_svc::sys::esp_random() });
+    transport::set_transport(EspTransport::default())
+}
+
+/// The account in `.env`, to try the api without going through the settings panel
+pub fn env_login() -> Result<OnlineGoLoginInfo> {
+    let mut login = OnlineGoLoginInfo {
+        username: heapless::String::new(),
+        password: heapless::String::new(),
+    };
+    login
+        .username
+        .push_str(ONLINE_GO_USERNAME)
+        .map_err(|_| anyhow!("ONLINE_GO_USERNAME is too long"))?;
+    login
+        .password
+        .push_str(ONLINE_GO_PASSWORD)
+        .map_err(|_| anyhow!("ONLINE_GO_PASSWORD is too long"))?;
+    Ok(login)
+}
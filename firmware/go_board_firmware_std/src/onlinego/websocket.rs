@@ -1,162 +1,357 @@
-// pub fn main() {
-//     #[cfg(esp_idf_version_major = "4")]
-//     example::main().unwrap();
-//
-//     // Note that the ESP IDF websocket client IS available on ESP IDF >= 5 too
-//     // It is just that it is now an external component, so to use it, you need
-//     // to put the following snippet at the end of the `Cargo.toml` file of your binary crate:
-//     //
-//     // ```toml
-//     // [[package.metadata.esp-idf-sys.extra_components]]
-//     // remote_component = { name = "espressif/esp_websocket_client", version = "1.1.0" }
-//     // ```
-//
-// }
-
-use core::time::Duration;
-
-use anyhow::Result;
-use esp_idf_svc::eventloop::EspSystemEventLoop;
-use esp_idf_svc::hal::peripherals::Peripherals;
+//! Real time game updates from online-go
+//!
+//! online-go speaks socket.io over Engine.IO v3. Every websocket text frame is an Engine.IO
+//! packet: a type digit followed by its payload. Socket.IO packets travel inside Engine.IO
+//! messages (`4`), so an event looks like `42["game/123/move",{...}]`.
+//! In v3 the client sends the pings (`2`) and the server answers with pongs (`3`).
+
+use super::api::GameMove;
+use crate::game::rules::Move;
+use crate::CHANNEL_SIZE;
+use anyhow::{anyhow, bail, Result};
 use esp_idf_svc::io::EspIOError;
-use esp_idf_svc::log::EspLogger;
-use esp_idf_svc::nvs::EspDefaultNvsPartition;
-use esp_idf_svc::tls::X509;
-use esp_idf_svc::wifi::*;
 use esp_idf_svc::ws::client::{
     EspWebSocketClient, EspWebSocketClientConfig, FrameType, WebSocketEvent, WebSocketEventType,
 };
-use log::info;
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::time::Duration;
+use tokio::select;
+use tokio::sync::mpsc::{self, Receiver, Sender};
+use tokio::time::{interval, sleep, Instant};
 
-use std::sync::mpsc;
+/// set `ONLINE_GO_SOCKET_URL` (ie in `.env`) to connect to a local stand-in for online-go
+const WS_URL: &str = match option_env!("ONLINE_GO_SOCKET_URL") {
+    Some(socket_url) => socket_url,
+    None => "wss://online-go.com/socket.io/?EIO=3&transport=websocket",
+};
 
-const ECHO_SERVER_URI: &str = "wss://echo.websocket.org";
+/// how long to wait for the websocket to connect
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// used until the server sends its handshake
+const DEFAULT_PING_INTERVAL: Duration = Duration::from_secs(25);
+const DEFAULT_PING_TIMEOUT: Duration = Duration::from_secs(60);
+/// the first reconnect waits this long, every failed attempt doubles it up to `MAX_RECONNECT_DELAY`
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(60);
 
-const WS_URL: &str = "wss://online-go.com/socket.io/?EIO=3&transport=websocket";
+/// What the rest of the firmware hears from the socket
+#[derive(Clone, PartialEq, Debug)]
+pub enum SocketEvent {
+    /// (re)connected, every game passed to [`OgsSocket::connect_game`] is subscribed again.
+    /// Moves may have been missed while disconnected
+    Connected,
+    Disconnected,
+    /// a move was played in a subscribed game, `move_number` counts every move of the game
+    /// including this one (and handicap stones)
+    Move {
+        game_id: i64,
+        move_number: usize,
+        mv: Move,
+    },
+    /// the game changed phase, ie `stone removal` or `finished`
+    Phase {
+        game_id: i64,
+        phase: String,
+    },
+}
 
+/// Handshake sent by the server when the Engine.IO connection opens
+#[derive(Deserialize, Clone, PartialEq, Eq, Debug)]
+struct Handshake {
+    #[serde(rename = "pingInterval")]
+    ping_interval: u64,
+    #[serde(rename = "pingTimeout")]
+    ping_timeout: u64,
+}
 
-/// The relevant events for this example as it connects to the server,
-/// sends a message, receives the same message, and closes the connection.
-#[derive(Debug, PartialEq)]
-enum ExampleEvent {
-    Connected,
-    MessageReceived,
-    Closed,
+#[derive(Clone, PartialEq, Debug)]
+enum Packet {
+    Open(Handshake),
+    Close,
+    Ping,
+    Pong,
+    /// socket.io connected to the default namespace
+    Connect,
+    /// socket.io disconnected from the default namespace
+    Disconnect,
+    Event {
+        name: String,
+        data: Value,
+    },
+    /// upgrades, noops, acks and errors, none of them are used
+    Other,
 }
 
-pub fn test() -> Result<()> {
-    // Connect websocket
-    let config = EspWebSocketClientConfig {
-        use_global_ca_store: true,
-        crt_bundle_attach: Some(esp_idf_svc::sys::esp_crt_bundle_attach),
-        ..Default::default()
+fn parse_packet(text: &str) -> Result<Packet> {
+    let mut chars = text.chars();
+    let packet = match chars.next() {
+        Some('0') => Packet::Open(serde_json::from_str(chars.as_str())?),
+        Some('1') => Packet::Close,
+        Some('2') => Packet::Ping,
+        Some('3') => Packet::Pong,
+        Some('4') => parse_socket_io(chars.as_str())?,
+        Some(_) => Packet::Other,
+        None => bail!("Empty Engine.IO packet"),
     };
-    let timeout = Duration::from_secs(10);
-    let (tx, rx) = mpsc::channel::<ExampleEvent>();
-    let mut client = EspWebSocketClient::new(ECHO_SERVER_URI, &config, timeout, move |event| {
-        handle_event_test(&tx, event)
-    })?;
-    assert_eq!(rx.recv(), Ok(ExampleEvent::Connected));
-    assert!(client.is_connected());
+    Ok(packet)
+}
+
+/// The payload of an Engine.IO message
+fn parse_socket_io(text: &str) -> Result<Packet> {
+    let mut chars = text.chars();
+    let packet = match chars.next() {
+        Some('0') => Packet::Connect,
+        Some('1') => Packet::Disconnect,
+        Some('2') => {
+            // an optional ack id sits between the type and the data
+            let data = chars
+                .as_str()
+                .trim_start_matches(|c: char| c.is_ascii_digit());
+            match serde_json::from_str::<Value>(data)? {
+                Value::Array(mut values) if !values.is_empty() => {
+                    let data = if values.len() > 1 {
+                        values.swap_remove(1)
+                    } else {
+                        Value::Null
+                    };
+                    match values.swap_remove(0) {
+                        Value::String(name) => Packet::Event { name, data },
+                        name => bail!("Socket.IO event name {name} is not a string"),
+                    }
+                }
+                _ => bail!("Socket.IO event {text} is not a non empty array"),
+            }
+        }
+        _ => Packet::Other,
+    };
+    Ok(packet)
+}
 
-    // Send message and receive it back
-    let message = "Hello, World!";
-    info!("Websocket send, text: {}", message);
-    client.send(FrameType::Text(false), message.as_bytes())?;
-    assert_eq!(rx.recv(), Ok(ExampleEvent::MessageReceived));
+fn event_packet(name: &str, data: &impl Serialize) -> Result<String> {
+    Ok(format!("42{}", serde_json::to_string(&(name, data))?))
+}
 
-    // Close websocket
-    drop(client);
-    assert_eq!(rx.recv(), Ok(ExampleEvent::Closed));
+#[derive(Serialize, Debug)]
+struct GameConnect {
+    game_id: i64,
+    player_id: i64,
+    chat: bool,
+}
 
-    Ok(())
+#[derive(Deserialize, Debug)]
+struct MoveEventData {
+    game_id: i64,
+    move_number: usize,
+    #[serde(rename = "move")]
+    game_move: GameMove,
 }
 
-fn handle_event_test(tx: &mpsc::Sender<ExampleEvent>, event: &Result<WebSocketEvent, EspIOError>) {
-    if let Ok(event) = event {
-        match event.event_type {
-            WebSocketEventType::BeforeConnect => {
-                info!("Websocket before connect");
-            }
-            WebSocketEventType::Connected => {
-                info!("Websocket connected");
-                tx.send(ExampleEvent::Connected).ok();
-            }
-            WebSocketEventType::Disconnected => {
-                info!("Websocket disconnected");
-            }
-            WebSocketEventType::Close(reason) => {
-                info!("Websocket close, reason: {reason:?}");
-            }
-            WebSocketEventType::Closed => {
-                info!("Websocket closed");
-                tx.send(ExampleEvent::Closed).ok();
-            }
-            WebSocketEventType::Text(text) => {
-                info!("Websocket recv, text: {text}");
-                if text == "Hello, World!" {
-                    tx.send(ExampleEvent::MessageReceived).ok();
-                }
-            }
-            WebSocketEventType::Binary(binary) => {
-                info!("Websocket recv, binary: {binary:?}");
+/// `game/<id>/<topic>` events that matter to the board, everything else is ignored
+fn to_socket_event(name: &str, data: Value) -> Result<Option<SocketEvent>> {
+    let Some((game_id, topic)) = name
+        .strip_prefix("game/")
+        .and_then(|rest| rest.split_once('/'))
+    else {
+        return Ok(None);
+    };
+    let game_id = game_id.parse()?;
+    let event = match topic {
+        "move" => {
+            let data = serde_json::from_value::<MoveEventData>(data)?;
+            Some(SocketEvent::Move {
+                game_id: data.game_id,
+                move_number: data.move_number,
+                mv: data.game_move.to_move(),
+            })
+        }
+        "phase" => Some(SocketEvent::Phase {
+            game_id,
+            phase: serde_json::from_value(data)?,
+        }),
+        _ => None,
+    };
+    Ok(event)
+}
+
+/// Sent from the websocket's event handler (an esp-idf task) to the tokio side
+enum Incoming {
+    Packet(Packet),
+    Closed,
+}
+
+fn handle_event(tx: &Sender<Incoming>, event: &Result<WebSocketEvent, EspIOError>) {
+    let event = match event {
+        Ok(event) => event,
+        Err(e) => {
+            error!("Websocket error: {e:?}");
+            return;
+        }
+    };
+    let incoming = match event.event_type {
+        WebSocketEventType::Text(text) => match parse_packet(text) {
+            Ok(packet) => Incoming::Packet(packet),
+            Err(e) => {
+                error!("Failed to parse websocket packet {text}: {e:?}");
+                return;
             }
-            WebSocketEventType::Ping => {
-                info!("Websocket ping");
+        },
+        WebSocketEventType::Disconnected | WebSocketEventType::Closed => Incoming::Closed,
+        WebSocketEventType::Close(reason) => {
+            info!("Websocket close, reason: {reason:?}");
+            Incoming::Closed
+        }
+        _ => return,
+    };
+    // never block the esp-idf task, a full channel means the firmware is already behind
+    if tx.try_send(incoming).is_err() {
+        error!("Websocket event dropped, channel full or closed");
+    }
+}
+
+/// Handle to the task keeping the socket connected, dropping it closes the socket
+pub struct OgsSocket {
+    games: Sender<i64>,
+}
+
+impl OgsSocket {
+    /// Spawns the task that connects (and reconnects) the socket, events are sent to `events`
+    pub fn spawn(player_id: i64, events: Sender<SocketEvent>) -> Self {
+        let (games, games_rx) = mpsc::channel(CHANNEL_SIZE);
+        tokio::spawn(async move {
+            if let Err(e) = run(player_id, events, games_rx).await {
+                error!("Online-go socket stopped: {e:?}");
             }
-            WebSocketEventType::Pong => {
-                info!("Websocket pong");
+        });
+        Self { games }
+    }
+
+    /// Subscribes to the moves and phase changes of a game
+    pub async fn connect_game(&self, game_id: i64) -> Result<()> {
+        self.games
+            .send(game_id)
+            .await
+            .map_err(|_| anyhow!("Online-go socket task is not running"))
+    }
+}
+
+async fn run(
+    player_id: i64,
+    events: Sender<SocketEvent>,
+    mut games_rx: Receiver<i64>,
+) -> Result<()> {
+    let mut games = Vec::new();
+    let mut reconnect_delay = RECONNECT_DELAY;
+    loop {
+        match session(
+            player_id,
+            &events,
+            &mut games_rx,
+            &mut games,
+            &mut reconnect_delay,
+        )
+        .await
+        {
+            // the handle was dropped
+            Ok(()) => return Ok(()),
+            Err(e) => {
+                error!("Online-go socket disconnected: {e:?}, reconnecting in {reconnect_delay:?}");
+                events.send(SocketEvent::Disconnected).await?;
+                sleep(reconnect_delay).await;
+                reconnect_delay = (reconnect_delay * 2).min(MAX_RECONNECT_DELAY);
             }
         }
     }
 }
 
-pub fn setup() -> Result<()> {
+fn send(client: &mut EspWebSocketClient<'static>, text: &str) -> Result<()> {
+    client.send(FrameType::Text(false), text.as_bytes())?;
+    Ok(())
+}
+
+/// One websocket connection, returns an error once it is lost
+async fn session(
+    player_id: i64,
+    events: &Sender<SocketEvent>,
+    games_rx: &mut Receiver<i64>,
+    games: &mut Vec<i64>,
+    reconnect_delay: &mut Duration,
+) -> Result<()> {
     let config = EspWebSocketClientConfig {
         use_global_ca_store: true,
         crt_bundle_attach: Some(esp_idf_svc::sys::esp_crt_bundle_attach),
+        // reconnecting is done here so the games can be subscribed again
+        disable_auto_reconnect: true,
         ..Default::default()
     };
-    let timeout = Duration::from_secs(10);
-    let (tx, rx) = mpsc::channel::<ExampleEvent>();
-    let mut client = EspWebSocketClient::new(WS_URL, &config, timeout, move |event| {
-        if let Ok(event) = event {
-            match event.event_type {
-                WebSocketEventType::BeforeConnect => {
-                    info!("Websocket before connect");
-                }
-                WebSocketEventType::Connected => {
-                    info!("Websocket connected");
-                    tx.send(ExampleEvent::Connected).ok();
-                }
-                WebSocketEventType::Disconnected => {
-                    info!("Websocket disconnected");
-                }
-                WebSocketEventType::Close(reason) => {
-                    info!("Websocket close, reason: {reason:?}");
-                }
-                WebSocketEventType::Closed => {
-                    info!("Websocket closed");
-                    tx.send(ExampleEvent::Closed).ok();
-                }
-                WebSocketEventType::Text(text) => {
-                    info!("Websocket recv, text: {text}");
-                    if text == "Hello, World!" {
-                        tx.send(ExampleEvent::MessageReceived).ok();
+    let (incoming_tx, mut incoming_rx) = mpsc::channel::<Incoming>(CHANNEL_SIZE);
+    let mut client = EspWebSocketClient::new(WS_URL, &config, CONNECT_TIMEOUT, move |event| {
+        handle_event(&incoming_tx, event)
+    })?;
+
+    let game_connect = |game_id| {
+        event_packet(
+            "game/connect",
+            &GameConnect {
+                game_id,
+                player_id,
+                chat: false,
+            },
+        )
+    };
+    let mut connected = false;
+    let mut heartbeat = interval(DEFAULT_PING_INTERVAL);
+    let mut ping_timeout = DEFAULT_PING_TIMEOUT;
+    let mut ping_sent: Option<Instant> = None;
+    loop {
+        select! {
+            incoming = incoming_rx.recv() => match incoming {
+                None | Some(Incoming::Closed) => bail!("Websocket closed"),
+                Some(Incoming::Packet(packet)) => match packet {
+                    Packet::Open(handshake) => {
+                        info!("Online-go socket open: {handshake:?}");
+                        heartbeat = interval(Duration::from_millis(handshake.ping_interval));
+                        ping_timeout = Duration::from_millis(handshake.ping_timeout);
+                    }
+                    Packet::Ping => send(&mut client, "3")?,
+                    Packet::Pong => ping_sent = None,
+                    Packet::Connect => {
+                        connected = true;
+                        *reconnect_delay = RECONNECT_DELAY;
+                        for game_id in games.iter() {
+                            send(&mut client, &game_connect(*game_id)?)?;
+                        }
+                        events.send(SocketEvent::Connected).await?;
+                    }
+                    Packet::Close | Packet::Disconnect => bail!("Online-go closed the socket"),
+                    Packet::Event { name, data } => match to_socket_event(&name, data) {
+                        Ok(Some(event)) => events.send(event).await?,
+                        Ok(None) => {}
+                        Err(e) => error!("Failed to read socket event {name}: {e:?}"),
+                    },
+                    Packet::Other => {}
+                },
+            },
+            game_id = games_rx.recv() => {
+                let Some(game_id) = game_id else {
+                    return Ok(());
+                };
+                if !games.contains(&game_id) {
+                    games.push(game_id);
+                    if connected {
+                        send(&mut client, &game_connect(game_id)?)?;
                     }
                 }
-                WebSocketEventType::Binary(binary) => {
-                    info!("Websocket recv, binary: {binary:?}");
-                }
-                WebSocketEventType::Ping => {
-                    info!("Websocket ping");
-                }
-                WebSocketEventType::Pong => {
-                    info!("Websocket pong");
+            },
+            _ = heartbeat.tick(), if connected => match ping_sent {
+                Some(sent) if sent.elapsed() > ping_timeout => bail!("No pong within {ping_timeout:?}"),
+                Some(_) => {}
+                None => {
+                    send(&mut client, "2")?;
+                    ping_sent = Some(Instant::now());
                 }
-            }
+            },
         }
-    })?;
-
-    Ok(())
+    }
 }
@@ -1,6 +1,7 @@
 use crate::onlinego;
 use crate::onlinego::api::{get_current_player_games, GameList, OnlineGoLoginInfo};
 use crate::onlinego::status_codes::StatusCode;
+use crate::onlinego::token_manager::TokenManager;
 use crate::settings::server::deserialize_json_req::{
     deserialize_json_from_request, DataResponseOrValue,
 };
@@ -282,7 +283,8 @@ impl CaptiveServerHandler<HandlerRoute> for OnlineGoGamesList {
             match saved_info {
                 Some(saved_info) => match saved_info.auth_with_password()? {
                     Ok(valid) => {
-                        let games: GameList = get_current_player_games(&valid.access_token)?;
+                        let tokens = TokenManager::new(saved_info, valid, None);
+                        let games: GameList = get_current_player_games(&tokens)?;
                         Ok(DataResponse::Ok(Some(serde_json::to_value(&games)?)))
                     }
                     Err(err) => Ok(DataResponse::HandledErr(
@@ -1,9 +1,9 @@
-use crate::encoder::{EncoderInfo, RotaryEncoderState};
-use crate::neopixel::led_ctrl::LedChange;
+use crate::encoder::{ButtonPress, EncoderInfo, RotaryEncoderState};
+use crate::neopixel::frame::{self, FrameUpdate, LedDisplay};
 use crate::restart_recovery::{get_and_clear_recover_option, RecoverOption};
 use crate::storage::SaveInNvs;
 use crate::wifi::WifiCredentials;
-use crate::{settings, CHANNEL_SIZE};
+use crate::settings;
 use anyhow::{anyhow, Result};
 use esp_idf_svc::eventloop::{EspEventLoop, EspSystemEventLoop, System};
 use esp_idf_svc::hal::gpio;
@@ -103,10 +103,12 @@ pub fn setup<'esp_wifi, 'rotary_encoder>() -> Result<(
         RotaryEncoderState<'rotary_encoder>,
         broadcast::Sender<EncoderInfo>,
         broadcast::Receiver<EncoderInfo>,
+        broadcast::Sender<ButtonPress>,
+        broadcast::Receiver<ButtonPress>,
     ),
     (
-        mpsc::Receiver<LedChange>,
-        mpsc::Sender<LedChange>,
+        mpsc::Receiver<FrameUpdate>,
+        LedDisplay,
         impl Peripheral<P = impl OutputPin>,
         impl Peripheral<P: RmtChannel>,
     ),
@@ -156,15 +158,22 @@ pub fn setup<'esp_wifi, 'rotary_encoder>() -> Result<(
         timer.clone(),
     )?;
 
-    let (led_change_tx, led_change_rx) = mpsc::channel::<LedChange>(CHANNEL_SIZE);
+    let (led_display, frame_rx) = frame::channel();
     let (tx_encoder_info, rx_encoder_info) = broadcast::channel::<EncoderInfo>(100);
+    let (tx_button_press, rx_button_press) = broadcast::channel::<ButtonPress>(10);
 
     Ok((
         (wifi_creds, wifi),
-        (rotary_encoder_state, tx_encoder_info, rx_encoder_info),
         (
-            led_change_rx,
-            led_change_tx,
+            rotary_encoder_state,
+            tx_encoder_info,
+            rx_encoder_info,
+            tx_button_press,
+            rx_button_press,
+        ),
+        (
+            frame_rx,
+            led_display,
             board_led_grid_pin,
             rmt_channel0,
         ),
@@ -1,3 +1,6 @@
+use crate::game::picker::SelectedGame;
+use crate::onlinego::api::OnlineGoLoginInfo;
+use crate::onlinego::token_manager::{SavedTokens, TokenStore};
 use crate::wifi::WifiCredentials;
 use anyhow::{anyhow, Result};
 use esp_idf_svc::nvs::{EspNvs, EspNvsPartition, NvsDefault};
@@ -81,6 +84,18 @@ impl NvsNamespace {
         let s = from_bytes::<StructType>(buff)?;
         Ok(Some(s))
     }
+    pub fn remove(&mut self, key: &str) -> Result<()> {
+        match self.nvs.remove(key) {
+            Ok(_) => {
+                debug!("Key {key} removed from namespace {}", self.name);
+                Ok(())
+            }
+            Err(e) => Err(anyhow!(e).context(format!(
+                "key {key} not removed from namespace {} {e:?}",
+                self.name
+            ))),
+        }
+    }
 }
 
 pub trait SaveInNvs: Sized + Serialize + DeserializeOwned + Debug + Clone + MaxSize {
@@ -115,5 +130,61 @@ pub trait SaveInNvs: Sized + Serialize + DeserializeOwned + Debug + Clone + MaxS
         let mut struct_buffer = Self::get_struct_buffer();
         nvs.set_struct::<Self>(Self::key(), self, struct_buffer.as_mut())
     }
+    /// Forgets the saved value, nothing happens if there is none
+    fn remove_saved_in_nvs(partition: EspNvsPartition<NvsDefault>) -> Result<()> {
+        let mut nvs = NvsNamespace::access(partition, Self::namespace(), false)?;
+        nvs.remove(Self::key())
+    }
+}
+
+impl SaveInNvs for OnlineGoLoginInfo {
+    fn namespace() -> &'static str {
+        "og"
+    }
+
+    fn key() -> &'static str {
+        "login"
+    }
+    fn get_struct_buffer<'a>() -> impl AsMut<[u8]> {
+        [0; Self::POSTCARD_MAX_SIZE]
+    }
 }
 
+impl SaveInNvs for SavedTokens {
+    fn namespace() -> &'static str {
+        "og"
+    }
+
+    fn key() -> &'static str {
+        "tokens"
+    }
+    fn get_struct_buffer<'a>() -> impl AsMut<[u8]> {
+        [0; Self::POSTCARD_MAX_SIZE]
+    }
+}
+
+impl SaveInNvs for SelectedGame {
+    fn namespace() -> &'static str {
+        "og"
+    }
+
+    fn key() -> &'static str {
+        "game"
+    }
+    fn get_struct_buffer<'a>() -> impl AsMut<[u8]> {
+        [0; Self::POSTCARD_MAX_SIZE]
+    }
+}
+
+/// Keeps the online-go tokens in nvs so they outlive a reboot
+pub struct NvsTokenStore(pub EspNvsPartition<NvsDefault>);
+
+impl TokenStore for NvsTokenStore {
+    fn load(&self) -> Result<Option<SavedTokens>> {
+        SavedTokens::get_saved_in_nvs(self.0.clone())
+    }
+
+    fn save(&self, tokens: &SavedTokens) -> Result<()> {
+        tokens.set_saved_in_nvs(self.0.clone())
+    }
+}
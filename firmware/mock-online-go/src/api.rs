@@ -0,0 +1,245 @@
+//! The online-go endpoints the firmware uses
+
+use crate::game::{
+    player_json, points_from_letters, Color, PHASE_FINISHED, PHASE_PLAY, PHASE_STONE_REMOVAL,
+};
+use crate::http::{Request, Response};
+use crate::state::{Shared, State};
+use serde_json::{json, Value};
+
+const DEFAULT_PAGE_SIZE: usize = 10;
+
+fn error(status: u16, message: &str) -> Response {
+    Response::json(status, &json!({ "error": message }))
+}
+
+fn not_found() -> Response {
+    Response::json(404, &json!({ "detail": "Not found." }))
+}
+
+pub fn handle(request: &Request, shared: &Shared) -> Response {
+    let mut state = shared.lock();
+    if let Some(failure) = state.take_failure(&request.path) {
+        println!("injected {} for {}", failure.status, request.path);
+        let response = error(failure.status, "Injected by the scenario");
+        return match failure.retry_after {
+            Some(retry_after) => response.with_header("Retry-After", retry_after),
+            None => response,
+        };
+    }
+
+    let segments: Vec<&str> = request
+        .path
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .collect();
+    let response = match (request.method.as_str(), segments.as_slice()) {
+        ("POST", ["oauth2", "token"]) => token(request, &mut state),
+        (_, ["api", ..] | ["termination-api", ..]) => {
+            let authorized = request
+                .bearer_token()
+                .is_some_and(|token| state.is_valid_access_token(token));
+            if authorized {
+                api(request, &segments, &mut state)
+            } else {
+                Response::json(
+                    401,
+                    &json!({ "detail": "Authentication credentials were not provided." }),
+                )
+            }
+        }
+        _ => not_found(),
+    };
+    drop(state);
+    shared.notify();
+    response
+}
+
+/// `/oauth2/token/`, password and refresh token grants
+fn token(request: &Request, state: &mut State) -> Response {
+    let form = request.form();
+    let field = |name: &str| form.get(name).map(String::as_str).unwrap_or_default();
+    let granted = match field("grant_type") {
+        "password" => {
+            field("username") == state.account.username
+                && field("password") == state.account.password
+        }
+        "refresh_token" => state.use_refresh_token(field("refresh_token")),
+        grant_type => {
+            return Response::json(
+                400,
+                &json!({
+                    "error": "unsupported_grant_type",
+                    "error_description": format!("{grant_type} is not supported"),
+                }),
+            )
+        }
+    };
+    if !granted {
+        return Response::json(
+            401,
+            &json!({
+                "error": "invalid_grant",
+                "error_description": "Invalid credentials given.",
+            }),
+        );
+    }
+    let (access_token, refresh_token) = state.issue_tokens();
+    Response::json(
+        200,
+        &json!({
+            "access_token": access_token,
+            "expires_in": state.token_lifetime.as_secs(),
+            "token_type": "Bearer",
+            "scope": "read write",
+            "refresh_token": refresh_token,
+        }),
+    )
+}
+
+fn api(request: &Request, segments: &[&str], state: &mut State) -> Response {
+    let game_id = |segment: &str| segment.parse::<i64>().ok();
+    match (request.method.as_str(), segments) {
+        ("GET", ["api", "v1", "me"]) => Response::json(200, &player_json(&state.account)),
+        ("GET", ["api", "v1", "me", "games"]) => games(request, state),
+        ("GET", ["api", "v1", "games", id]) => {
+            match game_id(id).and_then(|id| state.game_mut(id)) {
+                Some(game) => Response::json(200, &game.record_json()),
+                None => not_found(),
+            }
+        }
+        ("GET", ["termination-api", "game", id, "state"]) => {
+            match game_id(id).and_then(|id| state.game_mut(id)) {
+                Some(game) => Response::json(200, &game.state_json()),
+                None => not_found(),
+            }
+        }
+        ("POST", ["api", "v1", "games", id, action @ ..]) => match game_id(id) {
+            Some(id) if state.game_mut(id).is_some() => game_action(request, id, action, state),
+            _ => not_found(),
+        },
+        _ => not_found(),
+    }
+}
+
+/// `/api/v1/me/games`, `ended__isnull=true` leaves out finished games, paginated with
+/// `page` and `page_size` like the real one
+fn games(request: &Request, state: &State) -> Response {
+    let only_running = request.query.get("ended__isnull").map(String::as_str) == Some("true");
+    let games: Vec<Value> = state
+        .games
+        .iter()
+        .filter(|game| !only_running || game.phase != PHASE_FINISHED)
+        .map(|game| game.list_json())
+        .collect();
+    let page_size = request
+        .query
+        .get("page_size")
+        .and_then(|size| size.parse().ok())
+        .filter(|size| *size > 0)
+        .unwrap_or(DEFAULT_PAGE_SIZE);
+    let page = request
+        .query
+        .get("page")
+        .and_then(|page| page.parse().ok())
+        .filter(|page| *page > 0)
+        .unwrap_or(1_usize);
+    let host = request.headers.get("host").cloned().unwrap_or_default();
+    let page_url = |page: usize| {
+        let mut query = request.query.clone();
+        query.insert("page".to_string(), page.to_string());
+        let mut query: Vec<String> = query
+            .iter()
+            .map(|(name, value)| format!("{name}={value}"))
+            .collect();
+        query.sort();
+        Value::from(format!("http://{host}{}?{}", request.path, query.join("&")))
+    };
+    let start = (page - 1) * page_size;
+    let results: Vec<Value> = games.iter().skip(start).take(page_size).cloned().collect();
+    Response::json(
+        200,
+        &json!({
+            "count": games.len(),
+            "next": if start + page_size < games.len() { page_url(page + 1) } else { Value::Null },
+            "previous": if page > 1 { page_url(page - 1) } else { Value::Null },
+            "results": results,
+        }),
+    )
+}
+
+/// Moves, resignations and the stone removal phase
+fn game_action(request: &Request, game_id: i64, action: &[&str], state: &mut State) -> Response {
+    let form = request.form();
+    let field = |name: &str| form.get(name).map(String::as_str).unwrap_or_default();
+    let player_id = state.account.id;
+    let Some(game) = state.game_mut(game_id) else {
+        return not_found();
+    };
+    let Some(color) = game.color_of(player_id) else {
+        return Response::json(
+            403,
+            &json!({ "error": "You are not a player in this game" }),
+        );
+    };
+    match action {
+        ["move"] => {
+            let (x, y) = match points_from_letters(field("move")).as_deref() {
+                Some([point]) => *point,
+                _ => return error(400, "Invalid move"),
+            };
+            if let Err(message) = state.play(game_id, color, x, y) {
+                return error(400, &message);
+            }
+            *state.player_moves.entry(game_id).or_default() += 1;
+            auto_reply(game_id, color.opponent(), state);
+            Response::json(200, &json!({}))
+        }
+        ["resign"] => {
+            if game.phase == PHASE_FINISHED {
+                return error(400, "The game is over");
+            }
+            game.finish("Resignation", Some(color.opponent()));
+            state.set_phase(game_id, PHASE_FINISHED).ok();
+            Response::json(200, &json!({}))
+        }
+        ["removed_stones"] => {
+            if game.phase != PHASE_STONE_REMOVAL {
+                return error(400, "The game is not in the stone removal phase");
+            }
+            let Some(points) = points_from_letters(field("stones")) else {
+                return error(400, "Invalid stones");
+            };
+            game.set_removed(&points, field("removed") == "true");
+            Response::json(200, &json!({}))
+        }
+        ["removed_stones", "accept"] => {
+            if game.phase != PHASE_STONE_REMOVAL {
+                return error(400, "The game is not in the stone removal phase");
+            }
+            // scoring is not simulated, the board's account always wins by half a point
+            game.finish("0.5 points", Some(color));
+            state.set_phase(game_id, PHASE_FINISHED).ok();
+            Response::json(200, &json!({}))
+        }
+        ["removed_stones", "reject"] => {
+            game.clear_removal();
+            state.set_phase(game_id, PHASE_PLAY).ok();
+            Response::json(200, &json!({}))
+        }
+        _ => not_found(),
+    }
+}
+
+fn auto_reply(game_id: i64, opponent: Color, state: &mut State) {
+    let Some(game) = state.game_mut(game_id) else {
+        return;
+    };
+    if !game.auto_reply || game.phase != PHASE_PLAY {
+        return;
+    }
+    let (x, y) = game.first_empty_point().unwrap_or((-1, -1));
+    if let Err(message) = state.play(game_id, opponent, x, y) {
+        println!("auto reply in game {game_id} failed: {message}");
+    }
+}
@@ -0,0 +1,418 @@
+//! The games the mock serves, just enough of the rules to keep the board the firmware sees
+//! consistent (captures and suicide, ko is not checked)
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct Account {
+    pub id: i64,
+    pub username: String,
+    /// only needed for the board's account
+    #[serde(default)]
+    pub password: String,
+    /// online-go's rating scale, 30 is 1 dan
+    #[serde(default = "default_ranking")]
+    pub ranking: f32,
+}
+
+fn default_ranking() -> f32 {
+    20.0
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Color {
+    Black,
+    White,
+}
+
+impl Color {
+    pub fn opponent(self) -> Color {
+        match self {
+            Color::Black => Color::White,
+            Color::White => Color::Black,
+        }
+    }
+
+    /// How the color is written in online-go's boards
+    fn value(self) -> i32 {
+        match self {
+            Color::Black => 1,
+            Color::White => 2,
+        }
+    }
+}
+
+/// A game as written in a scenario
+#[derive(Deserialize, Clone, Debug)]
+pub struct GameSpec {
+    pub id: i64,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default = "default_size")]
+    pub width: usize,
+    #[serde(default = "default_size")]
+    pub height: usize,
+    pub opponent: Account,
+    /// the color the board's account plays
+    #[serde(default = "default_color")]
+    pub color: Color,
+    #[serde(default = "default_komi")]
+    pub komi: f32,
+    #[serde(default = "default_rules")]
+    pub rules: String,
+    /// moves already played, `[x, y]` with x the column, `[-1, -1]` is a pass
+    #[serde(default)]
+    pub moves: Vec<[i32; 2]>,
+    /// the opponent answers every move of the board with the first empty point
+    #[serde(default)]
+    pub auto_reply: bool,
+    #[serde(default)]
+    pub ranked: bool,
+    /// seconds each player has per move, 0 for no limit. An hour or more is correspondence
+    #[serde(default = "default_time_per_move")]
+    pub time_per_move: u64,
+}
+
+fn default_size() -> usize {
+    19
+}
+
+fn default_color() -> Color {
+    Color::Black
+}
+
+fn default_time_per_move() -> u64 {
+    24 * 60 * 60
+}
+
+fn default_komi() -> f32 {
+    6.5
+}
+
+fn default_rules() -> String {
+    "japanese".to_string()
+}
+
+pub const PHASE_PLAY: &str = "play";
+pub const PHASE_STONE_REMOVAL: &str = "stone removal";
+pub const PHASE_FINISHED: &str = "finished";
+
+#[derive(Clone, Debug)]
+pub struct Game {
+    pub id: i64,
+    pub name: String,
+    pub width: usize,
+    pub height: usize,
+    pub black: Account,
+    pub white: Account,
+    pub komi: f32,
+    pub rules: String,
+    pub auto_reply: bool,
+    pub ranked: bool,
+    pub time_per_move: u64,
+    /// when the last move was played, the clock of the player to move runs from here
+    last_move_at: SystemTime,
+    pub phase: String,
+    /// `[x, y]` of every move, `[-1, -1]` for passes
+    pub moves: Vec<[i32; 2]>,
+    /// `board[y][x]`, 0 empty, 1 black, 2 white
+    board: Vec<Vec<i32>>,
+    /// dead stones marked in the stone removal phase, same layout as `board`
+    removal: Vec<Vec<i32>>,
+    pub outcome: String,
+    pub winner: Option<i64>,
+}
+
+impl Game {
+    pub fn new(spec: GameSpec, account: &Account) -> Result<Self, String> {
+        let (black, white) = match spec.color {
+            Color::Black => (account.clone(), spec.opponent),
+            Color::White => (spec.opponent, account.clone()),
+        };
+        let mut game = Self {
+            id: spec.id,
+            name: spec
+                .name
+                .unwrap_or_else(|| format!("{} vs {}", black.username, white.username)),
+            width: spec.width,
+            height: spec.height,
+            black,
+            white,
+            komi: spec.komi,
+            rules: spec.rules,
+            auto_reply: spec.auto_reply,
+            ranked: spec.ranked,
+            time_per_move: spec.time_per_move,
+            last_move_at: SystemTime::now(),
+            phase: PHASE_PLAY.to_string(),
+            moves: Vec::new(),
+            board: vec![vec![0; spec.width]; spec.height],
+            removal: vec![vec![0; spec.width]; spec.height],
+            outcome: String::new(),
+            winner: None,
+        };
+        for [x, y] in spec.moves {
+            game.play(game.to_move(), x, y)?;
+        }
+        Ok(game)
+    }
+
+    pub fn to_move(&self) -> Color {
+        if self.moves.len().is_multiple_of(2) {
+            Color::Black
+        } else {
+            Color::White
+        }
+    }
+
+    pub fn player(&self, color: Color) -> &Account {
+        match color {
+            Color::Black => &self.black,
+            Color::White => &self.white,
+        }
+    }
+
+    pub fn color_of(&self, player_id: i64) -> Option<Color> {
+        if self.black.id == player_id {
+            Some(Color::Black)
+        } else if self.white.id == player_id {
+            Some(Color::White)
+        } else {
+            None
+        }
+    }
+
+    fn contains(&self, x: i32, y: i32) -> bool {
+        x >= 0 && y >= 0 && (x as usize) < self.width && (y as usize) < self.height
+    }
+
+    fn at(&self, x: i32, y: i32) -> i32 {
+        self.board[y as usize][x as usize]
+    }
+
+    fn neighbors(&self, x: i32, y: i32) -> impl Iterator<Item = (i32, i32)> + '_ {
+        [(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)]
+            .into_iter()
+            .filter(|(x, y)| self.contains(*x, *y))
+    }
+
+    /// The chain at `x, y` and whether it has a liberty
+    fn chain(&self, x: i32, y: i32) -> (Vec<(i32, i32)>, bool) {
+        let color = self.at(x, y);
+        let mut chain = vec![(x, y)];
+        let mut has_liberty = false;
+        let mut next = 0;
+        while next < chain.len() {
+            let (x, y) = chain[next];
+            next += 1;
+            for (nx, ny) in self.neighbors(x, y) {
+                let value = self.at(nx, ny);
+                if value == 0 {
+                    has_liberty = true;
+                } else if value == color && !chain.contains(&(nx, ny)) {
+                    chain.push((nx, ny));
+                }
+            }
+        }
+        (chain, has_liberty)
+    }
+
+    /// Plays a move, `x, y` of `-1, -1` is a pass. The error is the message online-go would send
+    pub fn play(&mut self, color: Color, x: i32, y: i32) -> Result<(), String> {
+        if self.phase != PHASE_PLAY {
+            return Err("The game is over".to_string());
+        }
+        if color != self.to_move() {
+            return Err("Not your turn".to_string());
+        }
+        if x < 0 || y < 0 {
+            self.moves.push([-1, -1]);
+            self.last_move_at = SystemTime::now();
+            if self.moves.len() >= 2 && self.moves[self.moves.len() - 2] == [-1, -1] {
+                self.phase = PHASE_STONE_REMOVAL.to_string();
+            }
+            return Ok(());
+        }
+        if !self.contains(x, y) {
+            return Err(format!("Invalid move, {x}, {y} is off the board"));
+        }
+        if self.at(x, y) != 0 {
+            return Err("Illegal move, the point is occupied".to_string());
+        }
+        self.board[y as usize][x as usize] = color.value();
+        let mut captured = false;
+        for (nx, ny) in self.neighbors(x, y).collect::<Vec<_>>() {
+            if self.at(nx, ny) == color.opponent().value() {
+                let (chain, has_liberty) = self.chain(nx, ny);
+                if !has_liberty {
+                    captured = true;
+                    for (cx, cy) in chain {
+                        self.board[cy as usize][cx as usize] = 0;
+                    }
+                }
+            }
+        }
+        if !captured && !self.chain(x, y).1 {
+            self.board[y as usize][x as usize] = 0;
+            return Err("Illegal move, suicide".to_string());
+        }
+        self.moves.push([x, y]);
+        self.last_move_at = SystemTime::now();
+        Ok(())
+    }
+
+    /// Where the opponent plays when `auto_reply` is set
+    pub fn first_empty_point(&self) -> Option<(i32, i32)> {
+        (0..self.height as i32)
+            .flat_map(|y| (0..self.width as i32).map(move |x| (x, y)))
+            .find(|(x, y)| self.at(*x, *y) == 0)
+    }
+
+    pub fn set_removed(&mut self, points: &[(i32, i32)], removed: bool) {
+        for (x, y) in points {
+            if self.contains(*x, *y) {
+                self.removal[*y as usize][*x as usize] = removed as i32;
+            }
+        }
+    }
+
+    pub fn clear_removal(&mut self) {
+        self.removal = vec![vec![0; self.width]; self.height];
+    }
+
+    pub fn finish(&mut self, outcome: &str, winner: Option<Color>) {
+        self.phase = PHASE_FINISHED.to_string();
+        self.outcome = outcome.to_string();
+        self.winner = winner.map(|color| self.player(color).id);
+    }
+
+    /// The `clock` of the game record, only what the firmware reads
+    fn clock_json(&self) -> Value {
+        let millis = |time: SystemTime| {
+            time.duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis() as u64
+        };
+        let expiration = (self.time_per_move > 0)
+            .then(|| millis(self.last_move_at + Duration::from_secs(self.time_per_move)));
+        json!({
+            "game_id": self.id,
+            "current_player": self.player(self.to_move()).id,
+            "black_player_id": self.black.id,
+            "white_player_id": self.white.id,
+            "last_move": millis(self.last_move_at),
+            "expiration": expiration,
+        })
+    }
+
+    fn last_move(&self) -> [i32; 2] {
+        self.moves.last().copied().unwrap_or([-1, -1])
+    }
+
+    /// An entry of `/api/v1/me/games`
+    pub fn list_json(&self) -> Value {
+        let finished = self.phase == PHASE_FINISHED;
+        let lost = |color: Color| match self.winner {
+            Some(winner) => winner != self.player(color).id,
+            // online-go sets both while the game is running
+            None => !finished,
+        };
+        json!({
+            "id": self.id,
+            "name": self.name,
+            "width": self.width,
+            "height": self.height,
+            "players": {
+                "black": player_json(&self.black),
+                "white": player_json(&self.white),
+            },
+            "started": "2024-01-01T00:00:00Z",
+            "ended": if finished { Value::from("2024-01-02T00:00:00Z") } else { Value::Null },
+            "black_lost": lost(Color::Black),
+            "white_lost": lost(Color::White),
+            "rules": self.rules,
+            "ranked": self.ranked,
+            "time_per_move": self.time_per_move,
+        })
+    }
+
+    /// `/termination-api/game/<id>/state`
+    pub fn state_json(&self) -> Value {
+        let [x, y] = self.last_move();
+        json!({
+            "move_number": self.moves.len(),
+            "player_to_move": self.player(self.to_move()).id,
+            "phase": self.phase,
+            "board": self.board,
+            "outcome": self.outcome,
+            "removal": self.removal,
+            "last_move": { "x": x, "y": y },
+        })
+    }
+
+    /// `/api/v1/games/<id>`
+    pub fn record_json(&self) -> Value {
+        let moves: Vec<Value> = self
+            .moves
+            .iter()
+            .map(|[x, y]| json!([x, y, 1000]))
+            .collect();
+        json!({
+            "id": self.id,
+            "gamedata": {
+                "width": self.width,
+                "height": self.height,
+                "komi": self.komi,
+                "rules": self.rules,
+                "handicap": 0,
+                "initial_player": "black",
+                "initial_state": { "black": "", "white": "" },
+                "moves": moves,
+                "players": {
+                    "black": { "id": self.black.id, "username": self.black.username },
+                    "white": { "id": self.white.id, "username": self.white.username },
+                },
+                "outcome": self.outcome,
+                "winner": self.winner,
+                "clock": self.clock_json(),
+            },
+        })
+    }
+
+    /// The socket.io `game/<id>/move` payload for the last move
+    pub fn move_event_json(&self) -> Value {
+        let [x, y] = self.last_move();
+        json!({
+            "game_id": self.id,
+            "move_number": self.moves.len(),
+            "move": [x, y, 1000],
+        })
+    }
+}
+
+pub fn player_json(account: &Account) -> Value {
+    json!({
+        "id": account.id,
+        "username": account.username,
+        "ranking": account.ranking,
+    })
+}
+
+/// online-go's letter coordinates, each pair is column then row, `..` is a pass
+pub fn points_from_letters(letters: &str) -> Option<Vec<(i32, i32)>> {
+    let bytes = letters.as_bytes();
+    if !bytes.len().is_multiple_of(2) {
+        return None;
+    }
+    bytes
+        .chunks(2)
+        .map(|pair| match (pair[0], pair[1]) {
+            (b'.', b'.') => Some((-1, -1)),
+            (x @ b'a'..=b'z', y @ b'a'..=b'z') => Some(((x - b'a') as i32, (y - b'a') as i32)),
+            _ => None,
+        })
+        .collect()
+}
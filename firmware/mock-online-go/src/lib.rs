@@ -0,0 +1,92 @@
+//! A local stand-in for online-go.com, serves the endpoints the board firmware uses over plain
+//! http so the api can be run against it on a computer (or the board on the same network).
+//!
+//! The binary serves a scenario file, tests can start a [`MockServer`] on a free port instead
+
+mod api;
+mod game;
+mod http;
+pub mod scenario;
+mod socket;
+mod state;
+mod websocket;
+
+use anyhow::Result;
+use http::Request;
+use scenario::{Scenario, Step};
+use state::Shared;
+use std::io::BufReader;
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::Arc;
+use std::thread;
+
+/// A scenario bound to a port, nothing is served until [`MockServer::run`]
+pub struct MockServer {
+    listener: TcpListener,
+    shared: Arc<Shared>,
+    script: Vec<Step>,
+}
+
+impl MockServer {
+    /// Port 0 picks a free port, see [`MockServer::port`]. The steps of the script before its
+    /// first wait are applied before this returns, so they are in place for the first request
+    pub fn bind(scenario: Scenario, address: impl ToSocketAddrs) -> Result<Self> {
+        let (state, mut script) = scenario.into_state()?;
+        let shared = Arc::new(Shared::new(state));
+        let waits_at = script.iter().position(Step::waits).unwrap_or(script.len());
+        for step in script.drain(..waits_at) {
+            scenario::apply(step, &shared);
+        }
+        Ok(Self {
+            listener: TcpListener::bind(address)?,
+            shared,
+            script,
+        })
+    }
+
+    pub fn port(&self) -> Result<u16> {
+        Ok(self.listener.local_addr()?.port())
+    }
+
+    /// Starts the rest of the script and serves every connection on its own thread, never returns unless
+    /// the listener fails
+    pub fn run(self) -> Result<()> {
+        let scenario_shared = self.shared.clone();
+        thread::spawn(move || scenario::run(self.script, &scenario_shared));
+
+        for stream in self.listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(e) => {
+                    println!("Failed to accept a connection: {e}");
+                    continue;
+                }
+            };
+            let shared = self.shared.clone();
+            thread::spawn(move || {
+                if let Err(e) = serve(stream, &shared) {
+                    println!("Connection failed: {e:?}");
+                }
+            });
+        }
+        Ok(())
+    }
+
+    /// Runs the server in the background, for tests
+    pub fn spawn(self) -> Result<u16> {
+        let port = self.port()?;
+        thread::spawn(move || self.run());
+        Ok(port)
+    }
+}
+
+fn serve(mut stream: TcpStream, shared: &Shared) -> Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let request = Request::read(&mut reader)?;
+    if socket::is_upgrade(&request) {
+        return socket::serve(stream, &request, shared);
+    }
+    let response = api::handle(&request, shared);
+    println!("{} {} -> {}", request.method, request.path, response.status);
+    response.write(&mut stream)
+}
@@ -0,0 +1,35 @@
+//! `cargo run -- scenarios/basic.json --port 8000`, then build the firmware with
+//! `ONLINE_GO_BASE_URL=http://<this computer>:8000` and
+//! `ONLINE_GO_SOCKET_URL=ws://<this computer>:8000/socket.io/?EIO=3&transport=websocket`
+
+use anyhow::{anyhow, Result};
+use mock_online_go::scenario::Scenario;
+use mock_online_go::MockServer;
+
+const DEFAULT_PORT: u16 = 8000;
+
+fn main() -> Result<()> {
+    let mut scenario_path = None;
+    let mut port = DEFAULT_PORT;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--port" => {
+                port = args
+                    .next()
+                    .ok_or_else(|| anyhow!("--port needs a value"))?
+                    .parse()?
+            }
+            path => scenario_path = Some(path.to_string()),
+        }
+    }
+    let scenario_path =
+        scenario_path.ok_or_else(|| anyhow!("Usage: mock-online-go <scenario.json> [--port N]"))?;
+
+    let server = MockServer::bind(Scenario::load(&scenario_path)?, ("0.0.0.0", port))?;
+    println!(
+        "mock online-go listening on port {} with {scenario_path}",
+        server.port()?
+    );
+    server.run()
+}
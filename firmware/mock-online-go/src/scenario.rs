@@ -0,0 +1,180 @@
+//! Scenarios are json files describing the account, its games and a script of steps run one
+//! after the other while the server is up, the steps before the first wait are in place before
+//! the first request, ie
+//!
+//! ```json
+//! {
+//!   "account": { "id": 1, "username": "board", "password": "hunter2" },
+//!   "token_lifetime_secs": 600,
+//!   "games": [
+//!     { "id": 10, "width": 9, "height": 9, "opponent": { "id": 2, "username": "rival" } }
+//!   ],
+//!   "script": [
+//!     { "step": "wait_for_player_move", "game_id": 10 },
+//!     { "step": "opponent_move", "game_id": 10, "x": 2, "y": 6 },
+//!     { "step": "expire_tokens" },
+//!     { "step": "fail", "path": "/api/v1/me", "status": 503, "retry_after": 2 }
+//!   ]
+//! }
+//! ```
+
+use crate::game::{Account, Game, GameSpec};
+use crate::state::{Failure, Shared, State};
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use std::thread;
+use std::time::Duration;
+
+#[derive(Deserialize, Debug)]
+pub struct Scenario {
+    /// the board's account, the firmware logs in with its username and password
+    pub account: Account,
+    #[serde(default = "default_token_lifetime")]
+    pub token_lifetime_secs: u64,
+    #[serde(default)]
+    pub games: Vec<GameSpec>,
+    #[serde(default)]
+    pub script: Vec<Step>,
+}
+
+fn default_token_lifetime() -> u64 {
+    // online-go's tokens last 30 days
+    30 * 24 * 60 * 60
+}
+
+fn default_count() -> u32 {
+    1
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(tag = "step", rename_all = "snake_case")]
+pub enum Step {
+    Wait {
+        secs: f64,
+    },
+    /// waits until the board plays (or passes) its next move in the game
+    WaitForPlayerMove {
+        game_id: i64,
+    },
+    /// the opponent plays `x, y` (x is the column), `-1, -1` passes
+    OpponentMove {
+        game_id: i64,
+        x: i32,
+        y: i32,
+    },
+    /// every access token is refused from now on, refresh tokens still work
+    ExpireTokens,
+    /// refresh tokens are refused too, only the password grant works
+    RevokeRefreshTokens,
+    /// the next `count` requests to paths starting with `path` get `status`
+    Fail {
+        path: String,
+        status: u16,
+        #[serde(default)]
+        retry_after: Option<u64>,
+        #[serde(default = "default_count")]
+        count: u32,
+    },
+    /// ie `stone removal` or `finished`
+    SetPhase {
+        game_id: i64,
+        phase: String,
+    },
+}
+
+impl Scenario {
+    pub fn load(path: &str) -> Result<Self> {
+        let json = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!(e).context(format!("Failed to read scenario {path}")))?;
+        Ok(serde_json::from_str(&json)?)
+    }
+
+    /// The state the server starts with, the script is returned to be run with [`run`]
+    pub fn into_state(self) -> Result<(State, Vec<Step>)> {
+        let games = self
+            .games
+            .into_iter()
+            .map(|spec| {
+                let id = spec.id;
+                Game::new(spec, &self.account)
+                    .map_err(|e| anyhow!("Failed to set up game {id}: {e}"))
+            })
+            .collect::<Result<_>>()?;
+        let state = State::new(
+            self.account,
+            Duration::from_secs(self.token_lifetime_secs),
+            games,
+        );
+        Ok((state, self.script))
+    }
+}
+
+impl Step {
+    /// true for the steps that hold up the rest of the script
+    pub fn waits(&self) -> bool {
+        matches!(self, Step::Wait { .. } | Step::WaitForPlayerMove { .. })
+    }
+}
+
+pub fn run(script: Vec<Step>, shared: &Shared) {
+    for step in script {
+        apply(step, shared);
+    }
+    println!("scenario: done");
+}
+
+/// Runs a single step, blocks while it [waits](Step::waits)
+pub fn apply(step: Step, shared: &Shared) {
+    println!("scenario: {step:?}");
+    match step {
+        Step::Wait { secs } => thread::sleep(Duration::from_secs_f64(secs)),
+        Step::WaitForPlayerMove { game_id } => {
+            let played = shared.lock().player_moves.get(&game_id).copied();
+            let played = played.unwrap_or_default();
+            shared.wait_until(|state| {
+                state
+                    .player_moves
+                    .get(&game_id)
+                    .copied()
+                    .unwrap_or_default()
+                    > played
+            });
+        }
+        Step::OpponentMove { game_id, x, y } => {
+            let mut state = shared.lock();
+            let account_id = state.account.id;
+            let opponent = state
+                .games
+                .iter()
+                .find(|game| game.id == game_id)
+                .and_then(|game| game.color_of(account_id))
+                .map(|color| color.opponent());
+            let result = match opponent {
+                Some(opponent) => state.play(game_id, opponent, x, y),
+                None => Err(format!("No game {game_id}")),
+            };
+            if let Err(message) = result {
+                println!("scenario: opponent move failed: {message}");
+            }
+        }
+        Step::ExpireTokens => shared.lock().expire_access_tokens(),
+        Step::RevokeRefreshTokens => shared.lock().revoke_refresh_tokens(),
+        Step::Fail {
+            path,
+            status,
+            retry_after,
+            count,
+        } => shared.lock().failures.push(Failure {
+            path,
+            status,
+            retry_after,
+            remaining: count,
+        }),
+        Step::SetPhase { game_id, phase } => {
+            if let Err(message) = shared.lock().set_phase(game_id, &phase) {
+                println!("scenario: {message}");
+            }
+        }
+    }
+    shared.notify();
+}
@@ -0,0 +1,159 @@
+//! online-go's realtime socket: socket.io over Engine.IO v3, the client pings and the server
+//! answers. Only `game/connect` and `game/disconnect` are understood, subscribed sockets get
+//! the `game/<id>/move` and `game/<id>/phase` events
+
+use crate::http::{Request, Response};
+use crate::state::Shared;
+use crate::websocket::{self, Message};
+use anyhow::{bail, Result};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::net::TcpStream;
+use std::sync::{Arc, Mutex};
+
+const PING_INTERVAL_MS: u64 = 25_000;
+const PING_TIMEOUT_MS: u64 = 60_000;
+
+struct Subscriber {
+    writer: Arc<Mutex<TcpStream>>,
+    games: Vec<i64>,
+}
+
+/// The open sockets and the games they are subscribed to
+#[derive(Default)]
+pub struct Subscribers {
+    next_id: u64,
+    sockets: HashMap<u64, Subscriber>,
+}
+
+impl Subscribers {
+    fn add(&mut self, writer: Arc<Mutex<TcpStream>>) -> u64 {
+        self.next_id += 1;
+        self.sockets.insert(
+            self.next_id,
+            Subscriber {
+                writer,
+                games: Vec::new(),
+            },
+        );
+        self.next_id
+    }
+
+    fn remove(&mut self, id: u64) {
+        self.sockets.remove(&id);
+    }
+
+    fn subscribe(&mut self, id: u64, game_id: i64, subscribed: bool) {
+        if let Some(subscriber) = self.sockets.get_mut(&id) {
+            subscriber.games.retain(|game| *game != game_id);
+            if subscribed {
+                subscriber.games.push(game_id);
+            }
+        }
+    }
+
+    /// Sends an event to every socket subscribed to `game_id`, sockets that fail are dropped
+    pub fn send(&mut self, game_id: i64, name: &str, data: &Value) {
+        let packet = format!("42{}", json!([name, data]));
+        self.sockets.retain(|id, subscriber| {
+            if !subscriber.games.contains(&game_id) {
+                return true;
+            }
+            let mut writer = subscriber.writer.lock().unwrap_or_else(|e| e.into_inner());
+            match websocket::write_text(&mut *writer, &packet) {
+                Ok(()) => {
+                    println!("socket {id} <- {packet}");
+                    true
+                }
+                Err(e) => {
+                    println!("socket {id} dropped: {e}");
+                    false
+                }
+            }
+        });
+    }
+}
+
+#[derive(Deserialize)]
+struct GameConnect {
+    game_id: i64,
+}
+
+pub fn is_upgrade(request: &Request) -> bool {
+    request
+        .headers
+        .get("upgrade")
+        .is_some_and(|upgrade| upgrade.eq_ignore_ascii_case("websocket"))
+}
+
+/// Completes the websocket upgrade and serves the socket until it closes
+pub fn serve(mut stream: TcpStream, request: &Request, shared: &Shared) -> Result<()> {
+    let Some(key) = request.headers.get("sec-websocket-key") else {
+        return Response::json(400, &json!({ "error": "Missing Sec-WebSocket-Key" }))
+            .write(&mut stream);
+    };
+    let upgrade = format!(
+        "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+         Sec-WebSocket-Accept: {}\r\n\r\n",
+        websocket::accept_key(key)
+    );
+    std::io::Write::write_all(&mut stream, upgrade.as_bytes())?;
+
+    let writer = Arc::new(Mutex::new(stream.try_clone()?));
+    let send = |text: &str| -> Result<()> {
+        let mut writer = writer.lock().unwrap_or_else(|e| e.into_inner());
+        websocket::write_text(&mut *writer, text)
+    };
+    let handshake = json!({
+        "sid": "mock",
+        "upgrades": [],
+        "pingInterval": PING_INTERVAL_MS,
+        "pingTimeout": PING_TIMEOUT_MS,
+    });
+    send(&format!("0{handshake}"))?;
+    send("40")?;
+
+    let id = shared.lock().subscribers.add(writer.clone());
+    println!("socket {id} connected");
+    let result = session(&mut stream, id, shared, send);
+    shared.lock().subscribers.remove(id);
+    println!("socket {id} closed");
+    result
+}
+
+fn session(
+    stream: &mut TcpStream,
+    id: u64,
+    shared: &Shared,
+    send: impl Fn(&str) -> Result<()>,
+) -> Result<()> {
+    loop {
+        let text = match websocket::read_message(stream)? {
+            Message::Close => return Ok(()),
+            Message::Text(text) => text,
+        };
+        println!("socket {id} -> {text}");
+        if text == "2" {
+            send("3")?;
+        } else if text == "41" || text == "1" {
+            return Ok(());
+        } else if let Some(event) = text.strip_prefix("42") {
+            let Value::Array(event) = serde_json::from_str::<Value>(event)? else {
+                bail!("Socket.IO event {event} is not an array");
+            };
+            let name = event.first().and_then(Value::as_str).unwrap_or_default();
+            let data = event.get(1).cloned().unwrap_or(Value::Null);
+            let subscribed = match name {
+                "game/connect" => true,
+                "game/disconnect" => false,
+                _ => continue,
+            };
+            let game = serde_json::from_value::<GameConnect>(data)?;
+            shared
+                .lock()
+                .subscribers
+                .subscribe(id, game.game_id, subscribed);
+        }
+    }
+}
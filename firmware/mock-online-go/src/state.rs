@@ -0,0 +1,161 @@
+//! Everything the mock knows, shared by the connections and the scenario
+
+use crate::game::{Account, Color, Game};
+use crate::socket::Subscribers;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Condvar, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+/// A response sent instead of the real one, set up by the scenario
+#[derive(Clone, Debug)]
+pub struct Failure {
+    /// requests whose path starts with this fail
+    pub path: String,
+    pub status: u16,
+    pub retry_after: Option<u64>,
+    /// how many more requests fail
+    pub remaining: u32,
+}
+
+#[derive(Default)]
+pub struct Tokens {
+    issued: u32,
+    access: HashMap<String, Instant>,
+    refresh: HashSet<String>,
+}
+
+pub struct State {
+    pub account: Account,
+    pub token_lifetime: Duration,
+    pub tokens: Tokens,
+    pub games: Vec<Game>,
+    pub failures: Vec<Failure>,
+    pub subscribers: Subscribers,
+    /// moves the board's account played, per game, for `wait_for_player_move`
+    pub player_moves: HashMap<i64, usize>,
+}
+
+impl State {
+    pub fn new(account: Account, token_lifetime: Duration, games: Vec<Game>) -> Self {
+        Self {
+            account,
+            token_lifetime,
+            tokens: Tokens::default(),
+            games,
+            failures: Vec::new(),
+            subscribers: Subscribers::default(),
+            player_moves: HashMap::new(),
+        }
+    }
+
+    /// A new access and refresh token pair
+    pub fn issue_tokens(&mut self) -> (String, String) {
+        self.tokens.issued += 1;
+        let access = format!("mock-access-{}", self.tokens.issued);
+        let refresh = format!("mock-refresh-{}", self.tokens.issued);
+        self.tokens
+            .access
+            .insert(access.clone(), Instant::now() + self.token_lifetime);
+        self.tokens.refresh.insert(refresh.clone());
+        (access, refresh)
+    }
+
+    pub fn is_valid_access_token(&self, token: &str) -> bool {
+        self.tokens
+            .access
+            .get(token)
+            .is_some_and(|expires| *expires > Instant::now())
+    }
+
+    /// Refresh tokens can only be used once, like on online-go
+    pub fn use_refresh_token(&mut self, token: &str) -> bool {
+        self.tokens.refresh.remove(token)
+    }
+
+    pub fn expire_access_tokens(&mut self) {
+        self.tokens.access.clear();
+    }
+
+    pub fn revoke_refresh_tokens(&mut self) {
+        self.tokens.refresh.clear();
+    }
+
+    /// The injected failure for `path`, if any is left
+    pub fn take_failure(&mut self, path: &str) -> Option<Failure> {
+        let index = self
+            .failures
+            .iter()
+            .position(|failure| path.starts_with(&failure.path))?;
+        let failure = self.failures[index].clone();
+        if failure.remaining <= 1 {
+            self.failures.remove(index);
+        } else {
+            self.failures[index].remaining -= 1;
+        }
+        Some(failure)
+    }
+
+    pub fn game_mut(&mut self, game_id: i64) -> Option<&mut Game> {
+        self.games.iter_mut().find(|game| game.id == game_id)
+    }
+
+    /// Plays a move and tells the sockets subscribed to the game
+    pub fn play(&mut self, game_id: i64, color: Color, x: i32, y: i32) -> Result<(), String> {
+        let game = self
+            .game_mut(game_id)
+            .ok_or_else(|| format!("No game {game_id}"))?;
+        let phase = game.phase.clone();
+        game.play(color, x, y)?;
+        let event = game.move_event_json();
+        let new_phase = (game.phase != phase).then(|| game.phase.clone());
+        self.subscribers
+            .send(game_id, &format!("game/{game_id}/move"), &event);
+        if let Some(phase) = new_phase {
+            self.subscribers
+                .send(game_id, &format!("game/{game_id}/phase"), &phase.into());
+        }
+        Ok(())
+    }
+
+    pub fn set_phase(&mut self, game_id: i64, phase: &str) -> Result<(), String> {
+        let game = self
+            .game_mut(game_id)
+            .ok_or_else(|| format!("No game {game_id}"))?;
+        game.phase = phase.to_string();
+        self.subscribers
+            .send(game_id, &format!("game/{game_id}/phase"), &phase.into());
+        Ok(())
+    }
+}
+
+/// The state and a condvar notified on every change, the scenario waits on it
+pub struct Shared {
+    state: Mutex<State>,
+    changed: Condvar,
+}
+
+impl Shared {
+    pub fn new(state: State) -> Self {
+        Self {
+            state: Mutex::new(state),
+            changed: Condvar::new(),
+        }
+    }
+
+    pub fn lock(&self) -> MutexGuard<'_, State> {
+        // a panicking connection thread does not make the state unusable
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn notify(&self) {
+        self.changed.notify_all();
+    }
+
+    /// Blocks until `done` is true
+    pub fn wait_until(&self, mut done: impl FnMut(&State) -> bool) {
+        let mut state = self.lock();
+        while !done(&state) {
+            state = self.changed.wait(state).unwrap_or_else(|e| e.into_inner());
+        }
+    }
+}
@@ -0,0 +1,170 @@
+//! A minimal websocket server side (RFC 6455): the upgrade handshake and text frames.
+//! SHA-1 and base64 are only needed for `Sec-WebSocket-Accept`, so they are done here
+//! instead of pulling in crates for them
+
+use anyhow::{bail, Result};
+use std::io::{Read, Write};
+
+const ACCEPT_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+const OPCODE_CONTINUATION: u8 = 0x0;
+const OPCODE_TEXT: u8 = 0x1;
+const OPCODE_BINARY: u8 = 0x2;
+const OPCODE_CLOSE: u8 = 0x8;
+const OPCODE_PING: u8 = 0x9;
+const OPCODE_PONG: u8 = 0xA;
+
+/// The `Sec-WebSocket-Accept` answer to a `Sec-WebSocket-Key`
+pub fn accept_key(key: &str) -> String {
+    base64(&sha1(format!("{key}{ACCEPT_GUID}").as_bytes()))
+}
+
+fn sha1(message: &[u8]) -> [u8; 20] {
+    let mut h: [u32; 5] = [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476, 0xC3D2E1F0];
+
+    let mut padded = message.to_vec();
+    padded.push(0x80);
+    while padded.len() % 64 != 56 {
+        padded.push(0);
+    }
+    padded.extend_from_slice(&(message.len() as u64 * 8).to_be_bytes());
+
+    for block in padded.chunks(64) {
+        let mut w = [0u32; 80];
+        for (i, word) in block.chunks(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e] = h;
+        for (i, word) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5A827999),
+                20..=39 => (b ^ c ^ d, 0x6ED9EBA1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1BBCDC),
+                _ => (b ^ c ^ d, 0xCA62C1D6),
+            };
+            let temp = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(*word);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+        for (h, value) in h.iter_mut().zip([a, b, c, d, e]) {
+            *h = h.wrapping_add(value);
+        }
+    }
+
+    let mut digest = [0u8; 20];
+    for (bytes, h) in digest.chunks_mut(4).zip(h) {
+        bytes.copy_from_slice(&h.to_be_bytes());
+    }
+    digest
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = (chunk[0] as u32) << 16
+            | (*chunk.get(1).unwrap_or(&0) as u32) << 8
+            | *chunk.get(2).unwrap_or(&0) as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(n >> (18 - 6 * i) & 0x3F) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+/// What the client sent
+pub enum Message {
+    Text(String),
+    Close,
+}
+
+/// Reads frames until a whole text message or a close arrives, pings are answered here
+pub fn read_message(stream: &mut (impl Read + Write)) -> Result<Message> {
+    let mut message = Vec::new();
+    loop {
+        let mut head = [0u8; 2];
+        stream.read_exact(&mut head)?;
+        let fin = head[0] & 0x80 != 0;
+        let opcode = head[0] & 0x0F;
+        let masked = head[1] & 0x80 != 0;
+        let length = match head[1] & 0x7F {
+            126 => {
+                let mut length = [0u8; 2];
+                stream.read_exact(&mut length)?;
+                u16::from_be_bytes(length) as usize
+            }
+            127 => {
+                let mut length = [0u8; 8];
+                stream.read_exact(&mut length)?;
+                u64::from_be_bytes(length) as usize
+            }
+            length => length as usize,
+        };
+        let mut mask = [0u8; 4];
+        if masked {
+            stream.read_exact(&mut mask)?;
+        }
+        let mut payload = vec![0u8; length];
+        stream.read_exact(&mut payload)?;
+        if masked {
+            for (i, byte) in payload.iter_mut().enumerate() {
+                *byte ^= mask[i % 4];
+            }
+        }
+
+        match opcode {
+            OPCODE_CLOSE => {
+                write_frame(stream, OPCODE_CLOSE, &[])?;
+                return Ok(Message::Close);
+            }
+            OPCODE_PING => write_frame(stream, OPCODE_PONG, &payload)?,
+            OPCODE_PONG => {}
+            OPCODE_TEXT | OPCODE_BINARY | OPCODE_CONTINUATION => {
+                message.extend_from_slice(&payload);
+                if fin {
+                    return Ok(Message::Text(String::from_utf8(message)?));
+                }
+            }
+            opcode => bail!("Unknown websocket opcode {opcode}"),
+        }
+    }
+}
+
+pub fn write_text(stream: &mut impl Write, text: &str) -> Result<()> {
+    write_frame(stream, OPCODE_TEXT, text.as_bytes())
+}
+
+/// Server frames are never masked
+fn write_frame(stream: &mut impl Write, opcode: u8, payload: &[u8]) -> Result<()> {
+    let mut frame = vec![0x80 | opcode];
+    match payload.len() {
+        length @ 0..=125 => frame.push(length as u8),
+        length @ 126..=0xFFFF => {
+            frame.push(126);
+            frame.extend_from_slice(&(length as u16).to_be_bytes());
+        }
+        length => {
+            frame.push(127);
+            frame.extend_from_slice(&(length as u64).to_be_bytes());
+        }
+    }
+    frame.extend_from_slice(payload);
+    stream.write_all(&frame)?;
+    stream.flush()?;
+    Ok(())
+}
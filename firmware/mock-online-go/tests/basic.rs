@@ -0,0 +1,275 @@
+//! Boots the mock with `scenarios/basic.json` and talks to it the way the firmware does
+
+use mock_online_go::scenario::Scenario;
+use mock_online_go::MockServer;
+use serde_json::{json, Value};
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+fn start() -> u16 {
+    let scenario = Scenario::load("scenarios/basic.json").unwrap();
+    MockServer::bind(scenario, "127.0.0.1:0")
+        .unwrap()
+        .spawn()
+        .unwrap()
+}
+
+fn connect(port: u16) -> TcpStream {
+    let stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+    stream.set_read_timeout(Some(TIMEOUT)).unwrap();
+    stream
+}
+
+/// One request per connection, like the firmware's host transport
+fn request(port: u16, method: &str, path: &str, token: Option<&str>, body: &str) -> (u16, Value) {
+    let mut stream = connect(port);
+    let mut head = format!(
+        "{method} {path} HTTP/1.1\r\nHost: 127.0.0.1:{port}\r\nConnection: close\r\n\
+         Content-Length: {}\r\n",
+        body.len()
+    );
+    if !body.is_empty() {
+        head.push_str("Content-Type: application/x-www-form-urlencoded\r\n");
+    }
+    if let Some(token) = token {
+        head.push_str(&format!("Authorization: Bearer {token}\r\n"));
+    }
+    head.push_str("\r\n");
+    stream.write_all(head.as_bytes()).unwrap();
+    stream.write_all(body.as_bytes()).unwrap();
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    let (head, body) = response.split_once("\r\n\r\n").unwrap();
+    let status = head.split_whitespace().nth(1).unwrap().parse().unwrap();
+    (status, serde_json::from_str(body).unwrap())
+}
+
+fn log_in(port: u16) -> Value {
+    let (status, body) = request(
+        port,
+        "POST",
+        "/oauth2/token/",
+        None,
+        "client_id=board&grant_type=password&username=board&password=board-password",
+    );
+    assert_eq!(status, 200, "{body}");
+    body
+}
+
+fn access_token(port: u16) -> String {
+    log_in(port)["access_token"].as_str().unwrap().to_string()
+}
+
+#[test]
+fn password_and_refresh_token_grants() {
+    let port = start();
+    let (status, body) = request(
+        port,
+        "POST",
+        "/oauth2/token/",
+        None,
+        "grant_type=password&username=board&password=wrong",
+    );
+    assert_eq!(status, 401);
+    assert_eq!(body["error"], "invalid_grant");
+
+    let tokens = log_in(port);
+    assert!(tokens["expires_in"].as_u64().unwrap() > 0);
+    let refresh = format!(
+        "grant_type=refresh_token&refresh_token={}",
+        tokens["refresh_token"].as_str().unwrap()
+    );
+    let (status, renewed) = request(port, "POST", "/oauth2/token/", None, &refresh);
+    assert_eq!(status, 200);
+    assert_ne!(renewed["access_token"], tokens["access_token"]);
+    // refresh tokens only work once
+    let (status, _) = request(port, "POST", "/oauth2/token/", None, &refresh);
+    assert_eq!(status, 401);
+
+    let token = renewed["access_token"].as_str().unwrap();
+    let (status, me) = request(port, "GET", "/api/v1/me", Some(token), "");
+    assert_eq!(status, 200);
+    assert_eq!(me["username"], "board");
+}
+
+#[test]
+fn game_list_needs_a_token() {
+    let port = start();
+    let path = "/api/v1/me/games?ended__isnull=true&page_size=50";
+    let (status, _) = request(port, "GET", path, None, "");
+    assert_eq!(status, 401);
+    let (status, _) = request(port, "GET", path, Some("made-up"), "");
+    assert_eq!(status, 401);
+
+    let (status, list) = request(port, "GET", path, Some(&access_token(port)), "");
+    assert_eq!(status, 200);
+    assert_eq!(list["count"], 1);
+    assert_eq!(list["next"], Value::Null);
+    let game = &list["results"][0];
+    assert_eq!(game["id"], 100);
+    assert_eq!(game["width"], 9);
+    assert_eq!(game["players"]["black"]["username"], "board");
+    assert_eq!(game["players"]["white"]["username"], "rival");
+}
+
+#[test]
+fn moves_are_played_and_answered() {
+    let port = start();
+    let token = access_token(port);
+    let (status, record) = request(port, "GET", "/api/v1/games/100", Some(&token), "");
+    assert_eq!(status, 200);
+    assert_eq!(record["gamedata"]["moves"].as_array().unwrap().len(), 2);
+    assert_eq!(record["gamedata"]["clock"]["current_player"], 1);
+
+    let (status, _) = request(
+        port,
+        "POST",
+        "/api/v1/games/100/move/",
+        Some(&token),
+        "move=ee",
+    );
+    assert_eq!(status, 200);
+    let (status, record) = request(port, "GET", "/api/v1/games/100", Some(&token), "");
+    assert_eq!(status, 200);
+    let moves = record["gamedata"]["moves"].as_array().unwrap();
+    assert_eq!(moves.len(), 4);
+    assert_eq!(moves[2], json!([4, 4, 1000]));
+    // the auto reply takes the first empty point
+    assert_eq!(moves[3], json!([0, 0, 1000]));
+
+    let (status, body) = request(
+        port,
+        "POST",
+        "/api/v1/games/100/move/",
+        Some(&token),
+        "move=ee",
+    );
+    assert_eq!(status, 400, "{body}");
+    let (status, _) = request(
+        port,
+        "POST",
+        "/api/v1/games/999/move/",
+        Some(&token),
+        "move=aa",
+    );
+    assert_eq!(status, 404);
+
+    let (status, state) = request(
+        port,
+        "GET",
+        "/termination-api/game/100/state",
+        Some(&token),
+        "",
+    );
+    assert_eq!(status, 200);
+    assert_eq!(state["move_number"], 4);
+    assert_eq!(state["board"][4][4], 1);
+    assert_eq!(state["last_move"]["x"], 0);
+}
+
+#[test]
+fn steps_before_the_first_wait_apply_to_the_first_request() {
+    let mut scenario: Value =
+        serde_json::from_str(&std::fs::read_to_string("scenarios/basic.json").unwrap()).unwrap();
+    scenario["script"] = json!([
+        { "step": "fail", "path": "/api/v1/me", "status": 503 },
+        { "step": "wait", "secs": 60 },
+        { "step": "fail", "path": "/api/v1/me", "status": 500 }
+    ]);
+    let scenario: Scenario = serde_json::from_value(scenario).unwrap();
+    let port = MockServer::bind(scenario, "127.0.0.1:0")
+        .unwrap()
+        .spawn()
+        .unwrap();
+    let token = access_token(port);
+    let (status, _) = request(port, "GET", "/api/v1/me", Some(&token), "");
+    assert_eq!(status, 503);
+    // the failure after the wait is not queued yet
+    let (status, _) = request(port, "GET", "/api/v1/me", Some(&token), "");
+    assert_eq!(status, 200);
+}
+
+/// A text frame from the server, those are never masked
+fn read_text(stream: &mut TcpStream) -> String {
+    let mut head = [0u8; 2];
+    stream.read_exact(&mut head).unwrap();
+    assert_eq!(head[0] & 0x0F, 0x1, "not a text frame");
+    let length = match head[1] & 0x7F {
+        126 => {
+            let mut length = [0u8; 2];
+            stream.read_exact(&mut length).unwrap();
+            u16::from_be_bytes(length) as usize
+        }
+        length => length as usize,
+    };
+    let mut payload = vec![0u8; length];
+    stream.read_exact(&mut payload).unwrap();
+    String::from_utf8(payload).unwrap()
+}
+
+/// Client frames are masked
+fn write_text(stream: &mut TcpStream, text: &str) {
+    let mask = [0x12, 0x34, 0x56, 0x78];
+    assert!(text.len() < 126);
+    let mut frame = vec![0x81, 0x80 | text.len() as u8];
+    frame.extend_from_slice(&mask);
+    frame.extend(text.bytes().enumerate().map(|(i, byte)| byte ^ mask[i % 4]));
+    stream.write_all(&frame).unwrap();
+}
+
+#[test]
+fn socket_sends_the_moves_of_subscribed_games() {
+    let port = start();
+    let mut socket = connect(port);
+    socket
+        .write_all(
+            format!(
+                "GET /socket.io/?EIO=3&transport=websocket HTTP/1.1\r\nHost: 127.0.0.1:{port}\r\n\
+                 Upgrade: websocket\r\nConnection: Upgrade\r\n\
+                 Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 13\r\n\r\n"
+            )
+            .as_bytes(),
+        )
+        .unwrap();
+    let mut head = Vec::new();
+    while !head.ends_with(b"\r\n\r\n") {
+        let mut byte = [0u8];
+        socket.read_exact(&mut byte).unwrap();
+        head.push(byte[0]);
+    }
+    let head = String::from_utf8(head).unwrap();
+    assert!(head.starts_with("HTTP/1.1 101"), "{head}");
+    // the example key of RFC 6455
+    assert!(head.contains("Sec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo="));
+
+    let open = read_text(&mut socket);
+    assert!(open.starts_with("0{"), "{open}");
+    assert_eq!(read_text(&mut socket), "40");
+    write_text(&mut socket, "2");
+    assert_eq!(read_text(&mut socket), "3");
+    write_text(&mut socket, r#"42["game/connect",{"game_id":100}]"#);
+    // the subscription is handled before the ping answer
+    write_text(&mut socket, "2");
+    assert_eq!(read_text(&mut socket), "3");
+
+    let token = access_token(port);
+    let (status, _) = request(
+        port,
+        "POST",
+        "/api/v1/games/100/move/",
+        Some(&token),
+        "move=ee",
+    );
+    assert_eq!(status, 200);
+    for (move_number, point) in [(3, [4, 4]), (4, [0, 0])] {
+        let event = read_text(&mut socket);
+        let event: Value = serde_json::from_str(event.strip_prefix("42").unwrap()).unwrap();
+        assert_eq!(event[0], "game/100/move");
+        assert_eq!(event[1]["move_number"], move_number);
+        assert_eq!(event[1]["move"], json!([point[0], point[1], 1000]));
+    }
+}